@@ -3,15 +3,30 @@ extern crate weezl;
 
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
 use std::fs;
-use weezl::{decode::Decoder, BitOrder, LzwStatus};
+use weezl::{
+    decode::{Configuration, TableStrategy},
+    BitOrder, LzwStatus,
+};
 
 pub fn criterion_benchmark(c: &mut Criterion, file: &str) {
+    for &strategy in &[
+        TableStrategy::Classic,
+        TableStrategy::Chunked,
+        TableStrategy::Streaming,
+    ] {
+        bench_strategy(c, file, strategy);
+    }
+}
+
+fn bench_strategy(c: &mut Criterion, file: &str, strategy: TableStrategy) {
     let data = fs::read(file).expect("Benchmark input not found");
-    let mut group = c.benchmark_group("msb-8");
+    let mut group = c.benchmark_group(format!("msb-8-{:?}", strategy));
     let id = BenchmarkId::new(file, data.len());
     let mut outbuf = vec![0; 1 << 26]; // 64MB, what wuff uses..
     let mut decode_once = |data: &[u8]| {
-        let mut decoder = Decoder::new(BitOrder::Msb, 8);
+        let mut decoder = Configuration::new(BitOrder::Msb, 8)
+            .with_table_strategy(strategy)
+            .build();
         let mut written = 0;
         let outbuf = outbuf.as_mut_slice();
         let mut data = data;
@@ -23,31 +23,54 @@ fn main() {
     } else {
         flags.min_code
     };
+    // The default maximum code size is also valid for 12 bit symbols.
+    if let Some(max_code) = flags.max_code {
+        if max_code <= min_code && max_code != 12 {
+            return explain();
+        }
+    }
+    let max_code = flags.max_code;
     let bit_order = flags.bit_order;
+    let encoder = || {
+        let configuration = enlzw::Configuration::new(bit_order, min_code);
+        match max_code {
+            Some(size) => configuration.with_max_code_size(size),
+            None => configuration,
+        }
+        .build()
+    };
+    let decoder = || {
+        let configuration = delzw::Configuration::new(bit_order, min_code);
+        match max_code {
+            Some(size) => configuration.with_max_code_size(size),
+            None => configuration,
+        }
+        .build()
+    };
 
     let result = match (input, operation) {
         (Input::File(file), Operation::Encode) => (|| {
             let data = fs::File::open(file)?;
             let file = io::BufReader::with_capacity(1 << 26, data);
 
-            let mut encoder = enlzw::Encoder::new(bit_order, min_code);
+            let mut encoder = encoder();
             encoder.into_stream(out).encode_all(file).status
         })(),
         (Input::Stdin, Operation::Encode) => {
             let input = io::BufReader::with_capacity(1 << 26, io::stdin());
-            let mut encoder = enlzw::Encoder::new(bit_order, min_code);
+            let mut encoder = encoder();
             encoder.into_stream(out).encode_all(input).status
         }
         (Input::File(file), Operation::Decode) => (|| {
             let data = fs::File::open(file)?;
             let file = io::BufReader::with_capacity(1 << 26, data);
 
-            let mut decoder = delzw::Decoder::new(bit_order, min_code);
+            let mut decoder = decoder();
             decoder.into_stream(out).decode_all(file).status
         })(),
         (Input::Stdin, Operation::Decode) => {
             let input = io::BufReader::with_capacity(1 << 26, io::stdin());
-            let mut decoder = delzw::Decoder::new(bit_order, min_code);
+            let mut decoder = decoder();
             decoder.into_stream(out).decode_all(input).status
         }
     };
@@ -59,6 +82,7 @@ struct Flags {
     files: Vec<Input>,
     operation: Option<Operation>,
     min_code: u8,
+    max_code: Option<u8>,
     bit_order: BitOrder,
 }
 
@@ -80,6 +104,7 @@ fn explain<T>() -> T {
         Arguments:\n\
         -e\t operation encode (default)\n\
         -d\t operation decode\n\
+        -b <bits>\t maximum code size in 9..=16 (default 12)\n\
         <file>\tfilepath or '-' for stdin"
     );
     process::exit(1);
@@ -91,6 +116,7 @@ impl Default for Flags {
             files: vec![],
             operation: None,
             min_code: 8,
+            max_code: None,
             bit_order: BitOrder::Msb,
         }
     }
@@ -121,6 +147,17 @@ impl Flags {
                         flags.min_code = st.parse().ok().ok_or(ParamError)?;
                     }
                 },
+                Some("-b") | Some("--max-bits") => match args.next() {
+                    None => return Err(ParamError),
+                    Some(bits) => {
+                        let st = bits.to_str().ok_or(ParamError)?;
+                        let size = st.parse().ok().ok_or(ParamError)?;
+                        if !(9..=16).contains(&size) {
+                            return Err(ParamError);
+                        }
+                        flags.max_code = Some(size);
+                    }
+                },
                 Some("-le") | Some("--little-endian") => {
                     flags.bit_order = BitOrder::Lsb;
                 }
@@ -133,7 +170,6 @@ impl Flags {
                 Some(other) if other.starts_with('-') => {
                     // Reserved for future use.
                     // -a: self-describing archive format, similar to actual compress
-                    // -b: maximum bits
                     // -v: verbosity
                     // some compress compatibility mode? Probably through arg(0) though.
                     return Err(ParamError);
@@ -0,0 +1,105 @@
+//! A module for the crunched members of `.ARC` archives, method `8` of SEA ARC and PKPAK.
+//!
+//! The data of a crunched member starts with a byte holding the maximum code size, which ARC
+//! always sets to `12`. An LZW stream follows that uses the codes of the `compress` utility in
+//! block mode: they are packed with the least significant bit first, start at 9 bits, `256` is the
+//! clear code and there is no end code. Codes are also read in the groups of `compress`, so the
+//! stream is decoded by the same decoder as a `.Z` file, only with another header.
+//!
+//! Before crunching, ARC packs runs of a repeated byte. The byte `0x90` followed by a count `n`
+//! repeats the byte before it to a total of `n`, a count of `0` stands for the byte `0x90` itself.
+//! [`decode`] undoes both steps while a decoder from [`build_decoder`] only decodes the LZW
+//! stream, its output is still packed and can be expanded with [`unpack`].
+//!
+//! # Example
+//!
+//! ```
+//! use weezl::{arc, compress};
+//!
+//! // The data of `compress` with a maximum code size of 12 bits, behind the header of ARC.
+//! let packed = b"TOBEORNOTTOBEORTOBEORNOT!\x90\x08";
+//! let compressed = compress::Configuration::new()
+//!     .with_max_code_size(12)
+//!     .build_encoder()
+//!     .encode(packed)
+//!     .unwrap();
+//! let mut crunched = vec![12];
+//! crunched.extend_from_slice(&compressed[3..]);
+//!
+//! let decoded = arc::decode(&crunched).unwrap();
+//! assert_eq!(decoded, b"TOBEORNOTTOBEORTOBEORNOT!!!!!!!!");
+//! ```
+//!
+//! [`decode`]: fn.decode.html
+//! [`build_decoder`]: fn.build_decoder.html
+//! [`unpack`]: fn.unpack.html
+use crate::alloc::{boxed::Box, vec::Vec};
+use crate::compress::DecodeHeader;
+use crate::decode::{self, Decoder};
+use crate::error::LzwError;
+use crate::{BitOrder, Dialect};
+
+/// The code size at the start of the stream and after each clear code.
+const INITIAL_CODE_SIZE: u8 = 9;
+/// The largest maximum code size of a crunched member.
+const MAX_CODE_SIZE: u8 = 12;
+/// The byte which introduces the count of a run.
+const DLE: u8 = 0x90;
+
+/// Create a decoder for a crunched member, including its header.
+///
+/// A maximum code size in the header outside of `9..=12` results in an error status. The output
+/// is the packed data, see [`unpack`].
+///
+/// [`unpack`]: fn.unpack.html
+pub fn build_decoder() -> Decoder {
+    Decoder::from_state(Box::new(DecodeHeader::new(1, MAX_CODE_SIZE, parse_header)))
+}
+
+/// Decode a crunched member and expand its runs.
+pub fn decode(data: &[u8]) -> Result<Vec<u8>, LzwError> {
+    let packed = build_decoder().decode(data)?;
+    Ok(unpack(&packed))
+}
+
+/// Expand the runs of a repeated byte in packed data.
+///
+/// A count at the very end of the data is missing, the `0x90` before it is ignored. A run at the
+/// very start repeats the byte `0`.
+pub fn unpack(packed: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(packed.len());
+    let mut last = 0;
+    let mut bytes = packed.iter();
+
+    while let Some(&byte) = bytes.next() {
+        if byte != DLE {
+            out.push(byte);
+            last = byte;
+            continue;
+        }
+
+        // The escaped `0x90` does not start a run itself.
+        match bytes.next() {
+            Some(0) => out.push(DLE),
+            Some(&count) => out.resize(out.len() + usize::from(count) - 1, last),
+            None => break,
+        }
+    }
+
+    out
+}
+
+/// The configuration of the stream after the header, if the header is valid.
+fn parse_header(header: &[u8], max_code_size: u8) -> Result<decode::Configuration, LzwError> {
+    let &[size] = header else {
+        return Err(LzwError::InvalidHeader);
+    };
+
+    if !(INITIAL_CODE_SIZE..=max_code_size).contains(&size) {
+        return Err(LzwError::InvalidHeader);
+    }
+
+    Ok(decode::Configuration::new(BitOrder::Lsb, 8)
+        .with_max_code_size(size)
+        .with_dialect(Dialect::Compress { block_mode: true }))
+}
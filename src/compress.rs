@@ -0,0 +1,481 @@
+//! A module for the `.Z` format of the `compress` utility.
+//!
+//! A `.Z` file is a three byte header followed by a single LZW stream. The header consists of the
+//! magic bytes `1f 9d` and a flag byte which holds the maximum code size in its low five bits and
+//! the block mode in its high bit. The codes are packed with the least significant bit first,
+//! starting at 9 bits for an alphabet of 8-bit bytes.
+//!
+//! The stream differs from those in GIF and TIFF in several ways, which is why you can not decode
+//! it with a `Decoder` from [`Decoder::new`]. It has no end code, the data simply ends. In block
+//! mode the code `256` is a clear code, otherwise there are no special codes at all. The encoder
+//! does not clear the dictionary when it is full but only when the compression ratio starts to
+//! drop. Finally, codes are written in groups of eight and each change of the code size first
+//! pads the current group to its full length.
+//!
+//! The decoder and encoder built here are the same types as the standard ones, so all of their
+//! adapters work the same. Since there is no end code, the adapters which require an end marker
+//! instead accept the end of the input.
+//!
+//! # Example
+//!
+#![cfg_attr(feature = "std", doc = "```")]
+#![cfg_attr(not(feature = "std"), doc = "```ignore")]
+//! use weezl::compress::Configuration;
+//!
+//! let data = b"TOBEORNOTTOBEORTOBEORNOT";
+//! let compressed = Configuration::new()
+//!     .build_encoder()
+//!     .encode(data)
+//!     .unwrap();
+//! assert_eq!(compressed[..2], weezl::compress::MAGIC);
+//!
+//! let decompressed = Configuration::new()
+//!     .build_decoder()
+//!     .decode(&compressed)
+//!     .unwrap();
+//! assert_eq!(decompressed, data);
+//! ```
+//!
+//! [`Decoder::new`]: ../decode/struct.Decoder.html#method.new
+use crate::decode::{self, Decoder};
+use crate::encode::{self, Encoder};
+use crate::error::{BufferResult, LzwError, LzwStatus};
+use crate::{BitOrder, Dialect, LARGEST_CODESIZE};
+
+use crate::alloc::boxed::Box;
+#[cfg(feature = "parallel")]
+use crate::alloc::{vec, vec::Vec};
+
+/// The magic bytes at the start of every `.Z` file.
+pub const MAGIC: [u8; 2] = [0x1f, 0x9d];
+
+/// The bit of the flag byte which signals block mode, i.e. the presence of a clear code.
+const BLOCK_MODE: u8 = 0x80;
+/// Bits of the flag byte without any assigned meaning.
+const RESERVED: u8 = 0x60;
+/// The bits of the flag byte holding the maximum code size.
+const MAX_CODE_SIZE_MASK: u8 = 0x1f;
+/// The code size at the start of the stream and after each clear code.
+const INITIAL_CODE_SIZE: u8 = 9;
+
+/// Describes the parameters for reading and writing `.Z` streams.
+#[derive(Clone, Debug)]
+pub struct Configuration {
+    max_code_size: u8,
+}
+
+/// Parses a complete header, with the largest maximum code size to accept.
+pub(crate) type ParseHeader = fn(&[u8], u8) -> Result<decode::Configuration, LzwError>;
+
+/// Decoding state that reads the header before passing on to the inner decoder.
+///
+/// This is shared with other formats that put a short header in front of a `compress` stream.
+pub(crate) struct DecodeHeader {
+    /// The largest maximum code size accepted in the header.
+    max_code_size: u8,
+    parse: ParseHeader,
+    header: [u8; 3],
+    /// The number of bytes in the header, up to the size of `header`.
+    header_size: usize,
+    header_len: usize,
+    state: Option<Box<dyn decode::Stateful + Send + 'static>>,
+}
+
+/// Encoding state that writes the header before passing on to the inner encoder.
+struct EncodeHeader {
+    header: [u8; 3],
+    header_len: usize,
+    state: Box<dyn encode::Stateful + Send + 'static>,
+}
+
+impl Configuration {
+    /// Create a configuration with the maximum code size of 16 bits, the default of `compress`.
+    pub fn new() -> Self {
+        Configuration {
+            max_code_size: LARGEST_CODESIZE,
+        }
+    }
+
+    /// Set the maximum code size, as the `-b` option of `compress`.
+    ///
+    /// The encoder writes streams with this maximum code size. The decoder accepts streams up to
+    /// this maximum code size, which bounds the memory it uses for its table.
+    ///
+    /// Default: `16`.
+    ///
+    /// # Panics
+    ///
+    /// The `size` needs to be in the interval `9..=16`.
+    pub fn with_max_code_size(self, size: u8) -> Self {
+        crate::assert_max_code_size(size, 8);
+        Configuration {
+            max_code_size: size,
+        }
+    }
+
+    /// Create a decoder for a `.Z` stream, including its header.
+    ///
+    /// All parameters of the stream are read from the header. A header with an invalid magic, any
+    /// reserved flag set, or a maximum code size above the configured one results in an error
+    /// status.
+    pub fn build_decoder(self) -> Decoder {
+        Decoder::from_state(Box::new(DecodeHeader::new(
+            3,
+            self.max_code_size,
+            parse_header,
+        )))
+    }
+
+    /// Create an encoder for a `.Z` stream, including its header.
+    ///
+    /// The stream is always written in block mode, as `compress` does by default.
+    pub fn build_encoder(self) -> Encoder {
+        let configuration = self.encode_configuration();
+
+        Encoder::from_state(Box::new(EncodeHeader {
+            header: [MAGIC[0], MAGIC[1], self.max_code_size | BLOCK_MODE],
+            header_len: 0,
+            state: Encoder::from_configuration(&configuration),
+        }))
+    }
+
+    /// Encode a `.Z` stream on all available threads, in chunks of the given size.
+    ///
+    /// The stream is a valid `.Z` file but differs from the output of [`build_encoder`], see
+    /// [`encode::parallel`] for details.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `chunk_size` is `0`.
+    ///
+    /// [`build_encoder`]: #method.build_encoder
+    /// [`encode::parallel`]: ../encode/parallel/index.html
+    #[cfg(feature = "parallel")]
+    pub fn encode_parallel(self, data: &[u8], chunk_size: usize) -> Result<Vec<u8>, LzwError> {
+        let configuration = self.encode_configuration();
+        let mut out = vec![MAGIC[0], MAGIC[1], self.max_code_size | BLOCK_MODE];
+        encode::parallel::encode_into(&configuration, data, chunk_size, &mut out)?;
+        Ok(out)
+    }
+
+    /// Decode a `.Z` stream on all available threads.
+    ///
+    /// The result is exactly that of a decoder from [`build_decoder`], see
+    /// [`decode::parallel`] for details.
+    ///
+    /// [`build_decoder`]: #method.build_decoder
+    /// [`decode::parallel`]: ../decode/parallel/index.html
+    #[cfg(feature = "parallel")]
+    pub fn decode_parallel(self, data: &[u8]) -> Result<Vec<u8>, LzwError> {
+        let parsed = match data {
+            [magic0, magic1, flags, rest @ ..] => {
+                parse_header(&[*magic0, *magic1, *flags], self.max_code_size)
+                    .ok()
+                    .map(|configuration| (configuration, rest))
+            }
+            _ => None,
+        };
+
+        match parsed {
+            Some((configuration, rest)) => decode::parallel::decode(&configuration, rest)
+                .map_err(|err| err.offset_by((data.len() - rest.len()) as u64)),
+            None => self.build_decoder().decode(data),
+        }
+    }
+
+    fn encode_configuration(&self) -> encode::Configuration {
+        encode::Configuration::new(BitOrder::Lsb, 8)
+            .with_max_code_size(self.max_code_size)
+            .with_dialect(Dialect::Compress { block_mode: true })
+    }
+}
+
+impl Default for Configuration {
+    fn default() -> Self {
+        Configuration::new()
+    }
+}
+
+/// The configuration of the stream after a header, if the header is valid.
+fn parse_header(header: &[u8], max_code_size: u8) -> Result<decode::Configuration, LzwError> {
+    let &[magic0, magic1, flags] = header else {
+        return Err(LzwError::InvalidHeader);
+    };
+
+    if [magic0, magic1] != MAGIC || flags & RESERVED != 0 {
+        return Err(LzwError::InvalidHeader);
+    }
+
+    let size = flags & MAX_CODE_SIZE_MASK;
+    if size < INITIAL_CODE_SIZE || size > max_code_size {
+        return Err(LzwError::InvalidHeader);
+    }
+
+    let block_mode = flags & BLOCK_MODE != 0;
+    Ok(decode::Configuration::new(BitOrder::Lsb, 8)
+        .with_max_code_size(size)
+        .with_dialect(Dialect::Compress { block_mode }))
+}
+
+impl DecodeHeader {
+    /// Read a header of `header_size` bytes, at most three, and decode with its configuration.
+    pub(crate) fn new(header_size: usize, max_code_size: u8, parse: ParseHeader) -> Self {
+        DecodeHeader {
+            max_code_size,
+            parse,
+            header: [0; 3],
+            header_size,
+            header_len: 0,
+            state: None,
+        }
+    }
+
+    fn parse_header(&self) -> Result<Box<dyn decode::Stateful + Send + 'static>, LzwError> {
+        let configuration = (self.parse)(&self.header[..self.header_size], self.max_code_size)?;
+        Ok(Decoder::from_configuration(&configuration))
+    }
+
+    fn advance_with(&mut self, mut inp: &[u8], out: &mut [u8], exact: bool) -> BufferResult {
+        let o_in = inp.len();
+
+        if self.state.is_none() {
+            let missing = &mut self.header[self.header_len..self.header_size];
+            let len = missing.len().min(inp.len());
+            missing[..len].copy_from_slice(&inp[..len]);
+            self.header_len += len;
+            inp = &inp[len..];
+
+            if self.header_len < self.header_size {
+                return BufferResult {
+                    consumed_in: len,
+                    consumed_out: 0,
+                    status: Ok(if len > 0 {
+                        LzwStatus::Ok
+                    } else {
+                        LzwStatus::NoProgress
+                    }),
+                };
+            }
+
+            match self.parse_header() {
+                Ok(state) => self.state = Some(state),
+                Err(err) => {
+                    return BufferResult {
+                        consumed_in: len,
+                        consumed_out: 0,
+                        status: Err(err),
+                    }
+                }
+            }
+        }
+
+        let header_len = o_in - inp.len();
+        let state = self.state.as_mut().unwrap();
+        let mut result = if exact {
+            state.advance_exact(inp, out)
+        } else {
+            state.advance(inp, out)
+        };
+        result.consumed_in += header_len;
+        result.status = result
+            .status
+            .map_err(|err| err.offset_by(self.header_size as u64));
+
+        if header_len > 0 {
+            if let Ok(LzwStatus::NoProgress) = result.status {
+                result.status = Ok(LzwStatus::Ok);
+            }
+        }
+
+        result
+    }
+}
+
+impl decode::Stateful for DecodeHeader {
+    fn advance(&mut self, inp: &[u8], out: &mut [u8]) -> BufferResult {
+        self.advance_with(inp, out, false)
+    }
+
+    fn advance_exact(&mut self, inp: &[u8], out: &mut [u8]) -> BufferResult {
+        self.advance_with(inp, out, true)
+    }
+
+    fn buffered_bits(&self) -> u8 {
+        self.state.as_ref().map_or(0, |state| state.buffered_bits())
+    }
+
+    fn has_ended(&self) -> bool {
+        self.state.as_ref().is_some_and(|state| state.has_ended())
+    }
+
+    fn ends_implicitly(&self) -> bool {
+        // A stream without a complete header is not valid, after it there is no end code.
+        self.state.is_some()
+    }
+
+    fn restart(&mut self) {
+        if let Some(state) = &mut self.state {
+            state.restart();
+        }
+    }
+
+    fn reset(&mut self) {
+        self.header_len = 0;
+        self.state = None;
+    }
+
+    fn clone_boxed(&self) -> Box<dyn decode::Stateful + Send + 'static> {
+        Box::new(DecodeHeader {
+            max_code_size: self.max_code_size,
+            parse: self.parse,
+            header: self.header,
+            header_size: self.header_size,
+            header_len: self.header_len,
+            state: self.state.as_ref().map(|state| state.clone_boxed()),
+        })
+    }
+
+    #[cfg(feature = "serde")]
+    fn save(&self) -> Option<decode::snapshot::State> {
+        None
+    }
+
+    #[cfg(feature = "serde")]
+    fn load(&mut self, _: &decode::snapshot::State) -> Result<(), &'static str> {
+        Err("the state of a decoder reading a header can not be restored")
+    }
+}
+
+impl encode::Stateful for EncodeHeader {
+    fn advance(&mut self, inp: &[u8], mut out: &mut [u8]) -> BufferResult {
+        let missing = &self.header[self.header_len..];
+        let len = missing.len().min(out.len());
+        out[..len].copy_from_slice(&missing[..len]);
+        self.header_len += len;
+        out = &mut out[len..];
+
+        if self.header_len < self.header.len() {
+            return BufferResult {
+                consumed_in: 0,
+                consumed_out: len,
+                status: Ok(LzwStatus::Ok),
+            };
+        }
+
+        let mut result = self.state.advance(inp, out);
+        result.consumed_out += len;
+        result
+    }
+
+    fn mark_ended(&mut self) -> bool {
+        self.state.mark_ended()
+    }
+
+    fn restart(&mut self) {
+        self.state.restart();
+    }
+
+    fn reset(&mut self) {
+        self.header_len = 0;
+        self.state.reset();
+    }
+
+    fn clone_boxed(&self) -> Box<dyn encode::Stateful + Send + 'static> {
+        Box::new(EncodeHeader {
+            header: self.header,
+            header_len: self.header_len,
+            state: self.state.clone_boxed(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Configuration, MAGIC};
+    use crate::alloc::vec::Vec;
+    use crate::LzwError;
+
+    #[test]
+    fn header() {
+        let encoded = Configuration::new().build_encoder().encode(b"").unwrap();
+        assert_eq!(encoded, [MAGIC[0], MAGIC[1], 0x90]);
+
+        let decoded = Configuration::new().build_decoder().decode(&encoded);
+        assert_eq!(decoded.unwrap(), b"");
+
+        let configuration = Configuration::new().with_max_code_size(12);
+        let encoded = configuration.clone().build_encoder().encode(b"").unwrap();
+        assert_eq!(encoded, [MAGIC[0], MAGIC[1], 0x8c]);
+
+        let decoded = configuration.build_decoder().decode(&encoded);
+        assert_eq!(decoded.unwrap(), b"");
+    }
+
+    #[test]
+    fn invalid_header() {
+        for header in [
+            &[0x1f, 0x8b, 0x8c][..],
+            &[0x1f, 0x9d, 0xec],
+            &[0x1f, 0x9d, 0x88],
+            &[0x1f, 0x9d, 0x91],
+        ] {
+            let decoded = Configuration::new().build_decoder().decode(header);
+            assert!(
+                matches!(decoded, Err(LzwError::InvalidHeader)),
+                "{:?}",
+                header
+            );
+        }
+
+        let decoded = Configuration::new().build_decoder().decode(&[0x1f, 0x9d]);
+        assert!(matches!(decoded, Err(LzwError::UnexpectedEof)));
+
+        // The stream would need a larger table than configured.
+        let decoded = Configuration::new()
+            .with_max_code_size(12)
+            .build_decoder()
+            .decode(&[0x1f, 0x9d, 0x90]);
+        assert!(matches!(decoded, Err(LzwError::InvalidHeader)));
+    }
+
+    #[test]
+    fn known_stream() {
+        // Checked with `uncompress`, the data is too short for any code size change.
+        const DATA: &[u8] = b"TOBEORNOTTOBEORTOBEORNOT";
+        const ENCODED: &[u8] = &[
+            0x1f, 0x9d, 0x8c, 0x54, 0x9e, 0x08, 0x29, 0xf2, 0x44, 0x8a, 0x93, 0x27, 0x54, 0x02,
+            0x0e, 0x2c, 0xa8, 0x90, 0xa0, 0x41, 0x84,
+        ];
+
+        let decoded = Configuration::new().build_decoder().decode(ENCODED);
+        assert_eq!(decoded.unwrap(), DATA);
+
+        let encoded = Configuration::new()
+            .with_max_code_size(12)
+            .build_encoder()
+            .encode(DATA);
+        assert_eq!(encoded.unwrap(), ENCODED);
+    }
+
+    #[test]
+    fn roundtrip_code_size_changes() {
+        // Repetitive data fills the table, the noisy data after it degrades the ratio which makes
+        // the encoder clear the table.
+        let data: Vec<u8> = (0..1u32 << 17)
+            .map(|i| match i >> 16 {
+                0 => (i % 7 * (i >> 10)) as u8,
+                _ => (i.wrapping_mul(0x9e37_79b9) >> 24) as u8,
+            })
+            .collect();
+
+        for max_code_size in 9..=16 {
+            let configuration = Configuration::new().with_max_code_size(max_code_size);
+            let encoded = configuration.clone().build_encoder().encode(&data).unwrap();
+            let decoded = Configuration::new()
+                .build_decoder()
+                .decode(&encoded)
+                .unwrap();
+            assert!(decoded == data, "{}", max_code_size);
+        }
+    }
+}
@@ -1,12 +1,16 @@
 //! A module for all decoding needs.
 #[cfg(feature = "std")]
 use crate::error::StreamResult;
-use crate::error::{BufferResult, LzwError, LzwStatus, VectorResult};
-use crate::{BitOrder, Code, StreamBuf, MAX_CODESIZE, MAX_ENTRIES, STREAM_BUF_SIZE};
+use crate::error::{BufferResult, CodeError, LzwError, LzwStatus, VectorResult};
+#[cfg(feature = "std")]
+use crate::WRITER_BUF_SIZE;
+use crate::{
+    BitOrder, Code, Dialect, StreamBuf, LARGEST_ENTRIES, MAX_CODESIZE, MAX_ENTRIES, STREAM_BUF_SIZE,
+};
 
 use crate::alloc::{boxed::Box, vec, vec::Vec};
 #[cfg(feature = "std")]
-use std::io::{self, BufRead, Write};
+use std::io::{self, BufRead, Read, Write};
 
 /// The state for decoding data with an LZW algorithm.
 ///
@@ -21,16 +25,73 @@ use std::io::{self, BufRead, Write};
 ///
 /// * [`decode`] for decoding once without any IO-loop.
 /// * [`into_async`] for decoding with the `futures` traits for asynchronous IO.
+/// * [`into_tokio_async`] for decoding with the `tokio` traits for asynchronous IO.
 /// * [`into_stream`] for decoding with the standard `io` traits.
+/// * [`into_read`] for pulling decoded data through the standard `io::Read` trait.
 /// * [`into_vec`] for in-memory decoding.
 ///
+/// The [`Reader`] and [`Writer`] take ownership of a decoder to decode data read through them or
+/// written to them respectively.
+///
+/// A clone of a decoder continues independently from the same state, for example to try decoding
+/// some data and to fall back to the original if that fails. With the `serde` feature, a decoder
+/// built from a [`Configuration`] can also be serialized along with its complete state.
+///
 /// [`decode_bytes`]: #method.decode_bytes
 /// [`decode`]: #method.decode
 /// [`into_async`]: #method.into_async
+/// [`into_tokio_async`]: #method.into_tokio_async
 /// [`into_stream`]: #method.into_stream
+/// [`into_read`]: #method.into_read
 /// [`into_vec`]: #method.into_vec
+/// [`Reader`]: struct.Reader.html
+/// [`Writer`]: struct.Writer.html
+/// [`Configuration`]: struct.Configuration.html
 pub struct Decoder {
     state: Box<dyn Stateful + Send + 'static>,
+    end_marker: EndMarker,
+    /// Whether the last call that produced output filled the output buffer completely.
+    filled_output: bool,
+    /// The maximum number of bytes to decode, if limited.
+    output_limit: Option<u64>,
+    /// The maximum ratio of decoded bytes to consumed bytes, if limited.
+    expansion_limit: Option<u32>,
+    /// The number of bytes decoded since the start, for the limits.
+    decoded: u64,
+    /// The number of bytes consumed since the start, for the limits.
+    consumed: u64,
+    /// The configuration of the state, to recreate it when deserializing.
+    #[cfg(feature = "serde")]
+    configuration: Option<Configuration>,
+}
+
+/// How to treat a stream that ends without an end marker.
+///
+/// This applies to the adapters that decode the whole stream, such as [`Decoder::decode`] and
+/// the `decode_all` methods, and to the end of the readers and writers. The output decoded up to
+/// the end of the data is kept in all cases, only the reported status differs.
+///
+/// Streams that do not have an end code at all, such as `.Z` files, are always accepted.
+///
+/// [`Decoder::decode`]: struct.Decoder.html#method.decode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EndMarker {
+    /// The end marker is required, otherwise an [`LzwError::UnexpectedEof`] is reported.
+    Require,
+    /// A stream that ends before its end marker is accepted.
+    AcceptMissing,
+    /// A missing end marker is accepted if the decoded data filled the output buffer.
+    ///
+    /// This is the counterpart to [`Configuration::with_yield_on_full_buffer`], where the length
+    /// of the decoded data is known out-of-band and indicated through the size of the output
+    /// buffer. The stream must have filled the last output buffer completely, otherwise an
+    /// [`LzwError::UnexpectedEof`] is reported. For [`IntoVec`] the buffer is the spare capacity
+    /// of the vector, and for the other adapters it is their intermediate buffer.
+    ///
+    /// [`Configuration::with_yield_on_full_buffer`]: struct.Configuration.html#method.with_yield_on_full_buffer
+    /// [`IntoVec`]: struct.IntoVec.html
+    AcceptMissingAfterFullBuffer,
 }
 
 /// A decoding stream sink.
@@ -63,6 +124,87 @@ pub struct IntoAsync<'d, W> {
     default_size: usize,
 }
 
+/// An async decoding sink for `tokio`.
+///
+/// See [`Decoder::into_tokio_async`] on how to create this type.
+///
+/// [`Decoder::into_tokio_async`]: struct.Decoder.html#method.into_tokio_async
+#[cfg(feature = "tokio")]
+pub struct IntoTokioAsync<'d, W> {
+    decoder: &'d mut Decoder,
+    writer: W,
+    buffer: Option<StreamBuf<'d>>,
+    default_size: usize,
+}
+
+/// A decoding source for reading decoded data.
+///
+/// See [`Decoder::into_read`] on how to create this type.
+///
+/// [`Decoder::into_read`]: struct.Decoder.html#method.into_read
+#[cfg(feature = "std")]
+pub struct IntoRead<'d, R> {
+    decoder: &'d mut Decoder,
+    reader: R,
+    error: Option<LzwError>,
+}
+
+/// An async decoding source for `tokio`, for reading decoded data.
+///
+/// See [`Decoder::into_tokio_read`] on how to create this type.
+///
+/// [`Decoder::into_tokio_read`]: struct.Decoder.html#method.into_tokio_read
+#[cfg(feature = "tokio")]
+pub struct IntoTokioRead<'d, R> {
+    decoder: &'d mut Decoder,
+    reader: R,
+    error: Option<LzwError>,
+}
+
+/// A decoding source for reading decoded data, owning its decoder.
+///
+/// This is the owning variant of [`IntoRead`], for use where the decoder can not be borrowed such
+/// as when returning an `impl Read`.
+///
+/// [`IntoRead`]: struct.IntoRead.html
+#[cfg(feature = "std")]
+pub struct Reader<R> {
+    decoder: Decoder,
+    reader: R,
+    error: Option<LzwError>,
+}
+
+/// A decoder that owns its sink and decodes all data written to it.
+///
+/// In contrast to [`IntoStream`] the encoded data need not be available at once in a reader.
+/// Instead it is passed in any number of calls to `write`, and the decoded data is written to the
+/// sink right away. Any data after the end code is ignored. Use [`finish`] to check that the
+/// stream was complete.
+///
+/// # Example
+///
+/// ```
+/// use std::io::Write;
+/// use weezl::{BitOrder, decode::{Decoder, Writer}};
+///
+/// let data = b"\x80\x04\x81\x94l\x1b\x06\xf0\xb0 \x1d\xc6\xf1\xc8l\x19 \x10";
+/// let mut writer = Writer::new(Decoder::new(BitOrder::Msb, 9), vec![]);
+/// for chunk in data.chunks(4) {
+///     writer.write_all(chunk).unwrap();
+/// }
+/// let decoded = writer.finish().unwrap();
+/// assert_eq!(decoded, b"Hello, world");
+/// ```
+///
+/// [`IntoStream`]: struct.IntoStream.html
+/// [`finish`]: #method.finish
+#[cfg(feature = "std")]
+pub struct Writer<W> {
+    decoder: Decoder,
+    writer: W,
+    buffer: Vec<u8>,
+}
+
 /// A decoding sink into a vector.
 ///
 /// See [`Decoder::into_vec`] on how to create this type.
@@ -73,13 +215,27 @@ pub struct IntoVec<'d> {
     vector: &'d mut Vec<u8>,
 }
 
-trait Stateful {
+pub(crate) trait Stateful {
     fn advance(&mut self, inp: &[u8], out: &mut [u8]) -> BufferResult;
+    /// Advance and yield on a full buffer, regardless of the configuration.
+    fn advance_exact(&mut self, inp: &[u8], out: &mut [u8]) -> BufferResult;
+    /// The number of bits consumed from the input that were not yet decoded.
+    fn buffered_bits(&self) -> u8;
     fn has_ended(&self) -> bool;
+    /// If the stream has no end code, such that running out of input is a regular end.
+    fn ends_implicitly(&self) -> bool;
     /// Ignore an end code and continue decoding (no implied reset).
     fn restart(&mut self);
     /// Reset the decoder to the beginning, dropping all buffers etc.
     fn reset(&mut self);
+    /// Copy the complete state, such that both copies continue independently.
+    fn clone_boxed(&self) -> Box<dyn Stateful + Send + 'static>;
+    /// Describe the complete state, if it can be recreated from a `Configuration`.
+    #[cfg(feature = "serde")]
+    fn save(&self) -> Option<snapshot::State>;
+    /// Replace the state with a saved one, of a state with the same configuration.
+    #[cfg(feature = "serde")]
+    fn load(&mut self, state: &snapshot::State) -> Result<(), &'static str>;
 }
 
 /// Internally has three bitfields: the previous code, the new last byte, and the first byte.
@@ -101,7 +257,7 @@ struct DerivationBase {
     first: u8,
 }
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 struct MsbBuffer {
     /// A buffer of individual bits. The oldest code is kept in the high-order bits.
     bit_buffer: u64,
@@ -113,7 +269,7 @@ struct MsbBuffer {
     bits: u8,
 }
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 struct LsbBuffer {
     /// A buffer of individual bits. The oldest code is kept in the high-order bits.
     bit_buffer: u64,
@@ -125,10 +281,27 @@ struct LsbBuffer {
     bits: u8,
 }
 
-trait CodeBuffer {
+/// A code buffer that reads codes in groups of eight, as `compress` does.
+///
+/// Each change of the code size, including the reset by a clear code, discards the remaining
+/// codes of the current group. A group of eight codes always ends on a byte boundary.
+#[derive(Clone)]
+struct GroupBuffer<B> {
+    inner: B,
+    /// The number of codes read in the current group.
+    codes: u8,
+    /// The number of padding bits which must be skipped before the next code.
+    skip: u8,
+}
+
+trait CodeBuffer: Clone + Send + 'static {
     fn new(min_size: u8) -> Self;
     fn reset(&mut self, min_size: u8);
     fn bump_code_size(&mut self);
+    /// Discard the rest of the current group of codes, if the packing has such groups.
+    fn align_group(&mut self);
+    /// Discard up to the given number of buffered bits, returning how many were discarded.
+    fn drop_bits(&mut self, bits: u8) -> u8;
 
     /// Retrieve the next symbol, refilling if necessary.
     fn next_symbol(&mut self, inp: &mut &[u8]) -> Option<Code>;
@@ -140,13 +313,23 @@ trait CodeBuffer {
 
     fn max_code(&self) -> Code;
     fn code_size(&self) -> u8;
+    /// The number of bits read from the input that have not yet been consumed.
+    fn buffered_bits(&self) -> u8;
+
+    #[cfg(feature = "serde")]
+    fn save(&self) -> snapshot::Bits;
+    /// Restore the saved bits, which must have a valid code size.
+    #[cfg(feature = "serde")]
+    fn load(&mut self, bits: &snapshot::Bits) -> Result<(), &'static str>;
 }
 
-trait CodegenConstants {
+trait CodegenConstants: Clone + Send + 'static {
     const YIELD_ON_FULL: bool;
 }
 
+#[derive(Clone)]
 pub(crate) struct NoYield;
+#[derive(Clone)]
 pub(crate) struct YieldOnFull;
 
 impl CodegenConstants for NoYield {
@@ -157,9 +340,12 @@ impl CodegenConstants for YieldOnFull {
     const YIELD_ON_FULL: bool = true;
 }
 
-struct DecodeState<CodeBuffer, Constants: CodegenConstants> {
+#[derive(Clone)]
+struct DecodeState<CodeBuffer, Constants: CodegenConstants, Table> {
     /// The original minimum code size.
     min_size: u8,
+    /// The code size at which the code size no longer increases.
+    max_code_size: u8,
     /// The table of decoded codes.
     table: Table,
     /// The buffer of decoded data.
@@ -167,11 +353,16 @@ struct DecodeState<CodeBuffer, Constants: CodegenConstants> {
     /// The link which we are still decoding and its original code.
     last: Option<DerivationBase>,
     /// The next code entry.
-    next_code: Code,
-    /// Code to reset all tables.
-    clear_code: Code,
-    /// Code to signal the end of the stream.
-    end_code: Code,
+    ///
+    /// This is one past the largest code when the table is full, which does not fit a `Code` for
+    /// 16-bit codes.
+    next_code: u32,
+    /// The first code entry after a reset.
+    first_code: Code,
+    /// Code to reset all tables, if the stream has one.
+    clear_code: Option<Code>,
+    /// Code to signal the end of the stream, if the stream has one.
+    end_code: Option<Code>,
     /// A stored flag if the end code has already appeared.
     has_ended: bool,
     /// If tiff then bumps are a single code sooner.
@@ -180,15 +371,28 @@ struct DecodeState<CodeBuffer, Constants: CodegenConstants> {
     implicit_reset: bool,
     /// The buffer for decoded words.
     code_buffer: CodeBuffer,
+    /// The number of input bytes consumed by previous calls, to locate errors.
+    bytes_read: u64,
     #[allow(dead_code)]
     constants: core::marker::PhantomData<Constants>,
 }
 
+/// A decoder that reads and decodes one code at a time, instead of bursts of codes.
+///
+/// This shares the table and all other state with `DecodeState`, only `advance` differs. Each
+/// code derives its table entry before its word is written, so the word of the `next_code` is
+/// reconstructed from the table as any other and the previous word need not be kept.
+#[derive(Clone)]
+struct StreamingState<CodeBuffer, Constants: CodegenConstants, Table> {
+    inner: DecodeState<CodeBuffer, Constants, Table>,
+}
+
 // We have a buffer of 64 bits. So at max size at most 5 units can be read at once without
 // refilling the buffer. At smaller code sizes there are more. We tune for 6 here, by slight
 // experimentation. This may be an architecture dependent constant.
 const BURST: usize = 6;
 
+#[derive(Clone)]
 struct Buffer {
     bytes: Box<[u8]>,
     read_mark: usize,
@@ -197,19 +401,114 @@ struct Buffer {
     pub(crate) reconstructed_another_code: bool,
 }
 
-/// Mask for indexing into fixed-size arrays. Since MAX_ENTRIES = 4096 = 2^12,
-/// `idx & MASK` is guaranteed < MAX_ENTRIES. LLVM can prove this for
-/// `[T; MAX_ENTRIES]` arrays, eliminating bounds checks. Corrupt `prev`
-/// values wrap to a valid index instead of panicking.
-const MASK: usize = MAX_ENTRIES - 1;
-
 const STREAMING_Q: usize = 8;
 
-struct Table {
-    suffixes: Box<[[u8; STREAMING_Q]; MAX_ENTRIES]>,
-    chain: Box<[Link; MAX_ENTRIES]>,
-    depths: Box<[u16; MAX_ENTRIES]>,
+/// A table of codes, as used by the decoding loop.
+///
+/// The capacity is a power of two, either `MAX_ENTRIES` or `LARGEST_ENTRIES`, such that the
+/// default code size does not pay for the larger tables. Fewer entries may be in use when the
+/// maximum code size is smaller.
+trait DecodeTable: Clone + Send + 'static {
+    fn new(entries: usize) -> Self;
+    /// The number of entries, including those reserved for special codes.
+    fn len(&self) -> usize;
+    /// The number of entries after which the table is full.
+    fn entries(&self) -> usize;
+    /// Forget all derived entries, keeping the alphabet.
+    fn clear(&mut self, first_code: Code);
+    /// Setup the alphabet and the entries reserved for special codes.
+    fn init(&mut self, min_size: u8, first_code: Code);
+    fn first_of(&self, code: Code) -> u8;
+    fn code_len(&self, code: Code) -> u16;
+    /// Add an entry for the word of `from` extended with `byte`.
+    fn derive(&mut self, from: &DerivationBase, byte: u8);
+    /// Write the word of `code` into `out`, which is exactly as long, and return its first byte.
+    fn reconstruct(&self, code: Code, out: &mut [u8]) -> u8;
+    /// Describe the derived entries, those from `first_code` on.
+    #[cfg(feature = "serde")]
+    fn save(&self, first_code: Code) -> Vec<snapshot::Entry>;
+    /// Derive the described entries, in an initialized table.
+    #[cfg(feature = "serde")]
+    fn load(&mut self, entries: &[snapshot::Entry]) -> Result<(), &'static str>;
+
+    /// Write the word of `code` into the start of `out`, which may be longer.
+    ///
+    /// Bytes after the word may be overwritten with arbitrary values.
+    fn reconstruct_relaxed(&self, code: Code, out: &mut [[u8; 8]]) -> u8 {
+        let len = usize::from(self.code_len(code));
+        self.reconstruct(code, &mut out.as_flattened_mut()[..len])
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn is_full(&self) -> bool {
+        self.len() >= self.entries()
+    }
+
+    fn derive_burst(&mut self, from: &mut DerivationBase, burst: &[Code], first: &[u8]) {
+        // A burst is constrained to the number of possible codes before a size switch, however at
+        // the maximum possible size we *never* perform a switch again (until reset) and so the
+        // size of the burst is not constrained. But we must not derive any of those additional
+        // codes which would wrap into the start and overwrite entries for codes 0-5.
+        let max = self.entries() - self.len();
+
+        for (&code, &first_byte) in burst.iter().zip(first.iter()).take(max) {
+            self.derive(from, first_byte);
+            from.code = code;
+            from.first = first_byte;
+        }
+    }
+}
+
+/// The table of codes storing up to eight bytes of each word per entry.
+///
+/// Each entry holds the last partial chunk of its word, the rest is found by following the chain
+/// of prefixes in steps of whole chunks.
+struct ChunkedTable<const N: usize> {
+    suffixes: Box<[[u8; STREAMING_Q]; N]>,
+    chain: Box<[Link; N]>,
+    depths: Box<[u16; N]>,
+    len: usize,
+    /// The number of entries after which the table is full.
+    entries: usize,
+}
+
+/// The table of codes storing one byte of each word per entry.
+///
+/// Each entry holds the last byte of its word, the rest is found by following the chain of
+/// prefixes one byte at a time.
+struct ClassicTable<const N: usize> {
+    bytes: Box<[u8; N]>,
+    chain: Box<[Link; N]>,
+    depths: Box<[u16; N]>,
     len: usize,
+    /// The number of entries after which the table is full.
+    entries: usize,
+}
+
+/// The layout of the decoding table and the loop decoding codes with it.
+///
+/// The strategies differ in speed depending on the data, see
+/// [`Configuration::with_table_strategy`]. Words with many repeated bytes favor the larger
+/// entries, while photographic images with many short words favor decoding each code at once.
+///
+/// [`Configuration::with_table_strategy`]: struct.Configuration.html#method.with_table_strategy
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum TableStrategy {
+    /// Store one byte per table entry and decode runs of independent codes in bursts.
+    Classic,
+    /// Store up to eight bytes per table entry and decode runs of independent codes in bursts.
+    #[default]
+    Chunked,
+    /// Store up to eight bytes per table entry and decode one code after another.
+    ///
+    /// A code that does not fit the output buffer is held back for the next call. This is usually
+    /// the fastest strategy for TIFF images and GIF images with short words.
+    Streaming,
 }
 
 /// Describes the static parameters for creating a decoder.
@@ -219,6 +518,12 @@ pub struct Configuration {
     size: u8,
     tiff: bool,
     yield_on_full: bool,
+    max_code_size: u8,
+    dialect: Dialect,
+    end_marker: EndMarker,
+    table_strategy: TableStrategy,
+    output_limit: Option<u64>,
+    expansion_limit: Option<u32>,
 }
 
 impl Configuration {
@@ -230,6 +535,12 @@ impl Configuration {
             size,
             tiff: false,
             yield_on_full: false,
+            max_code_size: MAX_CODESIZE,
+            dialect: Dialect::Standard,
+            end_marker: EndMarker::Require,
+            table_strategy: TableStrategy::Chunked,
+            output_limit: None,
+            expansion_limit: None,
         }
     }
 
@@ -241,6 +552,12 @@ impl Configuration {
             size,
             tiff: true,
             yield_on_full: false,
+            max_code_size: MAX_CODESIZE,
+            dialect: Dialect::Standard,
+            end_marker: EndMarker::Require,
+            table_strategy: TableStrategy::Chunked,
+            output_limit: None,
+            expansion_limit: None,
         }
     }
 
@@ -263,10 +580,118 @@ impl Configuration {
         }
     }
 
+    /// Set the maximum code size.
+    ///
+    /// The code size increases with the number of table entries until it reaches this size. The
+    /// table then holds `1 << size` entries and the stream must use a clear code to add any more.
+    /// Formats such as `.Z` files of `compress` use up to 16 bits. Note that larger tables take
+    /// longer to allocate and initialize.
+    ///
+    /// The decoder never reads codes wider than this size, and codes that are not yet in the
+    /// table are rejected as [`LzwError::CodeOutOfRange`]. A stream encoded with a larger maximum is
+    /// thus usually detected once its codes grow past the limit. This is not guaranteed however,
+    /// as every code is valid in a full table.
+    ///
+    /// Default: `12`.
+    ///
+    /// # Panics
+    ///
+    /// The `size` needs to be in the interval `9..=16` and larger than the symbol size, except for
+    /// the default of `12` which also accepts a symbol size of `12`.
+    pub fn with_max_code_size(self, size: u8) -> Self {
+        super::assert_max_code_size(size, self.size);
+        Configuration {
+            max_code_size: size,
+            ..self
+        }
+    }
+
+    /// Choose how to treat a stream that ends without an end marker.
+    ///
+    /// Default: [`EndMarker::Require`].
+    ///
+    /// [`EndMarker::Require`]: enum.EndMarker.html#variant.Require
+    pub fn with_end_marker(self, end_marker: EndMarker) -> Self {
+        Configuration { end_marker, ..self }
+    }
+
+    /// Limit the length of the decoded data.
+    ///
+    /// Decoding stops with [`LzwError::LimitExceeded`] when the stream holds more than `bytes` of
+    /// data, without writing or allocating any more output. This guards against streams crafted
+    /// to expand a small input enormously. The limit counts all data decoded since the decoder
+    /// was created or reset.
+    ///
+    /// Default: no limit.
+    pub fn with_output_limit(self, bytes: u64) -> Self {
+        Configuration {
+            output_limit: Some(bytes),
+            ..self
+        }
+    }
+
+    /// Limit the length of the decoded data relative to the input.
+    ///
+    /// Decoding stops with [`LzwError::LimitExceeded`] when the decoded data is more than `ratio`
+    /// times as long as the input consumed since the decoder was created or reset. Note that
+    /// images of a single color legitimately expand by a factor of several hundreds.
+    ///
+    /// Default: no limit.
+    pub fn with_expansion_limit(self, ratio: u32) -> Self {
+        Configuration {
+            expansion_limit: Some(ratio),
+            ..self
+        }
+    }
+
+    /// Choose the layout of the decoding table and how codes are decoded with it.
+    ///
+    /// This affects only the speed of decoding. All strategies decode a stream to the same data
+    /// and report the same errors.
+    ///
+    /// Default: [`TableStrategy::Chunked`].
+    ///
+    /// [`TableStrategy::Chunked`]: enum.TableStrategy.html#variant.Chunked
+    pub fn with_table_strategy(self, table_strategy: TableStrategy) -> Self {
+        Configuration {
+            table_strategy,
+            ..self
+        }
+    }
+
+    /// Choose the layout of special codes and the packing of codes.
+    pub(crate) fn with_dialect(self, dialect: Dialect) -> Self {
+        Configuration { dialect, ..self }
+    }
+
     /// Create a new decoder with the define configuration.
     pub fn build(self) -> Decoder {
         Decoder {
             state: Decoder::from_configuration(&self),
+            end_marker: self.end_marker,
+            filled_output: false,
+            output_limit: self.output_limit,
+            expansion_limit: self.expansion_limit,
+            decoded: 0,
+            consumed: 0,
+            #[cfg(feature = "serde")]
+            configuration: Some(self),
+        }
+    }
+}
+
+impl Clone for Decoder {
+    fn clone(&self) -> Self {
+        Decoder {
+            state: self.state.clone_boxed(),
+            end_marker: self.end_marker,
+            filled_output: self.filled_output,
+            output_limit: self.output_limit,
+            expansion_limit: self.expansion_limit,
+            decoded: self.decoded,
+            consumed: self.consumed,
+            #[cfg(feature = "serde")]
+            configuration: self.configuration.clone(),
         }
     }
 }
@@ -298,31 +723,86 @@ impl Decoder {
         Configuration::with_tiff_size_switch(order, size).build()
     }
 
-    fn from_configuration(configuration: &Configuration) -> Box<dyn Stateful + Send + 'static> {
+    pub(crate) fn from_configuration(
+        configuration: &Configuration,
+    ) -> Box<dyn Stateful + Send + 'static> {
+        macro_rules! make_decode_state {
+            ($buf:ty, $cgc:ty, $table:ty) => {{
+                let mut state = DecodeState::<$buf, $cgc, $table>::new(
+                    configuration.size,
+                    configuration.max_code_size,
+                    configuration.dialect,
+                );
+                state.is_tiff = configuration.tiff;
+                state
+            }};
+        }
+
         macro_rules! make_state {
+            ($buf:ty, $cgc:ty, $entries:expr) => {{
+                match configuration.table_strategy {
+                    TableStrategy::Classic => {
+                        Box::new(make_decode_state!($buf, $cgc, ClassicTable<{ $entries }>))
+                            as Box<dyn Stateful + Send + 'static>
+                    }
+                    TableStrategy::Chunked => {
+                        Box::new(make_decode_state!($buf, $cgc, ChunkedTable<{ $entries }>))
+                    }
+                    TableStrategy::Streaming => Box::new(StreamingState {
+                        inner: make_decode_state!($buf, $cgc, ChunkedTable<{ $entries }>),
+                    }),
+                }
+            }};
+        }
+
+        // Only streams with larger codes pay for the larger table.
+        macro_rules! make_sized_state {
             ($buf:ty, $cgc:ty) => {{
-                let mut state = Box::new(DecodeState::<$buf, $cgc>::new(configuration.size));
-                state.is_tiff = configuration.tiff;
-                state as Box<dyn Stateful + Send + 'static>
+                if configuration.max_code_size <= MAX_CODESIZE {
+                    make_state!($buf, $cgc, MAX_ENTRIES)
+                } else {
+                    make_state!($buf, $cgc, LARGEST_ENTRIES)
+                }
             }};
         }
 
-        match (configuration.order, configuration.yield_on_full) {
-            (BitOrder::Lsb, false) => {
-                make_state!(LsbBuffer, NoYield)
-            }
-            (BitOrder::Lsb, true) => {
-                make_state!(LsbBuffer, YieldOnFull)
-            }
-            (BitOrder::Msb, false) => {
-                make_state!(MsbBuffer, NoYield)
+        macro_rules! make_yielding_state {
+            ($buf:ty) => {{
+                if configuration.yield_on_full {
+                    make_sized_state!($buf, YieldOnFull)
+                } else {
+                    make_sized_state!($buf, NoYield)
+                }
+            }};
+        }
+
+        match (configuration.order, configuration.dialect) {
+            (BitOrder::Lsb, Dialect::Standard) => make_yielding_state!(LsbBuffer),
+            (BitOrder::Lsb, Dialect::Compress { .. }) => {
+                make_yielding_state!(GroupBuffer<LsbBuffer>)
             }
-            (BitOrder::Msb, true) => {
-                make_state!(MsbBuffer, YieldOnFull)
+            (BitOrder::Msb, Dialect::Standard) => make_yielding_state!(MsbBuffer),
+            (BitOrder::Msb, Dialect::Compress { .. }) => {
+                make_yielding_state!(GroupBuffer<MsbBuffer>)
             }
         }
     }
 
+    /// Wrap a state that is not built from a `Configuration`.
+    pub(crate) fn from_state(state: Box<dyn Stateful + Send + 'static>) -> Self {
+        Decoder {
+            state,
+            end_marker: EndMarker::Require,
+            filled_output: false,
+            output_limit: None,
+            expansion_limit: None,
+            decoded: 0,
+            consumed: 0,
+            #[cfg(feature = "serde")]
+            configuration: None,
+        }
+    }
+
     /// Decode some bytes from `inp` and write result to `out`.
     ///
     /// This will consume a prefix of the input buffer and write decoded output into a prefix of
@@ -336,20 +816,179 @@ impl Decoder {
     /// See [`into_stream`] for high-level functions (that are only available with the `std`
     /// feature).
     ///
+    /// When a limit of the configuration is exceeded this returns [`LzwError::LimitExceeded`],
+    /// now and on all further calls.
+    ///
     /// [`into_stream`]: #method.into_stream
     pub fn decode_bytes(&mut self, inp: &[u8], out: &mut [u8]) -> BufferResult {
-        self.state.advance(inp, out)
+        self.decode_bytes_with(inp, out, false)
+    }
+
+    /// Decode exactly `out.len()` bytes of data whose length is known out-of-band.
+    ///
+    /// This is meant for formats such as TIFF, whose strips state their decoded size, and GIF,
+    /// whose frames do. The decoder stops as soon as `out` has been filled, as with
+    /// [`Configuration::with_yield_on_full_buffer`] and regardless of that setting. It does not
+    /// interpret any following code except to check for an end code, as encoders may leave out
+    /// the end code or write garbage after the data.
+    ///
+    /// Returns the number of bytes of `inp` that make up the stream: all bytes holding the codes
+    /// of the data and, if it directly follows, the end code. After a successful call:
+    ///
+    /// * [`has_ended`] tells whether the data was followed by an end code,
+    /// * a return value less than `inp.len()` indicates trailing bytes, such as garbage, or more
+    ///   codes when the stream was longer than expected,
+    /// * otherwise the stream ended without an end code.
+    ///
+    /// When the stream ends before `out` is filled, either through an end code or the end of
+    /// the input, this returns [`LzwError::UnexpectedEof`]. The decoded data is still written to
+    /// the start of `out`. Other errors are reported as by [`decode_bytes`].
+    ///
+    /// The decoder should be [`reset`] before decoding another stream with it.
+    ///
+    /// [`Configuration::with_yield_on_full_buffer`]: struct.Configuration.html#method.with_yield_on_full_buffer
+    /// [`has_ended`]: #method.has_ended
+    /// [`decode_bytes`]: #method.decode_bytes
+    /// [`reset`]: #method.reset
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weezl::{BitOrder, decode::Decoder, encode::Encoder};
+    ///
+    /// let data = b"TOBEORNOTTOBEORTOBEORNOT";
+    /// let mut encoded = Encoder::new(BitOrder::Msb, 8).encode(data)?;
+    /// // Some bytes that do not belong to the stream.
+    /// encoded.extend_from_slice(&[0xff; 4]);
+    ///
+    /// let mut decoded = [0; 24];
+    /// let mut decoder = Decoder::new(BitOrder::Msb, 8);
+    /// let len = decoder.decode_exact(&encoded, &mut decoded)?;
+    ///
+    /// assert_eq!(&decoded, data);
+    /// assert!(decoder.has_ended());
+    /// assert_eq!(len, encoded.len() - 4);
+    /// # Ok::<(), weezl::LzwError>(())
+    /// ```
+    pub fn decode_exact(&mut self, inp: &[u8], out: &mut [u8]) -> Result<usize, LzwError> {
+        self.decode_exact_rows(inp, out, usize::MAX, |_| {})
+    }
+
+    /// Decode as [`decode_exact`], handing each row of `out` to `on_row` once it is decoded.
+    ///
+    /// Rows are `row_len` bytes long except for the last one, which may be shorter. When decoding
+    /// stops early the decoded part of the current row is handed over as well.
+    ///
+    /// [`decode_exact`]: #method.decode_exact
+    pub(crate) fn decode_exact_rows(
+        &mut self,
+        mut inp: &[u8],
+        out: &mut [u8],
+        row_len: usize,
+        mut on_row: impl FnMut(&mut [u8]),
+    ) -> Result<usize, LzwError> {
+        let o_in = inp.len();
+        let mut filled = 0;
+        let mut row_start: usize = 0;
+
+        while filled < out.len() {
+            // Only decode up to the end of the row, so it is still in cache when handed over.
+            let row_end = row_start.saturating_add(row_len).min(out.len());
+            let result = self.decode_bytes_with(inp, &mut out[filled..row_end], true);
+            inp = &inp[result.consumed_in..];
+            filled += result.consumed_out;
+
+            let status = result.status.and_then(|status| match status {
+                LzwStatus::Ok => Ok(()),
+                LzwStatus::NoProgress | LzwStatus::Done => Err(LzwError::UnexpectedEof),
+            });
+
+            if filled == row_end || status.is_err() {
+                on_row(&mut out[row_start..filled]);
+                row_start = filled;
+            }
+
+            status?;
+        }
+
+        // The bytes holding the data, a partially decoded byte belongs to it.
+        let stream_len = |decoder: &Self, inp: &[u8]| {
+            let unread = usize::from(decoder.state.buffered_bits() / 8);
+            o_in - inp.len() - unread
+        };
+
+        let data_len = stream_len(self, inp);
+        if self.state.has_ended() {
+            return Ok(data_len);
+        }
+
+        // Look at the next code, only an end code belongs to the stream. Any output is not
+        // required and garbage is not an error.
+        let mut next = [0];
+        loop {
+            let result = self.decode_bytes_with(inp, &mut next, true);
+            inp = &inp[result.consumed_in..];
+
+            match result.status {
+                Ok(LzwStatus::Done) => return Ok(stream_len(self, inp)),
+                Ok(LzwStatus::Ok) if result.consumed_out == 0 => {}
+                _ => return Ok(data_len),
+            }
+        }
+    }
+
+    fn decode_bytes_with(&mut self, inp: &[u8], out: &mut [u8], exact: bool) -> BufferResult {
+        if self.exceeds_limits() {
+            return BufferResult {
+                consumed_in: 0,
+                consumed_out: 0,
+                status: Err(LzwError::LimitExceeded),
+            };
+        }
+
+        let mut result = match self.output_room() {
+            // Decode a single byte to find out if the stream holds any more data.
+            Some(0) => {
+                let result = self.advance(inp, &mut [0], exact);
+                self.decoded += result.consumed_out as u64;
+                BufferResult {
+                    consumed_in: result.consumed_in,
+                    consumed_out: 0,
+                    status: result.status,
+                }
+            }
+            Some(room) => {
+                let len = out.len().min(room);
+                self.advance(inp, &mut out[..len], exact)
+            }
+            None => self.advance(inp, out, exact),
+        };
+
+        if result.consumed_out > 0 {
+            self.filled_output = result.consumed_out == out.len();
+        }
+
+        self.decoded += result.consumed_out as u64;
+        self.consumed += result.consumed_in as u64;
+
+        if result.status.is_ok() && self.exceeds_limits() {
+            result.status = Err(LzwError::LimitExceeded);
+        }
+
+        result
     }
 
     /// Decode a single chunk of lzw encoded data.
     ///
-    /// This method requires the data to contain an end marker, and returns an error otherwise.
+    /// This method requires the data to contain an end marker unless the configured [`EndMarker`]
+    /// policy accepts a missing one, and returns [`LzwError::UnexpectedEof`] otherwise.
     ///
     /// This is a convenience wrapper around [`into_vec`]. Use the `into_vec` adapter to customize
-    /// buffer size, to supply an existing vector, to control whether an end marker is required, or
-    /// to preserve partial data in the case of a decoding error.
+    /// buffer size, to supply an existing vector, to decode without an end marker, or to preserve
+    /// partial data in the case of a decoding error.
     ///
-    /// [`into_vec`]: #into_vec
+    /// [`EndMarker`]: enum.EndMarker.html
+    /// [`into_vec`]: #method.into_vec
     ///
     /// # Example
     ///
@@ -380,6 +1019,34 @@ impl Decoder {
         }
     }
 
+    /// Construct a decoder that is read from, pulling encoded data from a reader.
+    ///
+    /// Each call to `read` decodes only as much data as necessary to fill its buffer. The stream
+    /// signals EOF after the end code. An error is returned if the reader ends before an end code
+    /// in a stream that requires one, and decoding errors are returned as `InvalidData`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::io::Read;
+    /// use weezl::{BitOrder, decode::Decoder};
+    ///
+    /// let data = b"\x80\x04\x81\x94l\x1b\x06\xf0\xb0 \x1d\xc6\xf1\xc8l\x19 \x10";
+    /// let mut decoder = Decoder::new(BitOrder::Msb, 9);
+    ///
+    /// let mut decoded = String::new();
+    /// decoder.into_read(&data[..]).read_to_string(&mut decoded).unwrap();
+    /// assert_eq!(decoded, "Hello, world");
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn into_read<R: BufRead>(&mut self, reader: R) -> IntoRead<'_, R> {
+        IntoRead {
+            decoder: self,
+            reader,
+            error: None,
+        }
+    }
+
     /// Construct a decoder into an async writer.
     #[cfg(feature = "async")]
     pub fn into_async<W: futures::io::AsyncWrite>(&mut self, writer: W) -> IntoAsync<'_, W> {
@@ -391,9 +1058,40 @@ impl Decoder {
         }
     }
 
+    /// Construct a decoder into a `tokio` async writer.
+    #[cfg(feature = "tokio")]
+    pub fn into_tokio_async<W: tokio::io::AsyncWrite>(
+        &mut self,
+        writer: W,
+    ) -> IntoTokioAsync<'_, W> {
+        IntoTokioAsync {
+            decoder: self,
+            writer,
+            buffer: None,
+            default_size: STREAM_BUF_SIZE,
+        }
+    }
+
+    /// Construct a decoder that is read from asynchronously, pulling data from a `tokio` reader.
+    ///
+    /// This is the async equivalent of [`into_read`](#method.into_read) with the same behavior.
+    #[cfg(feature = "tokio")]
+    pub fn into_tokio_read<R: tokio::io::AsyncBufRead>(
+        &mut self,
+        reader: R,
+    ) -> IntoTokioRead<'_, R> {
+        IntoTokioRead {
+            decoder: self,
+            reader,
+            error: None,
+        }
+    }
+
     /// Construct a decoder into a vector.
     ///
-    /// All decoded data is appended and the vector is __not__ cleared.
+    /// All decoded data is appended and the vector is __not__ cleared. The data is decoded into
+    /// the spare capacity of the vector first, so reserving the expected length limits the first
+    /// output buffer to it.
     ///
     /// Compared to `into_stream` this interface allows a high-level access to decoding without
     /// requires the `std`-feature. Also, it can make full use of the extra buffer control that the
@@ -414,6 +1112,17 @@ impl Decoder {
         self.state.has_ended()
     }
 
+    /// Check if the stream may end at this point, according to the configured policy.
+    pub(crate) fn may_end(&self) -> bool {
+        self.state.has_ended()
+            || self.state.ends_implicitly()
+            || match self.end_marker {
+                EndMarker::Require => false,
+                EndMarker::AcceptMissing => true,
+                EndMarker::AcceptMissingAfterFullBuffer => self.filled_output,
+            }
+    }
+
     /// Ignore an end code and continue.
     ///
     /// This will _not_ reset any of the inner code tables and not have the effect of a clear code.
@@ -434,6 +1143,34 @@ impl Decoder {
     /// duplicate setup work.
     pub fn reset(&mut self) {
         self.state.reset();
+        self.filled_output = false;
+        self.decoded = 0;
+        self.consumed = 0;
+    }
+
+    fn advance(&mut self, inp: &[u8], out: &mut [u8], exact: bool) -> BufferResult {
+        if exact {
+            self.state.advance_exact(inp, out)
+        } else {
+            self.state.advance(inp, out)
+        }
+    }
+
+    /// The number of bytes that may still be decoded within the output limit, if any.
+    fn output_room(&self) -> Option<usize> {
+        use core::convert::TryFrom;
+        self.output_limit.map(|limit| {
+            let room = limit.saturating_sub(self.decoded);
+            usize::try_from(room).unwrap_or(usize::MAX)
+        })
+    }
+
+    fn exceeds_limits(&self) -> bool {
+        let output = self.output_limit.is_some_and(|limit| self.decoded > limit);
+        let expansion = self
+            .expansion_limit
+            .is_some_and(|ratio| self.decoded > u64::from(ratio) * self.consumed);
+        output || expansion
     }
 }
 
@@ -447,6 +1184,11 @@ impl<'d, W: Write> IntoStream<'d, W> {
     }
 
     /// Decode data from a reader, requiring an end marker.
+    ///
+    /// A stream that ends without one is accepted only as permitted by the configured
+    /// [`EndMarker`] policy. All decoded data is written to the writer, even on an error.
+    ///
+    /// [`EndMarker`]: enum.EndMarker.html
     pub fn decode_all(mut self, read: impl BufRead) -> StreamResult {
         self.decode_part(read, true)
     }
@@ -492,81 +1234,292 @@ impl<'d, W: Write> IntoStream<'d, W> {
             Done,
         }
 
-        let mut bytes_read = 0;
-        let mut bytes_written = 0;
-
-        // Converting to mutable refs to move into the `once` closure.
-        let read_bytes = &mut bytes_read;
-        let write_bytes = &mut bytes_written;
+        let mut bytes_read = 0;
+        let mut bytes_written = 0;
+
+        // Converting to mutable refs to move into the `once` closure.
+        let read_bytes = &mut bytes_read;
+        let write_bytes = &mut bytes_written;
+
+        let outbuf: &mut [u8] =
+            match buffer.get_or_insert_with(|| StreamBuf::Owned(vec![0u8; *default_size])) {
+                StreamBuf::Borrowed(slice) => slice,
+                StreamBuf::Owned(vec) => &mut *vec,
+            };
+        assert!(!outbuf.is_empty());
+
+        let once = move || {
+            // Try to grab one buffer of input data.
+            let data = read.fill_buf()?;
+
+            // Decode as much of the buffer as fits.
+            let result = decoder.decode_bytes(data, &mut outbuf[..]);
+            // Do the bookkeeping and consume the buffer.
+            *read_bytes += result.consumed_in;
+            *write_bytes += result.consumed_out;
+            read.consume(result.consumed_in);
+
+            // Write the decoded data first, it is kept even when the status is an error.
+            writer.write_all(&outbuf[..result.consumed_out])?;
+
+            // Handle the status in the result.
+            let done = result.status.map_err(io::Error::from)?;
+
+            // Check if we had any new data at all.
+            if let LzwStatus::NoProgress = done {
+                debug_assert_eq!(
+                    result.consumed_out, 0,
+                    "No progress means we have not decoded any data"
+                );
+                // In particular we did not finish decoding.
+                if must_finish && !decoder.may_end() {
+                    return Err(io::Error::from(LzwError::UnexpectedEof));
+                } else {
+                    return Ok(Progress::Done);
+                }
+            }
+
+            Ok(if let LzwStatus::Done = done {
+                Progress::Done
+            } else {
+                Progress::Ok
+            })
+        };
+
+        // Decode chunks of input data until we're done.
+        let status = core::iter::repeat_with(once)
+            // scan+fuse can be replaced with map_while
+            .scan((), |(), result| match result {
+                Ok(Progress::Ok) => Some(Ok(())),
+                Err(err) => Some(Err(err)),
+                Ok(Progress::Done) => None,
+            })
+            .fuse()
+            .collect();
+
+        StreamResult {
+            bytes_read,
+            bytes_written,
+            status,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R> IntoRead<'_, R> {
+    /// Get a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Get a mutable reference to the underlying reader.
+    ///
+    /// Reading from it directly may corrupt the decoded stream.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Unwrap the underlying reader.
+    ///
+    /// It is positioned after the last byte consumed by the decoder. Note that the decoder may
+    /// have consumed some bytes beyond the end code.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: BufRead> Read for IntoRead<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        read_decoded(self.decoder, &mut self.reader, &mut self.error, buf)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R> Reader<R> {
+    /// Create a reader of the data decoded from `reader`.
+    ///
+    /// See [`Decoder::into_read`] for the behavior of the stream.
+    ///
+    /// [`Decoder::into_read`]: struct.Decoder.html#method.into_read
+    pub fn new(decoder: Decoder, reader: R) -> Self {
+        Reader {
+            decoder,
+            reader,
+            error: None,
+        }
+    }
+
+    /// Get a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Get a mutable reference to the underlying reader.
+    ///
+    /// Reading from it directly may corrupt the decoded stream.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Unwrap the underlying reader.
+    ///
+    /// It is positioned after the last byte consumed by the decoder. Note that the decoder may
+    /// have consumed some bytes beyond the end code.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Unwrap the decoder and the underlying reader.
+    pub fn into_parts(self) -> (Decoder, R) {
+        (self.decoder, self.reader)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: BufRead> Read for Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        read_decoded(&mut self.decoder, &mut self.reader, &mut self.error, buf)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> Writer<W> {
+    /// Create a writer that decodes into `writer`.
+    pub fn new(decoder: Decoder, writer: W) -> Self {
+        Writer::with_capacity(WRITER_BUF_SIZE, decoder, writer)
+    }
+
+    /// Create a writer with an intermediate buffer of the specified size.
+    ///
+    /// Decoded data is collected in this buffer before it is written to the sink. The default
+    /// size is 64 KiB.
+    ///
+    /// # Panics
+    /// This method panics if `size` is `0`.
+    pub fn with_capacity(size: usize, decoder: Decoder, writer: W) -> Self {
+        assert_ne!(size, 0, "Attempted to set empty buffer");
+        Writer {
+            decoder,
+            writer,
+            buffer: vec![0; size],
+        }
+    }
+
+    /// Get a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Get a mutable reference to the underlying writer.
+    ///
+    /// Data written to it directly is interleaved with the decoded stream.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Unwrap the underlying writer, without checking that the stream was complete.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Finish decoding, requiring that the stream was complete.
+    ///
+    /// An error is returned if no end code was decoded in a stream that requires one, unless the
+    /// configured [`EndMarker`] policy accepts a missing one.
+    ///
+    /// [`EndMarker`]: enum.EndMarker.html
+    pub fn finish(self) -> io::Result<W> {
+        if !self.decoder.may_end() {
+            return Err(io::Error::from(LzwError::UnexpectedEof));
+        }
+
+        Ok(self.writer)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> Write for Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut consumed = 0;
+
+        // Decode until all input is consumed and all buffered output has been written. Otherwise
+        // the data would only be written on the next call, or never.
+        while !self.decoder.has_ended() {
+            let inp = &buf[consumed..];
+            let result = self.decoder.decode_bytes(inp, &mut self.buffer);
+            consumed += result.consumed_in;
+            self.writer.write_all(&self.buffer[..result.consumed_out])?;
+
+            let status = result.status.map_err(io::Error::from)?;
+
+            if inp.is_empty() && !matches!(status, LzwStatus::Ok) {
+                break;
+            }
+        }
+
+        // Anything after the end code is ignored.
+        Ok(buf.len())
+    }
 
-        let outbuf: &mut [u8] =
-            match buffer.get_or_insert_with(|| StreamBuf::Owned(vec![0u8; *default_size])) {
-                StreamBuf::Borrowed(slice) => slice,
-                StreamBuf::Owned(vec) => &mut *vec,
-            };
-        assert!(!outbuf.is_empty());
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
 
-        let once = move || {
-            // Try to grab one buffer of input data.
-            let data = read.fill_buf()?;
+/// Decode into `buf` until at least one byte is available, the stream ended, or it failed.
+///
+/// A decoding error that occurs after some bytes have been decoded is stashed in `error` and
+/// returned on the next call instead, so that no decoded data is lost.
+#[cfg(feature = "std")]
+fn read_decoded(
+    decoder: &mut Decoder,
+    reader: &mut impl BufRead,
+    error: &mut Option<LzwError>,
+    buf: &mut [u8],
+) -> io::Result<usize> {
+    if let Some(err) = error {
+        return Err(io::Error::from(*err));
+    }
 
-            // Decode as much of the buffer as fits.
-            let result = decoder.decode_bytes(data, &mut outbuf[..]);
-            // Do the bookkeeping and consume the buffer.
-            *read_bytes += result.consumed_in;
-            *write_bytes += result.consumed_out;
-            read.consume(result.consumed_in);
+    if buf.is_empty() {
+        return Ok(0);
+    }
 
-            // Handle the status in the result.
-            let done = result.status.map_err(|err| {
-                io::Error::new(io::ErrorKind::InvalidData, &*format!("{:?}", err))
-            })?;
+    // First decode the codes the decoder already holds, only then wait for more input. The end
+    // code may be among them and the source need not signal its own end.
+    let mut fetch = false;
 
-            // Check if we had any new data at all.
-            if let LzwStatus::NoProgress = done {
-                debug_assert_eq!(
-                    result.consumed_out, 0,
-                    "No progress means we have not decoded any data"
-                );
-                // In particular we did not finish decoding.
-                if must_finish {
-                    return Err(io::Error::new(
-                        io::ErrorKind::UnexpectedEof,
-                        "No more data but no end marker detected",
-                    ));
-                } else {
-                    return Ok(Progress::Done);
-                }
-            }
+    loop {
+        let data = if fetch { reader.fill_buf()? } else { &[] };
+        let exhausted = data.is_empty();
 
-            // And finish by writing our result.
-            // TODO: we may lose data on error (also on status error above) which we might want to
-            // deterministically handle so that we don't need to restart everything from scratch as
-            // the only recovery strategy. Any changes welcome.
-            writer.write_all(&outbuf[..result.consumed_out])?;
+        let result = decoder.decode_bytes(data, buf);
+        reader.consume(result.consumed_in);
 
-            Ok(if let LzwStatus::Done = done {
-                Progress::Done
-            } else {
-                Progress::Ok
-            })
-        };
+        match result.status {
+            Err(err) => {
+                *error = Some(err);
+                if result.consumed_out > 0 {
+                    return Ok(result.consumed_out);
+                }
 
-        // Decode chunks of input data until we're done.
-        let status = core::iter::repeat_with(once)
-            // scan+fuse can be replaced with map_while
-            .scan((), |(), result| match result {
-                Ok(Progress::Ok) => Some(Ok(())),
-                Err(err) => Some(Err(err)),
-                Ok(Progress::Done) => None,
-            })
-            .fuse()
-            .collect();
+                return Err(io::Error::from(err));
+            }
+            _ if result.consumed_out > 0 => return Ok(result.consumed_out),
+            Ok(LzwStatus::Done) => return Ok(0),
+            // Consumed some input without completing a code, drain the decoder before
+            // waiting for more.
+            Ok(_) if !exhausted => fetch = false,
+            Ok(LzwStatus::Ok) if !fetch => {}
+            Ok(_) if !fetch => fetch = true,
+            Ok(_) => {
+                if decoder.may_end() {
+                    return Ok(0);
+                }
 
-        StreamResult {
-            bytes_read,
-            bytes_written,
-            status,
+                return Err(io::Error::from(LzwError::UnexpectedEof));
+            }
         }
     }
 }
@@ -580,6 +1533,11 @@ impl IntoVec<'_> {
     }
 
     /// Decode data from a slice, requiring an end marker.
+    ///
+    /// A stream that ends without one is accepted only as permitted by the configured
+    /// [`EndMarker`] policy. All decoded data is appended to the vector, even on an error.
+    ///
+    /// [`EndMarker`]: enum.EndMarker.html
     pub fn decode_all(mut self, read: &[u8]) -> VectorResult {
         self.decode_part(read, true)
     }
@@ -589,10 +1547,19 @@ impl IntoVec<'_> {
         let decoder = &mut self.decoder;
         let length = self.vector.len();
 
-        // Use the vector to do overflow checks and w/e.
-        self.vector.reserve(CHUNK_SIZE);
+        // Decode into the spare capacity, its end may mark the expected length of the data.
+        if self.vector.capacity() == length {
+            // Do not allocate beyond the output limit.
+            let chunk = decoder
+                .output_room()
+                .map_or(CHUNK_SIZE, |room| room.min(CHUNK_SIZE));
+            // Use the vector to do overflow checks and w/e.
+            self.vector.reserve(chunk);
+        }
+        // Grant at most a chunk of it, as zeroing all spare capacity on every call is slow.
+        let end = self.vector.capacity().min(length + CHUNK_SIZE);
         // FIXME: decoding into uninit buffer?
-        self.vector.resize(length + CHUNK_SIZE, 0u8);
+        self.vector.resize(end, 0u8);
 
         (&mut self.vector[length..], decoder)
     }
@@ -631,10 +1598,18 @@ impl IntoVec<'_> {
             let filled = self.vector.len() - unfilled;
             self.vector.truncate(filled);
 
+            // The output buffer of the end marker policy is all of the spare capacity.
+            if result.consumed_out > 0 {
+                self.decoder.filled_output = self.vector.len() == self.vector.capacity();
+            }
+            let may_end = self.decoder.may_end();
+
             // Handle the status in the result.
             match result.status {
                 Err(err) => Err(err),
-                Ok(LzwStatus::NoProgress) if must_finish => Err(LzwError::InvalidCode),
+                Ok(LzwStatus::NoProgress) if must_finish && !may_end => {
+                    Err(LzwError::UnexpectedEof)
+                }
                 Ok(LzwStatus::NoProgress) | Ok(LzwStatus::Done) => Ok(Progress::Done),
                 Ok(LzwStatus::Ok) => Ok(Progress::Ok),
             }
@@ -666,20 +1641,55 @@ impl IntoVec<'_> {
 #[path = "decode_into_async.rs"]
 mod impl_decode_into_async;
 
-impl<C: CodeBuffer, CgC: CodegenConstants> DecodeState<C, CgC> {
-    fn new(min_size: u8) -> Self {
+#[cfg(feature = "tokio")]
+#[path = "decode_into_tokio_async.rs"]
+mod impl_decode_into_tokio_async;
+
+#[cfg(feature = "parallel")]
+#[path = "decode_batch.rs"]
+pub mod batch;
+
+#[cfg(feature = "parallel")]
+#[path = "decode_parallel.rs"]
+pub mod parallel;
+
+#[cfg(feature = "std")]
+#[path = "decode_index.rs"]
+mod index;
+#[cfg(feature = "std")]
+pub use self::index::{Index, IndexedReader};
+
+#[path = "decode_code_reader.rs"]
+mod code_reader;
+pub use self::code_reader::CodeReader;
+
+#[cfg(feature = "serde")]
+#[path = "decode_serde.rs"]
+pub(crate) mod snapshot;
+
+#[path = "decode_tokens.rs"]
+mod tokens;
+pub use self::tokens::{Token, TokenKind, Tokens};
+
+impl<C: CodeBuffer, CgC: CodegenConstants, T: DecodeTable> DecodeState<C, CgC, T> {
+    fn new(min_size: u8, max_code_size: u8, dialect: Dialect) -> Self {
+        let (clear_code, end_code, first_code) = dialect.codes(min_size);
+        let entries = 1 << max_code_size;
         let mut pre_state = DecodeState {
             min_size,
-            table: Table::new(),
-            buffer: Buffer::new(),
+            max_code_size: dialect.code_size_limit(max_code_size),
+            table: T::new(entries),
+            buffer: Buffer::new(entries),
             last: None,
-            clear_code: 1 << min_size,
-            end_code: (1 << min_size) + 1,
-            next_code: (1 << min_size) + 2,
+            clear_code,
+            end_code,
+            next_code: first_code.into(),
+            first_code,
             has_ended: false,
             is_tiff: false,
             implicit_reset: true,
             code_buffer: C::new(min_size),
+            bytes_read: 0,
             constants: core::marker::PhantomData,
         };
 
@@ -687,17 +1697,43 @@ impl<C: CodeBuffer, CgC: CodegenConstants> DecodeState<C, CgC> {
         pre_state
     }
 
+    /// Describe a code that was just read and can not be decoded.
+    ///
+    /// The input consumed by the current call is `consumed`, including the code itself.
+    #[cold]
+    fn invalid_code(&self, code: Code, consumed: usize) -> LzwError {
+        let code_size = self.code_buffer.code_size();
+        let end =
+            (self.bytes_read + consumed as u64) * 8 - u64::from(self.code_buffer.buffered_bits());
+        let start = end.saturating_sub(code_size.into());
+
+        let err = CodeError {
+            code,
+            next_code: self.next_code,
+            code_size,
+            byte_offset: start / 8,
+            bit_offset: (start % 8) as u8,
+        };
+
+        if u32::from(code) > self.next_code {
+            LzwError::CodeOutOfRange(err)
+        } else {
+            LzwError::InvalidCode(err)
+        }
+    }
+
     fn init_tables(&mut self) {
         self.code_buffer.reset(self.min_size);
-        self.next_code = (1 << self.min_size) + 2;
-        self.table.init(self.min_size);
+        self.next_code = self.first_code.into();
+        self.table.init(self.min_size, self.first_code);
         self.bump_initial_code_size();
     }
 
     fn reset_tables(&mut self) {
+        self.code_buffer.align_group();
         self.code_buffer.reset(self.min_size);
-        self.next_code = (1 << self.min_size) + 2;
-        self.table.clear(self.min_size);
+        self.next_code = self.first_code.into();
+        self.table.clear(self.first_code);
         self.bump_initial_code_size();
     }
 
@@ -727,8 +1763,8 @@ impl<C: CodeBuffer, CgC: CodegenConstants> DecodeState<C, CgC> {
         //
         // However for TIFF the size switch is always one earlier, so compensate even though
         // realistically you should not use this combination: tiff mandates 8 bits.
-        if self.end_code > self.code_buffer.max_code() - Code::from(self.is_tiff)
-            && self.code_buffer.code_size() < MAX_CODESIZE
+        if self.first_code - 1 > self.code_buffer.max_code() - Code::from(self.is_tiff)
+            && self.code_buffer.code_size() < self.max_code_size
         {
             self.code_buffer.bump_code_size();
         }
@@ -737,39 +1773,77 @@ impl<C: CodeBuffer, CgC: CodegenConstants> DecodeState<C, CgC> {
     /// Bump the code size after the first coded symbol is read, ensure the `next_code` can be coded
     /// in all cases.
     fn bump_post_initial_code_size(&mut self) {
-        if self.next_code > self.code_buffer.max_code()
-            && self.code_buffer.code_size() < MAX_CODESIZE
+        if self.next_code > u32::from(self.code_buffer.max_code())
+            && self.code_buffer.code_size() < self.max_code_size
         {
             self.code_buffer.bump_code_size();
         }
     }
 }
 
-impl<C: CodeBuffer, CgC: CodegenConstants> Stateful for DecodeState<C, CgC> {
+impl<C: CodeBuffer, CgC: CodegenConstants, T: DecodeTable> Stateful for DecodeState<C, CgC, T> {
     fn has_ended(&self) -> bool {
         self.has_ended
     }
 
+    fn ends_implicitly(&self) -> bool {
+        self.end_code.is_none()
+    }
+
     fn restart(&mut self) {
         self.has_ended = false;
     }
 
     fn reset(&mut self) {
-        self.table.init(self.min_size);
-        self.next_code = (1 << self.min_size) + 2;
+        self.table.init(self.min_size, self.first_code);
+        self.next_code = self.first_code.into();
         self.buffer.read_mark = 0;
         self.buffer.write_mark = 0;
         self.last = None;
         self.restart();
         self.code_buffer = CodeBuffer::new(self.min_size);
-        if self.next_code > self.code_buffer.max_code()
-            && self.code_buffer.code_size() < MAX_CODESIZE
+        self.bytes_read = 0;
+        if self.next_code > u32::from(self.code_buffer.max_code())
+            && self.code_buffer.code_size() < self.max_code_size
         {
             self.code_buffer.bump_code_size();
         }
     }
 
-    fn advance(&mut self, mut inp: &[u8], mut out: &mut [u8]) -> BufferResult {
+    fn clone_boxed(&self) -> Box<dyn Stateful + Send + 'static> {
+        Box::new(self.clone())
+    }
+
+    #[cfg(feature = "serde")]
+    fn save(&self) -> Option<snapshot::State> {
+        Some(snapshot::save(self))
+    }
+
+    #[cfg(feature = "serde")]
+    fn load(&mut self, state: &snapshot::State) -> Result<(), &'static str> {
+        snapshot::load(self, state)
+    }
+
+    fn advance(&mut self, inp: &[u8], out: &mut [u8]) -> BufferResult {
+        self.advance_yielding(inp, out, CgC::YIELD_ON_FULL)
+    }
+
+    fn advance_exact(&mut self, inp: &[u8], out: &mut [u8]) -> BufferResult {
+        self.advance_yielding(inp, out, true)
+    }
+
+    fn buffered_bits(&self) -> u8 {
+        self.code_buffer.buffered_bits()
+    }
+}
+
+impl<C: CodeBuffer, CgC: CodegenConstants, T: DecodeTable> DecodeState<C, CgC, T> {
+    fn advance_yielding(
+        &mut self,
+        mut inp: &[u8],
+        mut out: &mut [u8],
+        yield_on_full: bool,
+    ) -> BufferResult {
         // Skip everything if there is nothing to do.
         if self.has_ended {
             return BufferResult {
@@ -807,7 +1881,7 @@ impl<C: CodeBuffer, CgC: CodegenConstants> Stateful for DecodeState<C, CgC> {
         // guaranteed to fit into the out slice without requiring a buffer. One burst can be
         // decoded in an extremely tight loop.
         //
-        // TODO: since words can be at most (1 << MAX_CODESIZE) = 4096 bytes long we could avoid
+        // TODO: since words can be at most (1 << max_code_size) bytes long we could avoid
         // that intermediate buffer at the expense of not always filling the output buffer
         // completely. Alternatively we might follow its chain of precursor states twice. This may
         // be even cheaper if we store more than one byte per link so it really should be
@@ -834,18 +1908,23 @@ impl<C: CodeBuffer, CgC: CodegenConstants> Stateful for DecodeState<C, CgC> {
             None => {
                 match self.next_symbol(&mut inp) {
                     // Plainly invalid code.
-                    Some(code) if code > self.next_code => status = Err(LzwError::InvalidCode),
+                    Some(code) if u32::from(code) > self.next_code => {
+                        status = Err(self.invalid_code(code, o_in - inp.len()))
+                    }
                     // next_code would require an actual predecessor.
-                    Some(code) if code == self.next_code => status = Err(LzwError::InvalidCode),
+                    Some(code) if u32::from(code) == self.next_code => {
+                        status = Err(self.invalid_code(code, o_in - inp.len()))
+                    }
                     // No more symbols available and nothing decoded yet.
                     // Assume that we didn't make progress, this may get reset to Done if we read
                     // some bytes from the input.
                     None => status = Ok(LzwStatus::NoProgress),
                     // Handle a valid code.
                     Some(init_code) => {
-                        if init_code == self.clear_code {
+                        if Some(init_code) == self.clear_code {
+                            self.code_buffer.align_group();
                             self.init_tables();
-                        } else if init_code == self.end_code {
+                        } else if Some(init_code) == self.end_code {
                             self.has_ended = true;
                             status = Ok(LzwStatus::Done);
                         } else {
@@ -853,7 +1932,7 @@ impl<C: CodeBuffer, CgC: CodegenConstants> Stateful for DecodeState<C, CgC> {
                             if self.table.is_empty() && self.implicit_reset {
                                 self.init_tables();
                             } else if self.table.is_empty() {
-                                status = Err(LzwError::InvalidCode);
+                                status = Err(self.invalid_code(init_code, o_in - inp.len()));
                             }
 
                             if !self.table.is_empty() {
@@ -934,7 +2013,7 @@ impl<C: CodeBuffer, CgC: CodegenConstants> Stateful for DecodeState<C, CgC> {
                 // In particular, we *also* break if the output buffer is still empty. Especially
                 // when the output parameter was an empty slice, we must try to fetch at least one
                 // code but with YIELD_ON_FULL we do not.
-                if CgC::YIELD_ON_FULL && out.is_empty() {
+                if yield_on_full && out.is_empty() {
                     break;
                 }
 
@@ -970,8 +2049,9 @@ impl<C: CodeBuffer, CgC: CodegenConstants> Stateful for DecodeState<C, CgC> {
 
                 debug_assert!(
                     // When the table is full, we have a max code above the size switch.
-                    self.table.len >= MAX_ENTRIES - usize::from(self.is_tiff)
-                        || self.code_buffer.max_code() - Code::from(self.is_tiff) >= self.next_code,
+                    self.table.len() >= self.table.entries() - usize::from(self.is_tiff)
+                        || u32::from(self.code_buffer.max_code() - Code::from(self.is_tiff))
+                            >= self.next_code,
                     "Table: {}, code_size: {}, next_code: {}, table_condition: {}",
                     self.table.is_full(),
                     self.code_buffer.code_size(),
@@ -985,11 +2065,12 @@ impl<C: CodeBuffer, CgC: CodegenConstants> Stateful for DecodeState<C, CgC> {
                 // code bounded by the current size's max code where we switch code size.
                 // Except in case the table is full then we actually want to allow decoding
                 // of an arbitrary count of non-resetting symbols.
-                let left_before_size_switch = size_switch_at.wrapping_sub(self.next_code);
+                let left_before_size_switch = size_switch_at.wrapping_sub(self.next_code as Code);
 
                 // Hoist loop-invariant fields into locals so the compiler doesn't reload
-                // from memory on every iteration of the hot burst loop.
-                let clear_code = self.clear_code;
+                // from memory on every iteration of the hot burst loop. Special codes, if any,
+                // directly follow the alphabet. Breaking on an absent one does no harm.
+                let clear_code: Code = 1 << self.min_size;
                 let next_code = self.next_code;
 
                 let mut last_decoded_bytes = None;
@@ -1018,7 +2099,7 @@ impl<C: CodeBuffer, CgC: CodegenConstants> Stateful for DecodeState<C, CgC> {
                     // A burst code can't be special. Fused check: since
                     // end_code = clear_code + 1, `read_code - clear_code < 2`
                     // catches both. Then one more compare for >= next_code.
-                    if read_code.wrapping_sub(clear_code) < 2 || read_code >= next_code {
+                    if read_code.wrapping_sub(clear_code) < 2 || u32::from(read_code) >= next_code {
                         break;
                     }
 
@@ -1032,7 +2113,7 @@ impl<C: CodeBuffer, CgC: CodegenConstants> Stateful for DecodeState<C, CgC> {
                     // We do exactly one more code (the one being inspected in the current iteration)
                     // after the 'burst'. When we want to break decoding precisely on the supplied
                     // buffer, we check if this is the last code to be decoded into it.
-                    if CgC::YIELD_ON_FULL {
+                    if yield_on_full {
                         if out.len() == usize::from(len) {
                             break;
                         }
@@ -1042,10 +2123,10 @@ impl<C: CodeBuffer, CgC: CodegenConstants> Stateful for DecodeState<C, CgC> {
                         let target = out.split_off_first_mut().unwrap();
                         burst_byte[burst_size - 1] = read_code as u8;
                         *target = burst_byte[burst_size - 1];
-                        debug_assert_eq!(1, self.table.depths[read_code as usize]);
+                        debug_assert_eq!(1, self.table.code_len(read_code));
                         last_decoded_bytes = Some(core::slice::from_mut(target));
                     } else {
-                        debug_assert_eq!(len, self.table.depths[read_code as usize]);
+                        debug_assert_eq!(len, self.table.code_len(read_code));
                         // If permissible, we do limited *overwrite* into the output buffer to save
                         // a lot of instructions that would be spent on computing exact copy
                         // lengths. That is instead of writing 2 individual bytes for a length 2
@@ -1067,7 +2148,7 @@ impl<C: CodeBuffer, CgC: CodegenConstants> Stateful for DecodeState<C, CgC> {
                         if chunked.len() >= usize::from(chunk_len) && slack >= 8 {
                             let relaxed = &mut chunked[..usize::from(chunk_len)];
                             burst_byte[burst_size - 1] =
-                                self.table.reconstruct_simple(read_code, relaxed);
+                                self.table.reconstruct_relaxed(read_code, relaxed);
                             target = out.split_off_mut(..usize::from(len)).unwrap();
                         } else {
                             target = out.split_off_mut(..usize::from(len)).unwrap();
@@ -1084,11 +2165,13 @@ impl<C: CodeBuffer, CgC: CodegenConstants> Stateful for DecodeState<C, CgC> {
 
                 if !broken_burst {
                     if !self.table.is_full() {
-                        self.next_code += cnt as u16;
+                        self.next_code += cnt as u32;
                         self.table.derive_burst(&mut deriv, codes, &burst_byte[..]);
                     }
 
-                    debug_assert!(self.table.is_full() || self.next_code <= size_switch_at);
+                    debug_assert!(
+                        self.table.is_full() || self.next_code <= u32::from(size_switch_at)
+                    );
                     code_link = Some(DerivationBase {
                         code: burst[cnt - 1],
                         first: burst_byte[cnt - 1],
@@ -1103,27 +2186,27 @@ impl<C: CodeBuffer, CgC: CodegenConstants> Stateful for DecodeState<C, CgC> {
                 let (&new_code, burst) = burst[..burst_size].split_last().unwrap();
 
                 if !self.table.is_full() {
-                    self.next_code += burst_size as u16 - 1;
+                    self.next_code += burst_size as u32 - 1;
                     self.table.derive_burst(&mut deriv, burst, &burst_byte[..]);
                 }
 
                 // Now handle the special codes.
-                if new_code == self.clear_code {
+                if Some(new_code) == self.clear_code {
                     self.reset_tables();
                     last_decoded = None;
                     // Restarts in the next call to the entry point.
                     break;
                 }
 
-                if new_code == self.end_code {
+                if Some(new_code) == self.end_code {
                     self.has_ended = true;
                     status = Ok(LzwStatus::Done);
                     last_decoded = None;
                     break;
                 }
 
-                if new_code > self.next_code {
-                    status = Err(LzwError::InvalidCode);
+                if u32::from(new_code) > self.next_code {
+                    status = Err(self.invalid_code(new_code, o_in - inp.len()));
                     last_decoded = None;
                     break;
                 }
@@ -1131,7 +2214,7 @@ impl<C: CodeBuffer, CgC: CodegenConstants> Stateful for DecodeState<C, CgC> {
                 // We need the decoded data of the new code if it is the `next_code`. This is the
                 // special case of LZW decoding that is demonstrated by `banana` (or form cScSc). In
                 // all other cases we only need the first character of the decoded data.
-                let have_next_code = new_code == self.next_code;
+                let have_next_code = u32::from(new_code) == self.next_code;
 
                 let required_len = if have_next_code {
                     self.table.code_len(deriv.code) + 1
@@ -1177,98 +2260,329 @@ impl<C: CodeBuffer, CgC: CodegenConstants> Stateful for DecodeState<C, CgC> {
                             None => &self.buffer.bytes[..self.buffer.write_mark],
                         };
 
-                        // We don't *actually* expect the unwrap to happen. Each source is at least 1
-                        // byte long. But llvm doesn't know this (too much indirect loads and cases).
-                        cha = source.first().copied().unwrap_or(0);
-                        target[..source.len()].copy_from_slice(source);
-                        target[source.len()..][0] = cha;
-                    } else {
-                        cha = self.table.reconstruct(new_code, target);
+                        // We don't *actually* expect the unwrap to happen. Each source is at least 1
+                        // byte long. But llvm doesn't know this (too much indirect loads and cases).
+                        cha = source.first().copied().unwrap_or(0);
+                        target[..source.len()].copy_from_slice(source);
+                        target[source.len()..][0] = cha;
+                    } else {
+                        cha = self.table.reconstruct(new_code, target);
+                    }
+
+                    // A new decoded word.
+                    last_decoded = Some(target);
+                }
+
+                // Each newly read code creates one new code/link based on the preceding code if we
+                // have enough space to put it there.
+                if !self.table.is_full() {
+                    self.table.derive(&deriv, cha);
+
+                    if self.next_code
+                        >= u32::from(self.code_buffer.max_code() - Code::from(self.is_tiff))
+                        && self.code_buffer.code_size() < self.max_code_size
+                    {
+                        self.bump_code_size();
+                    }
+
+                    self.next_code += 1;
+                }
+
+                // store the information on the decoded word.
+                code_link = Some(DerivationBase {
+                    code: new_code,
+                    first: cha,
+                });
+
+                // Can't make any more progress with decoding.
+                //
+                // We have more data buffered but not enough space to put it? We want fetch a next
+                // symbol if possible as in the case of it being a new symbol we can refer to the
+                // buffered output as the source for that symbol's meaning and do a memcpy.
+                //
+                // Since this test is after decoding at least one code, we can now check for an
+                // empty buffer and still guarantee progress when one was passed as a parameter.
+                if is_in_buffer || out.is_empty() {
+                    break;
+                }
+            }
+        }
+
+        // We need to store the last word into the buffer in case the first code in the next
+        // iteration is the next_code.
+        if let Some(tail) = last_decoded {
+            self.buffer.bytes[..tail.len()].copy_from_slice(tail);
+            self.buffer.write_mark = tail.len();
+            // Mark the full buffer as having been consumed.
+            self.buffer.read_mark = tail.len();
+        }
+
+        // Ensure we don't indicate that no progress was made if we read some bytes from the input
+        // (which is progress). The field `new_value` will have been written if any reconstruction
+        // was buffered. This is mostly a fail-safe in case the above loop does nothing else but
+        // buffer the value, without consuming any of the bytes. (It really should right now but
+        // that may change and it's not structurally required to).
+        if o_in > inp.len() || self.buffer.reconstructed_another_code {
+            if let Ok(LzwStatus::NoProgress) = status {
+                status = Ok(LzwStatus::Ok);
+            }
+        }
+
+        // Store the code/link state.
+        self.last = code_link;
+        self.bytes_read += (o_in - inp.len()) as u64;
+
+        BufferResult {
+            consumed_in: o_in.wrapping_sub(inp.len()),
+            consumed_out: o_out.wrapping_sub(out.len()),
+            status,
+        }
+    }
+}
+
+impl<C: CodeBuffer, CgC: CodegenConstants, T: DecodeTable> DecodeState<C, CgC, T> {
+    fn next_symbol(&mut self, inp: &mut &[u8]) -> Option<Code> {
+        self.code_buffer.next_symbol(inp)
+    }
+
+    fn bump_code_size(&mut self) {
+        self.code_buffer.bump_code_size()
+    }
+
+    fn refill_bits(&mut self, inp: &mut &[u8]) {
+        self.code_buffer.refill_bits(inp)
+    }
+}
+
+impl<C: CodeBuffer, CgC: CodegenConstants, T: DecodeTable> Stateful for StreamingState<C, CgC, T> {
+    fn has_ended(&self) -> bool {
+        self.inner.has_ended()
+    }
+
+    fn ends_implicitly(&self) -> bool {
+        self.inner.ends_implicitly()
+    }
+
+    fn restart(&mut self) {
+        self.inner.restart()
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset()
+    }
+
+    fn clone_boxed(&self) -> Box<dyn Stateful + Send + 'static> {
+        Box::new(self.clone())
+    }
+
+    #[cfg(feature = "serde")]
+    fn save(&self) -> Option<snapshot::State> {
+        self.inner.save()
+    }
+
+    #[cfg(feature = "serde")]
+    fn load(&mut self, state: &snapshot::State) -> Result<(), &'static str> {
+        self.inner.load(state)
+    }
+
+    fn advance(&mut self, inp: &[u8], out: &mut [u8]) -> BufferResult {
+        self.advance_yielding(inp, out, CgC::YIELD_ON_FULL)
+    }
+
+    fn advance_exact(&mut self, inp: &[u8], out: &mut [u8]) -> BufferResult {
+        self.advance_yielding(inp, out, true)
+    }
+
+    fn buffered_bits(&self) -> u8 {
+        self.inner.buffered_bits()
+    }
+}
+
+impl<C: CodeBuffer, CgC: CodegenConstants, T: DecodeTable> StreamingState<C, CgC, T> {
+    fn advance_yielding(
+        &mut self,
+        mut inp: &[u8],
+        mut out: &mut [u8],
+        yield_on_full: bool,
+    ) -> BufferResult {
+        let state = &mut self.inner;
+
+        if state.has_ended {
+            return BufferResult {
+                consumed_in: 0,
+                consumed_out: 0,
+                status: Ok(LzwStatus::Done),
+            };
+        }
+
+        let o_in = inp.len();
+        let o_out = out.len();
+        let mut status = Ok(LzwStatus::Ok);
+        // If we read a code, such that an empty output buffer still makes progress.
+        let mut decoded_any = false;
+
+        // Drain the rest of a word that did not fit into the previous output buffer.
+        let pending = state.buffer.buffer();
+        let drained = pending.len().min(out.len());
+        out[..drained].copy_from_slice(&pending[..drained]);
+        state.buffer.consume(drained);
+        out = &mut out[drained..];
+
+        // Literals are the alphabet, directly before any special codes.
+        let literal_end: Code = 1 << state.min_size;
+
+        while state.buffer.buffer().is_empty() {
+            // Without YIELD_ON_FULL we decode at least one code, even into an empty buffer.
+            if out.is_empty() && (yield_on_full || decoded_any) {
+                break;
+            }
+
+            let Some(code) = state.next_symbol(&mut inp) else {
+                break;
+            };
+
+            decoded_any = true;
+
+            // Fast path for the most common code, a literal following some other word.
+            if code < literal_end && !out.is_empty() {
+                if let Some(prev) = &state.last {
+                    if !state.table.is_full() {
+                        state.table.derive(prev, code as u8);
+
+                        if state.next_code
+                            >= u32::from(state.code_buffer.max_code() - Code::from(state.is_tiff))
+                            && state.code_buffer.code_size() < state.max_code_size
+                        {
+                            state.bump_code_size();
+                        }
+
+                        state.next_code += 1;
+                    }
+
+                    let target = out.split_off_first_mut().unwrap();
+                    *target = code as u8;
+                    state.last = Some(DerivationBase {
+                        code,
+                        first: code as u8,
+                    });
+
+                    continue;
+                }
+            }
+
+            if Some(code) == state.clear_code {
+                match state.last.take() {
+                    // The first code after a reset.
+                    None => {
+                        state.code_buffer.align_group();
+                        state.init_tables();
+                    }
+                    Some(_) => state.reset_tables(),
+                }
+
+                continue;
+            }
+
+            if Some(code) == state.end_code {
+                state.has_ended = true;
+                status = Ok(LzwStatus::Done);
+                break;
+            }
+
+            let first = match &state.last {
+                // The first code after a reset has no predecessor and derives no entry.
+                None => {
+                    if u32::from(code) >= state.next_code {
+                        status = Err(state.invalid_code(code, o_in - inp.len()));
+                        break;
                     }
 
-                    // A new decoded word.
-                    last_decoded = Some(target);
+                    if state.table.is_empty() && state.implicit_reset {
+                        state.init_tables();
+                    } else if state.table.is_empty() {
+                        status = Err(state.invalid_code(code, o_in - inp.len()));
+                        break;
+                    }
+
+                    state.bump_post_initial_code_size();
+                    state.table.first_of(code)
                 }
+                Some(prev) => {
+                    if u32::from(code) > state.next_code {
+                        status = Err(state.invalid_code(code, o_in - inp.len()));
+                        break;
+                    }
 
-                // Each newly read code creates one new code/link based on the preceding code if we
-                // have enough space to put it there.
-                if !self.table.is_full() {
-                    self.table.derive(&deriv, cha);
+                    // The special case of the `next_code`, its word extends the previous one by
+                    // the first byte of that word (form cScSc).
+                    let first = if u32::from(code) == state.next_code {
+                        prev.first
+                    } else {
+                        state.table.first_of(code)
+                    };
 
-                    if self.next_code >= self.code_buffer.max_code() - Code::from(self.is_tiff)
-                        && self.code_buffer.code_size() < MAX_CODESIZE
-                    {
-                        self.bump_code_size();
+                    if !state.table.is_full() {
+                        state.table.derive(prev, first);
+
+                        if state.next_code
+                            >= u32::from(state.code_buffer.max_code() - Code::from(state.is_tiff))
+                            && state.code_buffer.code_size() < state.max_code_size
+                        {
+                            state.bump_code_size();
+                        }
+
+                        state.next_code += 1;
                     }
 
-                    self.next_code += 1;
+                    first
                 }
+            };
 
-                // store the information on the decoded word.
-                code_link = Some(DerivationBase {
-                    code: new_code,
-                    first: cha,
-                });
+            state.last = Some(DerivationBase { code, first });
+
+            let len = usize::from(state.table.code_len(code));
+            if len > out.len() {
+                // Keep the word and hand out what fits, the rest in the next call.
+                state.buffer.fill_reconstruct(&state.table, code);
+                let pending = state.buffer.buffer();
+                let fits = out.len();
+                out.copy_from_slice(&pending[..fits]);
+                state.buffer.consume(fits);
+                out = &mut [];
+                break;
+            }
 
-                // Can't make any more progress with decoding.
-                //
-                // We have more data buffered but not enough space to put it? We want fetch a next
-                // symbol if possible as in the case of it being a new symbol we can refer to the
-                // buffered output as the source for that symbol's meaning and do a memcpy.
-                //
-                // Since this test is after decoding at least one code, we can now check for an
-                // empty buffer and still guarantee progress when one was passed as a parameter.
-                if is_in_buffer || out.is_empty() {
-                    break;
-                }
+            // As in the burst, overwrite whole chunks past the word when there is room for it.
+            let chunks = len.div_ceil(8);
+            if out.len() - len >= 8 {
+                let relaxed = &mut out.as_chunks_mut::<8>().0[..chunks];
+                state.table.reconstruct_relaxed(code, relaxed);
+            } else {
+                state.table.reconstruct(code, &mut out[..len]);
             }
-        }
 
-        // We need to store the last word into the buffer in case the first code in the next
-        // iteration is the next_code.
-        if let Some(tail) = last_decoded {
-            self.buffer.bytes[..tail.len()].copy_from_slice(tail);
-            self.buffer.write_mark = tail.len();
-            // Mark the full buffer as having been consumed.
-            self.buffer.read_mark = tail.len();
+            out = &mut out[len..];
         }
 
-        // Ensure we don't indicate that no progress was made if we read some bytes from the input
-        // (which is progress). The field `new_value` will have been written if any reconstruction
-        // was buffered. This is mostly a fail-safe in case the above loop does nothing else but
-        // buffer the value, without consuming any of the bytes. (It really should right now but
-        // that may change and it's not structurally required to).
-        if o_in > inp.len() || self.buffer.reconstructed_another_code {
-            if let Ok(LzwStatus::NoProgress) = status {
-                status = Ok(LzwStatus::Ok);
+        let consumed_in = o_in - inp.len();
+        let consumed_out = o_out - out.len();
+
+        if consumed_in == 0 && consumed_out == 0 && !decoded_any {
+            if let Ok(LzwStatus::Ok) = status {
+                status = Ok(LzwStatus::NoProgress);
             }
         }
 
-        // Store the code/link state.
-        self.last = code_link;
+        state.bytes_read += consumed_in as u64;
 
         BufferResult {
-            consumed_in: o_in.wrapping_sub(inp.len()),
-            consumed_out: o_out.wrapping_sub(out.len()),
+            consumed_in,
+            consumed_out,
             status,
         }
     }
 }
 
-impl<C: CodeBuffer, CgC: CodegenConstants> DecodeState<C, CgC> {
-    fn next_symbol(&mut self, inp: &mut &[u8]) -> Option<Code> {
-        self.code_buffer.next_symbol(inp)
-    }
-
-    fn bump_code_size(&mut self) {
-        self.code_buffer.bump_code_size()
-    }
-
-    fn refill_bits(&mut self, inp: &mut &[u8]) {
-        self.code_buffer.refill_bits(inp)
-    }
-}
-
 impl CodeBuffer for MsbBuffer {
     fn new(min_size: u8) -> Self {
         MsbBuffer {
@@ -1281,7 +2595,7 @@ impl CodeBuffer for MsbBuffer {
 
     fn reset(&mut self, min_size: u8) {
         self.code_size = min_size + 1;
-        self.code_mask = (1 << self.code_size) - 1;
+        self.code_mask = Code::MAX >> (16 - self.code_size);
     }
 
     fn next_symbol(&mut self, inp: &mut &[u8]) -> Option<Code> {
@@ -1305,6 +2619,8 @@ impl CodeBuffer for MsbBuffer {
         self.code_mask = (self.code_mask << 1) | 1;
     }
 
+    fn align_group(&mut self) {}
+
     fn refill_bits(&mut self, inp: &mut &[u8]) {
         let wish_count = (64 - self.bits) / 8;
         let mut buffer = [0u8; 8];
@@ -1363,6 +2679,13 @@ impl CodeBuffer for MsbBuffer {
         self.bits = self.bits.wrapping_sub(bits);
     }
 
+    fn drop_bits(&mut self, bits: u8) -> u8 {
+        let bits = bits.min(self.bits);
+        self.bit_buffer = self.bit_buffer.checked_shl(bits.into()).unwrap_or(0);
+        self.bits -= bits;
+        bits
+    }
+
     fn max_code(&self) -> Code {
         self.code_mask
     }
@@ -1370,6 +2693,20 @@ impl CodeBuffer for MsbBuffer {
     fn code_size(&self) -> u8 {
         self.code_size
     }
+
+    fn buffered_bits(&self) -> u8 {
+        self.bits
+    }
+
+    #[cfg(feature = "serde")]
+    fn save(&self) -> snapshot::Bits {
+        self.save_bits()
+    }
+
+    #[cfg(feature = "serde")]
+    fn load(&mut self, bits: &snapshot::Bits) -> Result<(), &'static str> {
+        self.load_bits(bits)
+    }
 }
 
 impl CodeBuffer for LsbBuffer {
@@ -1384,7 +2721,7 @@ impl CodeBuffer for LsbBuffer {
 
     fn reset(&mut self, min_size: u8) {
         self.code_size = min_size + 1;
-        self.code_mask = (1 << self.code_size) - 1;
+        self.code_mask = Code::MAX >> (16 - self.code_size);
     }
 
     fn next_symbol(&mut self, inp: &mut &[u8]) -> Option<Code> {
@@ -1408,6 +2745,8 @@ impl CodeBuffer for LsbBuffer {
         self.code_mask = (self.code_mask << 1) | 1;
     }
 
+    fn align_group(&mut self) {}
+
     fn refill_bits(&mut self, inp: &mut &[u8]) {
         let wish_count = (64 - self.bits) / 8;
         let mut buffer = [0u8; 8];
@@ -1464,6 +2803,13 @@ impl CodeBuffer for LsbBuffer {
         self.bits = self.bits.wrapping_sub(bits);
     }
 
+    fn drop_bits(&mut self, bits: u8) -> u8 {
+        let bits = bits.min(self.bits);
+        self.bit_buffer = self.bit_buffer.checked_shr(bits.into()).unwrap_or(0);
+        self.bits -= bits;
+        bits
+    }
+
     fn max_code(&self) -> Code {
         self.code_mask
     }
@@ -1471,12 +2817,127 @@ impl CodeBuffer for LsbBuffer {
     fn code_size(&self) -> u8 {
         self.code_size
     }
+
+    fn buffered_bits(&self) -> u8 {
+        self.bits
+    }
+
+    #[cfg(feature = "serde")]
+    fn save(&self) -> snapshot::Bits {
+        self.save_bits()
+    }
+
+    #[cfg(feature = "serde")]
+    fn load(&mut self, bits: &snapshot::Bits) -> Result<(), &'static str> {
+        self.load_bits(bits)
+    }
+}
+
+impl<B: CodeBuffer> GroupBuffer<B> {
+    /// Skip pending padding bits, returning if all of them have been skipped.
+    fn skip_padding(&mut self, inp: &mut &[u8]) -> bool {
+        loop {
+            self.skip -= self.inner.drop_bits(self.skip);
+
+            if self.skip == 0 {
+                return true;
+            }
+
+            if inp.is_empty() {
+                return false;
+            }
+
+            self.inner.refill_bits(inp);
+        }
+    }
+}
+
+impl<B: CodeBuffer> CodeBuffer for GroupBuffer<B> {
+    fn new(min_size: u8) -> Self {
+        GroupBuffer {
+            inner: B::new(min_size),
+            codes: 0,
+            skip: 0,
+        }
+    }
+
+    fn reset(&mut self, min_size: u8) {
+        self.inner.reset(min_size);
+    }
+
+    fn bump_code_size(&mut self) {
+        self.align_group();
+        self.inner.bump_code_size();
+    }
+
+    fn align_group(&mut self) {
+        // At most 7 codes of at most 16 bits each, this fits.
+        let remaining = (8 - self.codes) % 8;
+        self.skip += remaining * self.inner.code_size();
+        self.codes = 0;
+    }
+
+    fn drop_bits(&mut self, bits: u8) -> u8 {
+        self.inner.drop_bits(bits)
+    }
+
+    fn next_symbol(&mut self, inp: &mut &[u8]) -> Option<Code> {
+        if !self.skip_padding(inp) {
+            return None;
+        }
+
+        let code = self.inner.next_symbol(inp)?;
+        self.codes = (self.codes + 1) % 8;
+        Some(code)
+    }
+
+    fn refill_bits(&mut self, inp: &mut &[u8]) {
+        if self.skip_padding(inp) {
+            self.inner.refill_bits(inp);
+        }
+    }
+
+    fn peek_bits(&self, code: &mut [Code; BURST]) -> usize {
+        if self.skip > 0 {
+            return 0;
+        }
+
+        self.inner.peek_bits(code)
+    }
+
+    fn consume_bits(&mut self, code_cnt: u8) {
+        self.codes = (self.codes + code_cnt) % 8;
+        self.inner.consume_bits(code_cnt);
+    }
+
+    fn max_code(&self) -> Code {
+        self.inner.max_code()
+    }
+
+    fn code_size(&self) -> u8 {
+        self.inner.code_size()
+    }
+
+    fn buffered_bits(&self) -> u8 {
+        self.inner.buffered_bits()
+    }
+
+    #[cfg(feature = "serde")]
+    fn save(&self) -> snapshot::Bits {
+        self.save_bits()
+    }
+
+    #[cfg(feature = "serde")]
+    fn load(&mut self, bits: &snapshot::Bits) -> Result<(), &'static str> {
+        self.load_bits(bits)
+    }
 }
 
 impl Buffer {
-    fn new() -> Self {
+    /// Create a buffer that holds the longest word of a table with that many entries.
+    fn new(entries: usize) -> Self {
         Buffer {
-            bytes: vec![0; MAX_ENTRIES].into_boxed_slice(),
+            bytes: vec![0; entries].into_boxed_slice(),
             read_mark: 0,
             write_mark: 0,
             reconstructed_another_code: false,
@@ -1496,7 +2957,7 @@ impl Buffer {
     }
 
     // Fill the buffer by decoding from the table
-    fn fill_reconstruct(&mut self, table: &Table, code: Code) -> u8 {
+    fn fill_reconstruct(&mut self, table: &impl DecodeTable, code: Code) -> u8 {
         self.write_mark = 0;
         self.read_mark = 0;
         let depth = table.code_len(code);
@@ -1516,35 +2977,80 @@ impl Buffer {
     }
 }
 
-impl Table {
-    fn new() -> Self {
-        Table {
+// Only the entries in use are copied, without the large arrays passing through the stack.
+impl<const N: usize> Clone for ChunkedTable<N> {
+    fn clone(&self) -> Self {
+        let mut table = Self::new(self.entries);
+        let used = self.len.min(N);
+        table.suffixes[..used].copy_from_slice(&self.suffixes[..used]);
+        table.chain[..used].copy_from_slice(&self.chain[..used]);
+        table.depths[..used].copy_from_slice(&self.depths[..used]);
+        table.len = self.len;
+        table
+    }
+}
+
+impl<const N: usize> ChunkedTable<N> {
+    /// Mask for indexing into the fixed-size arrays. Since `N` is a power of two, `idx & Self::MASK`
+    /// is guaranteed < N. LLVM can prove this for `[T; N]` arrays, eliminating bounds checks.
+    /// Corrupt `prev` values wrap to a valid index instead of panicking.
+    const MASK: usize = N - 1;
+
+    fn non_memcpy(out: &mut [u8], from: [u8; STREAMING_Q]) {
+        match out.len() {
+            0 => {}
+            1 => out[0] = from[0],
+            2 => out[..2].copy_from_slice(&from[..2]),
+            3 => out[..3].copy_from_slice(&from[..3]),
+            4 => out[..4].copy_from_slice(&from[..4]),
+            5 => out[..5].copy_from_slice(&from[..5]),
+            6 => out[..6].copy_from_slice(&from[..6]),
+            7 => out[..7].copy_from_slice(&from[..7]),
+            8 => out[..8].copy_from_slice(&from[..8]),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<const N: usize> DecodeTable for ChunkedTable<N> {
+    fn new(entries: usize) -> Self {
+        debug_assert!(N.is_power_of_two() && entries <= N);
+        ChunkedTable {
             suffixes: boxed_arr(),
             chain: boxed_arr(),
             depths: boxed_arr(),
             len: 0,
+            entries,
         }
     }
 
-    fn clear(&mut self, min_size: u8) {
-        self.len = usize::from(1u16 << u16::from(min_size)) + 2;
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn entries(&self) -> usize {
+        self.entries
+    }
+
+    fn clear(&mut self, first_code: Code) {
+        self.len = usize::from(first_code);
     }
 
-    fn init(&mut self, min_size: u8) {
+    fn init(&mut self, min_size: u8, first_code: Code) {
         self.len = 0;
         for i in 0..(1u16 << u16::from(min_size)) {
-            let idx = self.len & MASK;
+            let idx = self.len & Self::MASK;
             self.suffixes[idx] = [i as u8, 0, 0, 0, 0, 0, 0, 0];
             self.chain[idx] = Link::base(i as u8);
             self.depths[idx] = 1;
             self.len += 1;
         }
-        // Clear code + End code: skip writing when the masked index would
-        // alias an alphabet entry (happens at min_size=12 where clear=4096
-        // wraps to index 0).
-        for _ in 0..2 {
-            if self.len < MAX_ENTRIES {
-                let idx = self.len & MASK;
+        // Special codes: skip writing when the masked index would alias an
+        // alphabet entry (happens at min_size=12 where clear=4096 wraps to
+        // index 0).
+        while self.len < usize::from(first_code) {
+            if self.len < N {
+                let idx = self.len & Self::MASK;
                 self.chain[idx] = Link::base(0);
                 self.depths[idx] = 0;
             }
@@ -1553,26 +3059,28 @@ impl Table {
     }
 
     fn first_of(&self, code: Code) -> u8 {
-        self.chain[usize::from(code) & MASK].first
+        self.chain[usize::from(code) & Self::MASK].first
     }
 
     fn code_len(&self, code: Code) -> u16 {
-        self.depths[usize::from(code) & MASK]
+        self.depths[usize::from(code) & Self::MASK]
     }
 
-    fn is_empty(&self) -> bool {
-        self.len == 0
+    #[cfg(feature = "serde")]
+    fn save(&self, first_code: Code) -> Vec<snapshot::Entry> {
+        self.save_entries(first_code)
     }
 
-    fn is_full(&self) -> bool {
-        self.len >= MAX_ENTRIES
+    #[cfg(feature = "serde")]
+    fn load(&mut self, entries: &[snapshot::Entry]) -> Result<(), &'static str> {
+        self.load_entries(entries)
     }
 
     fn derive(&mut self, from: &DerivationBase, byte: u8) {
-        debug_assert!(self.len < MAX_ENTRIES);
-        let idx = self.len & MASK;
+        debug_assert!(self.len < self.entries);
+        let idx = self.len & Self::MASK;
 
-        let parent = usize::from(from.code) & MASK;
+        let parent = usize::from(from.code) & Self::MASK;
         let parent_depth = self.depths[parent];
         let pos = parent_depth as usize & (STREAMING_Q - 1);
         let mut link = from.derive();
@@ -1591,23 +3099,9 @@ impl Table {
         self.len += 1;
     }
 
-    fn derive_burst(&mut self, from: &mut DerivationBase, burst: &[Code], first: &[u8]) {
-        // A burst is constrained to the number of possible codes before a size switch, however at
-        // the maximum possible size we *never* perform a switch again (until reset) and so the
-        // size of the burst is not constrained. But we must not derive any of those additional
-        // codes which would wrap into the start and overwrite entries for codes 0-5.
-        let max = MAX_ENTRIES - self.len;
-
-        for (&code, &first_byte) in burst.iter().zip(first.iter()).take(max) {
-            self.derive(from, first_byte);
-            from.code = code;
-            from.first = first_byte;
-        }
-    }
-
     fn reconstruct(&self, code: Code, out: &mut [u8]) -> u8 {
         let o = out.len();
-        let code_index = usize::from(code) & MASK;
+        let code_index = usize::from(code) & Self::MASK;
         let suffix = self.suffixes[code_index];
 
         // Short path: whole value fits in one Q-chunk.
@@ -1629,7 +3123,7 @@ impl Table {
         // so LLVM compiles copy_from_slice to a single qword move with no
         // bounds check. The `.rev()` walks from end to start.
         for chunk in out[..tail_start].chunks_exact_mut(STREAMING_Q).rev() {
-            let code_index = usize::from(c) & MASK;
+            let code_index = usize::from(c) & Self::MASK;
             chunk.copy_from_slice(&self.suffixes[code_index]);
             c = self.chain[code_index].previous_code();
         }
@@ -1637,8 +3131,8 @@ impl Table {
         first
     }
 
-    fn reconstruct_simple(&self, code: Code, out: &mut [[u8; 8]]) -> u8 {
-        let code_index = usize::from(code) & MASK;
+    fn reconstruct_relaxed(&self, code: Code, out: &mut [[u8; 8]]) -> u8 {
+        let code_index = usize::from(code) & Self::MASK;
         let suffix = self.suffixes[code_index];
 
         let Some((last, prefix)) = out.split_last_mut() else {
@@ -1661,27 +3155,114 @@ impl Table {
         // so LLVM compiles copy_from_slice to a single qword move with no
         // bounds check. The `.rev()` walks from end to start.
         for chunk in prefix.iter_mut().rev() {
-            let code_index = usize::from(c) & MASK;
+            let code_index = usize::from(c) & Self::MASK;
             *chunk = self.suffixes[code_index];
             c = self.chain[code_index].previous_code();
         }
 
         first
     }
+}
 
-    fn non_memcpy(out: &mut [u8], from: [u8; STREAMING_Q]) {
-        match out.len() {
-            0 => {}
-            1 => out[0] = from[0],
-            2 => out[..2].copy_from_slice(&from[..2]),
-            3 => out[..3].copy_from_slice(&from[..3]),
-            4 => out[..4].copy_from_slice(&from[..4]),
-            5 => out[..5].copy_from_slice(&from[..5]),
-            6 => out[..6].copy_from_slice(&from[..6]),
-            7 => out[..7].copy_from_slice(&from[..7]),
-            8 => out[..8].copy_from_slice(&from[..8]),
-            _ => unreachable!(),
+impl<const N: usize> ClassicTable<N> {
+    /// Mask for indexing into the fixed-size arrays, see `ChunkedTable::MASK`.
+    const MASK: usize = N - 1;
+}
+
+impl<const N: usize> Clone for ClassicTable<N> {
+    fn clone(&self) -> Self {
+        let mut table = Self::new(self.entries);
+        let used = self.len.min(N);
+        table.bytes[..used].copy_from_slice(&self.bytes[..used]);
+        table.chain[..used].copy_from_slice(&self.chain[..used]);
+        table.depths[..used].copy_from_slice(&self.depths[..used]);
+        table.len = self.len;
+        table
+    }
+}
+
+impl<const N: usize> DecodeTable for ClassicTable<N> {
+    fn new(entries: usize) -> Self {
+        debug_assert!(N.is_power_of_two() && entries <= N);
+        ClassicTable {
+            bytes: boxed_arr(),
+            chain: boxed_arr(),
+            depths: boxed_arr(),
+            len: 0,
+            entries,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn entries(&self) -> usize {
+        self.entries
+    }
+
+    fn clear(&mut self, first_code: Code) {
+        self.len = usize::from(first_code);
+    }
+
+    fn init(&mut self, min_size: u8, first_code: Code) {
+        self.len = 0;
+        for i in 0..(1u16 << u16::from(min_size)) {
+            let idx = self.len & Self::MASK;
+            self.bytes[idx] = i as u8;
+            self.chain[idx] = Link::base(i as u8);
+            self.depths[idx] = 1;
+            self.len += 1;
+        }
+        // Special codes, see `ChunkedTable::init` on the aliasing at the largest symbol size.
+        while self.len < usize::from(first_code) {
+            if self.len < N {
+                let idx = self.len & Self::MASK;
+                self.chain[idx] = Link::base(0);
+                self.depths[idx] = 0;
+            }
+            self.len += 1;
+        }
+    }
+
+    fn first_of(&self, code: Code) -> u8 {
+        self.chain[usize::from(code) & Self::MASK].first
+    }
+
+    fn code_len(&self, code: Code) -> u16 {
+        self.depths[usize::from(code) & Self::MASK]
+    }
+
+    #[cfg(feature = "serde")]
+    fn save(&self, first_code: Code) -> Vec<snapshot::Entry> {
+        self.save_entries(first_code)
+    }
+
+    #[cfg(feature = "serde")]
+    fn load(&mut self, entries: &[snapshot::Entry]) -> Result<(), &'static str> {
+        self.load_entries(entries)
+    }
+
+    fn derive(&mut self, from: &DerivationBase, byte: u8) {
+        debug_assert!(self.len < self.entries);
+        let idx = self.len & Self::MASK;
+        let parent = usize::from(from.code) & Self::MASK;
+
+        self.bytes[idx] = byte;
+        self.chain[idx] = from.derive();
+        self.depths[idx] = self.depths[parent] + 1;
+        self.len += 1;
+    }
+
+    fn reconstruct(&self, code: Code, out: &mut [u8]) -> u8 {
+        let mut code_iter = code;
+        for ch in out.iter_mut().rev() {
+            let idx = usize::from(code_iter) & Self::MASK;
+            *ch = self.bytes[idx];
+            code_iter = self.chain[idx].previous_code();
         }
+
+        self.first_of(code)
     }
 }
 
@@ -1820,8 +3401,10 @@ mod tests {
 
     #[test]
     fn table_derive() {
-        let mut table = super::Table::new();
-        table.init(8);
+        use super::DecodeTable;
+
+        let mut table = super::ChunkedTable::<{ crate::MAX_ENTRIES }>::new(crate::MAX_ENTRIES);
+        table.init(8, 258);
 
         let mut base = super::DerivationBase {
             code: 1,
@@ -0,0 +1,56 @@
+//! Decode many independent streams on several threads.
+//!
+//! Formats such as TIFF and GIF split an image into many short streams, strips and frames, whose
+//! decoded size is known in advance. Decoding them one by one spends a notable share of the time
+//! on setting up decoders. Here each thread instead builds a single decoder and [`reset`]s it
+//! between the streams it decodes.
+//!
+//! The threads and their decoders only live for the duration of one call. Pass all streams of an
+//! image, or of several images, in a single call to make the most of them.
+//!
+//! # Examples
+//!
+//! ```
+//! use weezl::{BitOrder, decode::{batch, Configuration}, encode::Encoder};
+//!
+//! let strips: Vec<Vec<u8>> = (0..64u8).map(|i| vec![i; 1000]).collect();
+//! let encoded: Vec<Vec<u8>> = strips
+//!     .iter()
+//!     .map(|strip| Encoder::new(BitOrder::Msb, 8).encode(strip).unwrap())
+//!     .collect();
+//!
+//! let mut decoded = vec![vec![0; 1000]; 64];
+//! let jobs = encoded.iter().map(|data| &data[..]).zip(decoded.iter_mut().map(|out| &mut out[..]));
+//! let results = batch::decode_exact(&Configuration::new(BitOrder::Msb, 8), jobs);
+//!
+//! assert!(results.iter().all(|result| result.is_ok()));
+//! assert_eq!(decoded, strips);
+//! ```
+//!
+//! [`reset`]: ../struct.Decoder.html#method.reset
+use super::Configuration;
+use crate::alloc::vec::Vec;
+use crate::error::LzwError;
+use crate::jobs;
+
+/// Decode streams of known decoded size on all available threads.
+///
+/// Each job is a pair of the encoded data and the buffer for its decoded data, which is filled
+/// as with [`Decoder::decode_exact`]. The results are returned in the order of the jobs. Jobs are
+/// handed out one at a time so that threads stay busy when the streams differ in size. The threads
+/// are spawned for this call and end with it.
+///
+/// [`Decoder::decode_exact`]: ../struct.Decoder.html#method.decode_exact
+pub fn decode_exact<'a, I>(configuration: &Configuration, jobs: I) -> Vec<Result<usize, LzwError>>
+where
+    I: IntoIterator<Item = (&'a [u8], &'a mut [u8])>,
+{
+    jobs::run(
+        jobs.into_iter().collect(),
+        || configuration.clone().build(),
+        |decoder, (inp, out)| {
+            decoder.reset();
+            decoder.decode_exact(inp, out)
+        },
+    )
+}
@@ -0,0 +1,129 @@
+//! Read codes of any width, with the bit packing of the decoder.
+use super::{CodeBuffer, LsbBuffer, MsbBuffer};
+use crate::{BitOrder, Code};
+
+/// Reads codes of arbitrary width from a slice of packed bits.
+///
+/// This unpacks bits exactly as the decoder does, in either bit order, but leaves the meaning and
+/// the width of each code to the caller. It is meant for formats related to LZW which the decoder
+/// does not support, and for inspecting test data.
+///
+/// # Examples
+///
+/// ```
+/// use weezl::{BitOrder, decode::CodeReader};
+///
+/// let mut reader = CodeReader::new(BitOrder::Msb, &[0b1010_0000, 0xff]);
+/// assert_eq!(reader.read_code(3), Some(0b101));
+/// reader.align_to_byte();
+/// assert_eq!(reader.bit_position(), 8);
+/// assert_eq!(reader.read_code(8), Some(0xff));
+/// assert_eq!(reader.read_code(1), None);
+/// ```
+pub struct CodeReader<'d> {
+    inp: &'d [u8],
+    buffer: ReadBuffer,
+    /// The number of bytes moved into the buffer so far.
+    bytes_read: u64,
+}
+
+enum ReadBuffer {
+    Msb(MsbBuffer),
+    Lsb(LsbBuffer),
+}
+
+impl<'d> CodeReader<'d> {
+    /// Create a reader for the bits of `data` in the given bit order.
+    pub fn new(order: BitOrder, data: &'d [u8]) -> Self {
+        let buffer = match order {
+            BitOrder::Msb => ReadBuffer::Msb(MsbBuffer::new(0)),
+            BitOrder::Lsb => ReadBuffer::Lsb(LsbBuffer::new(0)),
+        };
+
+        CodeReader {
+            inp: data,
+            buffer,
+            bytes_read: 0,
+        }
+    }
+
+    /// Read the next code with `width` bits.
+    ///
+    /// Returns `None` if fewer bits remain. These bits are not consumed and can still be read as
+    /// narrower codes.
+    ///
+    /// # Panics
+    ///
+    /// The `width` needs to be in the interval `1..=16`.
+    pub fn read_code(&mut self, width: u8) -> Option<u16> {
+        fn read<C: CodeBuffer>(buffer: &mut C, inp: &mut &[u8], width: u8) -> Option<Code> {
+            if buffer.code_size() != width {
+                buffer.reset(width - 1);
+            }
+
+            buffer.next_symbol(inp)
+        }
+
+        crate::assert_code_width(width);
+        let o_in = self.inp.len();
+        let code = match &mut self.buffer {
+            ReadBuffer::Msb(buffer) => read(buffer, &mut self.inp, width),
+            ReadBuffer::Lsb(buffer) => read(buffer, &mut self.inp, width),
+        };
+        self.bytes_read += (o_in - self.inp.len()) as u64;
+        code
+    }
+
+    /// Skip the bits up to the next byte boundary.
+    pub fn align_to_byte(&mut self) {
+        // The buffer is filled by whole bytes, so the partial byte is its remainder.
+        match &mut self.buffer {
+            ReadBuffer::Msb(buffer) => buffer.drop_bits(buffer.buffered_bits() % 8),
+            ReadBuffer::Lsb(buffer) => buffer.drop_bits(buffer.buffered_bits() % 8),
+        };
+    }
+
+    /// Skip the next `bits` bits, such as padding between codes.
+    ///
+    /// Returns `false` if fewer bits remain, all of which are skipped.
+    pub fn skip(&mut self, mut bits: u64) -> bool {
+        while bits > 0 {
+            let width = bits.min(16) as u8;
+            if self.read_code(width).is_none() {
+                // Skip the rest bit by bit, as it is shorter than the code.
+                while self.read_code(1).is_some() {}
+                return false;
+            }
+            bits -= u64::from(width);
+        }
+
+        true
+    }
+
+    /// Skip the bits up to the next multiple of `boundary` bits, counted from the start.
+    ///
+    /// Returns `false` if fewer bits remain, all of which are skipped. Groups that do not start at
+    /// such a multiple, as the groups of eight codes in `compress` after a change of the code
+    /// size, can be completed with [`skip`] instead.
+    ///
+    /// # Panics
+    ///
+    /// The `boundary` must not be `0`.
+    ///
+    /// [`skip`]: #method.skip
+    pub fn align_to(&mut self, boundary: u64) -> bool {
+        assert_ne!(boundary, 0, "Attempted to align to a boundary of zero bits");
+        let position = self.bit_position();
+        self.skip((boundary - position % boundary) % boundary)
+    }
+
+    /// The number of bits read or skipped so far.
+    pub fn bit_position(&self) -> u64 {
+        let buffered = match &self.buffer {
+            ReadBuffer::Msb(buffer) => buffer.buffered_bits(),
+            ReadBuffer::Lsb(buffer) => buffer.buffered_bits(),
+        };
+
+        self.bytes_read * 8 - u64::from(buffered)
+    }
+}
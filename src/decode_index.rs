@@ -0,0 +1,304 @@
+//! Random access into a decoded stream through checkpoints of the decoder state.
+use super::{read_decoded, Decoder};
+use crate::alloc::{vec, vec::Vec};
+use crate::error::LzwError;
+use crate::STREAM_BUF_SIZE;
+
+use core::convert::TryFrom;
+
+use std::io::{self, BufRead, Read, Seek, SeekFrom};
+
+/// Checkpoints into an encoded stream, to start decoding at any offset of the decoded data.
+///
+/// An index is built by decoding the stream once. Every `interval` bytes of decoded data it
+/// records the number of bytes consumed from the input, together with a copy of the decoder state
+/// at that point. This covers the bits of a code that were read but not yet decoded and a word
+/// that was only partially written, so decoding resumes exactly at the recorded offset. The
+/// [`IndexedReader`] then implements `Seek` by continuing from the closest checkpoint before the
+/// target.
+///
+/// Each checkpoint holds the complete code table, which takes tens of kilobytes and more for
+/// larger code sizes. Choose an interval that is large in comparison, such as a megabyte.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::{Cursor, Read, Seek, SeekFrom};
+/// use weezl::{BitOrder, decode::{Decoder, Index, IndexedReader}, encode::Encoder};
+///
+/// let data: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8 ^ (i / 1000) as u8).collect();
+/// let encoded = Encoder::new(BitOrder::Msb, 8).encode(&data).unwrap();
+///
+/// let index = Index::build(Decoder::new(BitOrder::Msb, 8), 4096, &encoded[..]).unwrap();
+/// assert_eq!(index.len(), data.len() as u64);
+///
+/// let mut reader = IndexedReader::new(&index, Cursor::new(&encoded)).unwrap();
+/// reader.seek(SeekFrom::Start(70_000)).unwrap();
+/// let mut part = [0; 100];
+/// reader.read_exact(&mut part).unwrap();
+/// assert_eq!(part, data[70_000..70_100]);
+/// ```
+///
+/// [`IndexedReader`]: struct.IndexedReader.html
+pub struct Index {
+    checkpoints: Vec<Checkpoint>,
+    interval: u64,
+    /// The length of the decoded data.
+    len: u64,
+}
+
+struct Checkpoint {
+    /// The offset in the decoded data.
+    output: u64,
+    /// The number of bytes of input consumed by the decoder.
+    input: u64,
+    decoder: Decoder,
+}
+
+/// A reader of decoded data that can seek, with the help of an [`Index`].
+///
+/// See [`Index`] for an example.
+///
+/// [`Index`]: struct.Index.html
+pub struct IndexedReader<'i, R> {
+    index: &'i Index,
+    reader: R,
+    /// The position of the stream start in `reader`.
+    start: u64,
+    decoder: Decoder,
+    error: Option<LzwError>,
+    /// The offset in the decoded data up to which `decoder` has decoded.
+    decoded: u64,
+    /// The offset in the decoded data that is read next.
+    position: u64,
+}
+
+/// Counts the bytes consumed from a reader.
+struct Counting<R> {
+    inner: R,
+    consumed: u64,
+}
+
+impl Index {
+    /// Build an index by decoding all data from `reader` with `decoder`.
+    ///
+    /// The decoder should be freshly constructed, its configuration is used for all decoding based
+    /// on this index. Decoding ends with the stream as for [`Decoder::into_read`] and all errors
+    /// it returns are returned here.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `interval` is `0`.
+    ///
+    /// [`Decoder::into_read`]: struct.Decoder.html#method.into_read
+    pub fn build(mut decoder: Decoder, interval: u64, reader: impl BufRead) -> io::Result<Self> {
+        assert_ne!(interval, 0, "Attempted to use an empty checkpoint interval");
+
+        let mut checkpoints = vec![Checkpoint {
+            output: 0,
+            input: 0,
+            decoder: decoder.clone(),
+        }];
+
+        let mut reader = Counting {
+            inner: reader,
+            consumed: 0,
+        };
+        let mut error = None;
+        // The decoded data is discarded, so a small interval needs no more than that.
+        let size = usize::try_from(interval).map_or(STREAM_BUF_SIZE, |n| n.min(STREAM_BUF_SIZE));
+        let mut buffer = vec![0; size];
+        let mut decoded = 0;
+
+        loop {
+            // Stop exactly at the next checkpoint.
+            let room = interval - decoded % interval;
+            let len = buffer
+                .len()
+                .min(usize::try_from(room).unwrap_or(usize::MAX));
+            let count = read_decoded(&mut decoder, &mut reader, &mut error, &mut buffer[..len])?;
+
+            if count == 0 {
+                break;
+            }
+
+            decoded += count as u64;
+            if decoded % interval == 0 {
+                checkpoints.push(Checkpoint {
+                    output: decoded,
+                    input: reader.consumed,
+                    decoder: decoder.clone(),
+                });
+            }
+        }
+
+        if let Some(err) = error {
+            return Err(err.into());
+        }
+
+        Ok(Index {
+            checkpoints,
+            interval,
+            len: decoded,
+        })
+    }
+
+    /// The length of the decoded data.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Check if the decoded data is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The distance of checkpoints in the decoded data.
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+
+    /// The last checkpoint at or before an offset of the decoded data.
+    fn checkpoint(&self, offset: u64) -> &Checkpoint {
+        // There is always a checkpoint at offset `0`.
+        let idx = self
+            .checkpoints
+            .partition_point(|point| point.output <= offset);
+        &self.checkpoints[idx - 1]
+    }
+}
+
+impl<'i, R: BufRead + Seek> IndexedReader<'i, R> {
+    /// Create a reader of the stream that `index` was built from.
+    ///
+    /// The `reader` must be positioned at the start of the encoded stream, and it must contain the
+    /// same data that was used to build the index. Positions are relative to this start.
+    pub fn new(index: &'i Index, mut reader: R) -> io::Result<Self> {
+        let start = reader.stream_position()?;
+        let decoder = index.checkpoints[0].decoder.clone();
+
+        Ok(IndexedReader {
+            index,
+            reader,
+            start,
+            decoder,
+            error: None,
+            decoded: 0,
+            position: 0,
+        })
+    }
+
+    /// Get a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Unwrap the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Move the decoder to the current position.
+    ///
+    /// Decoding continues from the current state if no checkpoint lies in between, otherwise from
+    /// the closest checkpoint.
+    fn catch_up(&mut self) -> io::Result<()> {
+        let checkpoint = self.index.checkpoint(self.position);
+        let resume = self.error.is_none()
+            && checkpoint.output <= self.decoded
+            && self.decoded <= self.position;
+
+        if !resume {
+            self.reader
+                .seek(SeekFrom::Start(self.start + checkpoint.input))?;
+            self.decoder = checkpoint.decoder.clone();
+            self.error = None;
+            self.decoded = checkpoint.output;
+        }
+
+        let mut scratch = [0; 1 << 12];
+        while self.decoded < self.position {
+            let room = self.position - self.decoded;
+            let len = scratch
+                .len()
+                .min(usize::try_from(room).unwrap_or(usize::MAX));
+            let count = read_decoded(
+                &mut self.decoder,
+                &mut self.reader,
+                &mut self.error,
+                &mut scratch[..len],
+            )?;
+
+            if count == 0 {
+                return Err(io::Error::from(LzwError::UnexpectedEof));
+            }
+
+            self.decoded += count as u64;
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: BufRead + Seek> Read for IndexedReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.index.len {
+            return Ok(0);
+        }
+
+        if self.decoded != self.position {
+            self.catch_up()?;
+        }
+
+        let count = read_decoded(&mut self.decoder, &mut self.reader, &mut self.error, buf)?;
+        self.decoded += count as u64;
+        self.position = self.decoded;
+        Ok(count)
+    }
+}
+
+impl<R: BufRead + Seek> Seek for IndexedReader<'_, R> {
+    /// Seek in the decoded data.
+    ///
+    /// This only moves the position, decoding to it is deferred to the next `read`.
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(offset) => {
+                self.position = offset;
+                return Ok(offset);
+            }
+            SeekFrom::Current(offset) => (self.position, offset),
+            SeekFrom::End(offset) => (self.index.len, offset),
+        };
+
+        match base.checked_add_signed(offset) {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+impl<R: Read> Read for Counting<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.consumed += count as u64;
+        Ok(count)
+    }
+}
+
+impl<R: BufRead> BufRead for Counting<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.consumed += amt as u64;
+        self.inner.consume(amt);
+    }
+}
@@ -1,6 +1,6 @@
 use crate::decode::IntoAsync;
-use crate::error::LzwStatus;
 use crate::error::StreamResult;
+use crate::error::{LzwError, LzwStatus};
 use crate::StreamBuf;
 use std::io;
 
@@ -13,6 +13,11 @@ impl<'d, W: futures::io::AsyncWrite + core::marker::Unpin> IntoAsync<'d, W> {
     }
 
     /// Decode data from a reader, requiring an end marker.
+    ///
+    /// A stream that ends without one is accepted only as permitted by the configured
+    /// [`EndMarker`] policy. All decoded data is written to the writer, even on an error.
+    ///
+    /// [`EndMarker`]: enum.EndMarker.html
     pub async fn decode_all(mut self, read: impl futures::io::AsyncBufRead) -> StreamResult {
         self.decode_part(read, true).await
     }
@@ -92,14 +97,17 @@ impl<'d, W: futures::io::AsyncWrite + core::marker::Unpin> IntoAsync<'d, W> {
             *write_bytes += result.consumed_out;
             read.as_mut().consume(result.consumed_in);
 
+            // Write the decoded data first, it is kept even when the status is an error.
+            match writer.write_all(&outbuf[..result.consumed_out]).await {
+                Ok(_) => {}
+                Err(err) => break Err(err),
+            }
+
             // Handle an error status in the result.
             let status = match result.status {
                 Ok(ok) => ok,
                 Err(err) => {
-                    break Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        &*format!("{:?}", err),
-                    ));
+                    break Err(io::Error::from(err));
                 }
             };
 
@@ -110,25 +118,13 @@ impl<'d, W: futures::io::AsyncWrite + core::marker::Unpin> IntoAsync<'d, W> {
                     "No progress means we have not decoded any data"
                 );
                 // In particular we did not finish decoding.
-                if must_finish {
-                    break Err(io::Error::new(
-                        io::ErrorKind::UnexpectedEof,
-                        "No more data but no end marker detected",
-                    ));
+                if must_finish && !decoder.may_end() {
+                    break Err(io::Error::from(LzwError::UnexpectedEof));
                 } else {
                     break Ok(());
                 }
             }
 
-            // And finish by writing our result.
-            // TODO: we may lose data on error (also on status error above) which we might want to
-            // deterministically handle so that we don't need to restart everything from scratch as
-            // the only recovery strategy. Any changes welcome.
-            match writer.write_all(&outbuf[..result.consumed_out]).await {
-                Ok(_) => {}
-                Err(err) => break Err(err),
-            }
-
             if let LzwStatus::Done = status {
                 break Ok(());
             }
@@ -0,0 +1,229 @@
+use crate::decode::{IntoTokioAsync, IntoTokioRead};
+use crate::error::StreamResult;
+use crate::error::{LzwError, LzwStatus};
+use crate::StreamBuf;
+use core::pin::Pin;
+use core::task::{ready, Context, Poll};
+use std::io;
+
+impl<'d, W: tokio::io::AsyncWrite + core::marker::Unpin> IntoTokioAsync<'d, W> {
+    /// Decode data from a reader.
+    ///
+    /// This will read data until the stream is empty or an end marker is reached.
+    pub async fn decode(&mut self, read: impl tokio::io::AsyncBufRead) -> StreamResult {
+        self.decode_part(read, false).await
+    }
+
+    /// Decode data from a reader, requiring an end marker.
+    ///
+    /// A stream that ends without one is accepted only as permitted by the configured
+    /// [`EndMarker`] policy. All decoded data is written to the writer, even on an error.
+    ///
+    /// [`EndMarker`]: enum.EndMarker.html
+    pub async fn decode_all(mut self, read: impl tokio::io::AsyncBufRead) -> StreamResult {
+        self.decode_part(read, true).await
+    }
+
+    /// Set the size of the intermediate decode buffer.
+    ///
+    /// A buffer of this size is allocated to hold one part of the decoded stream when no buffer is
+    /// available and any decoding method is called. No buffer is allocated if `set_buffer` has
+    /// been called. The buffer is reused.
+    ///
+    /// # Panics
+    /// This method panics if `size` is `0`.
+    pub fn set_buffer_size(&mut self, size: usize) {
+        assert_ne!(size, 0, "Attempted to set empty buffer");
+        self.default_size = size;
+    }
+
+    /// Use a particular buffer as an intermediate decode buffer.
+    ///
+    /// Calling this sets or replaces the buffer. When a buffer has been set then it is used
+    /// instead of dynamically allocating a buffer. Note that the size of the buffer is critical
+    /// for efficient decoding. Some optimization techniques require the buffer to hold one or more
+    /// previous decoded words. There is also additional overhead from `write` calls each time the
+    /// buffer has been filled.
+    ///
+    /// # Panics
+    /// This method panics if the `buffer` is empty.
+    pub fn set_buffer(&mut self, buffer: &'d mut [u8]) {
+        assert_ne!(buffer.len(), 0, "Attempted to set empty buffer");
+        self.buffer = Some(StreamBuf::Borrowed(buffer));
+    }
+
+    async fn decode_part(
+        &mut self,
+        read: impl tokio::io::AsyncBufRead,
+        must_finish: bool,
+    ) -> StreamResult {
+        use tokio::io::AsyncBufReadExt;
+        use tokio::io::AsyncWriteExt;
+
+        let IntoTokioAsync {
+            decoder,
+            writer,
+            buffer,
+            default_size,
+        } = self;
+
+        let mut read = core::pin::pin!(read);
+
+        let mut bytes_read = 0;
+        let mut bytes_written = 0;
+
+        // Converting to mutable refs to move into the `once` closure.
+        let read_bytes = &mut bytes_read;
+        let write_bytes = &mut bytes_written;
+
+        let outbuf: &mut [u8] =
+            match buffer.get_or_insert_with(|| StreamBuf::Owned(vec![0u8; *default_size])) {
+                StreamBuf::Borrowed(slice) => slice,
+                StreamBuf::Owned(vec) => &mut *vec,
+            };
+        assert!(!outbuf.is_empty());
+
+        let status = loop {
+            // Try to grab one buffer of input data.
+            let mut filler = read.as_mut();
+            let data = match filler.fill_buf().await {
+                Ok(buf) => buf,
+                Err(err) => break Err(err),
+            };
+
+            // Decode as much of the buffer as fits.
+            let result = decoder.decode_bytes(data, &mut outbuf[..]);
+            // Do the bookkeeping and consume the buffer.
+            *read_bytes += result.consumed_in;
+            *write_bytes += result.consumed_out;
+            read.as_mut().consume(result.consumed_in);
+
+            // Write the decoded data first, it is kept even when the status is an error.
+            match writer.write_all(&outbuf[..result.consumed_out]).await {
+                Ok(_) => {}
+                Err(err) => break Err(err),
+            }
+
+            // Handle an error status in the result.
+            let status = match result.status {
+                Ok(ok) => ok,
+                Err(err) => {
+                    break Err(io::Error::from(err));
+                }
+            };
+
+            // Check if we had any new data at all.
+            if let LzwStatus::NoProgress = status {
+                debug_assert_eq!(
+                    result.consumed_out, 0,
+                    "No progress means we have not decoded any data"
+                );
+                // In particular we did not finish decoding.
+                if must_finish && !decoder.may_end() {
+                    break Err(io::Error::from(LzwError::UnexpectedEof));
+                } else {
+                    break Ok(());
+                }
+            }
+
+            if let LzwStatus::Done = status {
+                break Ok(());
+            }
+        };
+
+        StreamResult {
+            bytes_read,
+            bytes_written,
+            status,
+        }
+    }
+}
+
+impl<R> IntoTokioRead<'_, R> {
+    /// Get a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Get a mutable reference to the underlying reader.
+    ///
+    /// Reading from it directly may corrupt the decoded stream.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Unwrap the underlying reader.
+    ///
+    /// It is positioned after the last byte consumed by the decoder. Note that the decoder may
+    /// have consumed some bytes beyond the end code.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: tokio::io::AsyncBufRead + core::marker::Unpin> tokio::io::AsyncRead
+    for IntoTokioRead<'_, R>
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let IntoTokioRead {
+            decoder,
+            reader,
+            error,
+        } = self.get_mut();
+
+        if let Some(err) = error {
+            return Poll::Ready(Err(io::Error::from(*err)));
+        }
+
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        // First decode the codes the decoder already holds, only then wait for more input. The
+        // end code may be among them and the source need not signal its own end.
+        let mut fetch = false;
+
+        loop {
+            let data = if fetch {
+                ready!(Pin::new(&mut *reader).poll_fill_buf(cx))?
+            } else {
+                &[]
+            };
+            let exhausted = data.is_empty();
+
+            let result = decoder.decode_bytes(data, buf.initialize_unfilled());
+            Pin::new(&mut *reader).consume(result.consumed_in);
+            buf.advance(result.consumed_out);
+
+            match result.status {
+                Err(err) => {
+                    // Return the decoded data first, the error on the next call.
+                    *error = Some(err);
+                    if result.consumed_out > 0 {
+                        return Poll::Ready(Ok(()));
+                    }
+
+                    return Poll::Ready(Err(io::Error::from(err)));
+                }
+                _ if result.consumed_out > 0 => return Poll::Ready(Ok(())),
+                Ok(LzwStatus::Done) => return Poll::Ready(Ok(())),
+                // Consumed some input without completing a code, drain the decoder before
+                // waiting for more.
+                Ok(_) if !exhausted => fetch = false,
+                Ok(LzwStatus::Ok) if !fetch => {}
+                Ok(_) if !fetch => fetch = true,
+                Ok(_) => {
+                    if decoder.may_end() {
+                        return Poll::Ready(Ok(()));
+                    }
+
+                    return Poll::Ready(Err(io::Error::from(LzwError::UnexpectedEof)));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,191 @@
+//! Decode a single stream on several threads, split at its clear codes.
+//!
+//! After a clear code the decoder starts over with an empty table and the initial code size, so
+//! the codes up to the next clear code decode independently of all others. Finding the clear
+//! codes requires a pass over all codes to track the code size, see [`Tokens`], but this is much
+//! cheaper than decoding them. The segments between clear codes are then decoded concurrently
+//! into their place in the output.
+//!
+//! This only helps with streams that clear their table frequently, as many TIFF writers and
+//! `compress` do. Streams from an encoder with [`ClearPolicy::WhenFull`] are cleared every few
+//! kilobytes, which is enough.
+//!
+//! # Examples
+//!
+//! ```
+//! use weezl::{BitOrder, decode::{parallel, Configuration, Decoder}, encode::Encoder};
+//!
+//! let data: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8 ^ (i / 1000) as u8).collect();
+//! let encoded = Encoder::new(BitOrder::Msb, 8).encode(&data).unwrap();
+//!
+//! let config = Configuration::new(BitOrder::Msb, 8);
+//! assert!(parallel::scan(&config, &encoded).unwrap().len() > 1);
+//! assert_eq!(parallel::decode(&config, &encoded).unwrap(), data);
+//! ```
+//!
+//! [`Tokens`]: ../struct.Tokens.html
+//! [`ClearPolicy::WhenFull`]: ../../encode/enum.ClearPolicy.html#variant.WhenFull
+use super::{Configuration, TokenKind};
+use crate::alloc::{vec, vec::Vec};
+use crate::error::LzwError;
+use crate::BitOrder;
+
+use crate::jobs;
+
+use core::convert::TryFrom;
+
+/// A part of a stream that starts with an empty table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Segment {
+    /// The position of the first code, counted in bits from the start of the data.
+    pub bit_offset: u64,
+    /// The position after the last code, counted in bits from the start of the data.
+    pub bit_end: u64,
+    /// The position of the decoded data in the decoded stream.
+    pub output_offset: u64,
+    /// The length of the decoded data.
+    pub output_len: u64,
+}
+
+/// Locate the segments of a stream between its clear codes.
+///
+/// The segments are in the order of the stream and only those with any codes are included. Their
+/// decoded data is contiguous. Invalid codes are rejected with the same error as the decoder. The
+/// scan ends with the end code or where the data has no complete code left, a missing end code is
+/// not an error here.
+pub fn scan(configuration: &Configuration, data: &[u8]) -> Result<Vec<Segment>, LzwError> {
+    scan_stream(configuration, data).map(|(segments, _)| segments)
+}
+
+/// Decode a stream on all available threads.
+///
+/// The result is exactly that of [`Decoder::decode`] for a decoder with the same configuration.
+/// Streams which are invalid, incomplete or exceed a limit of the configuration are left to such
+/// a decoder entirely, as are streams without clear codes. The threads are spawned for this call
+/// and end with it.
+///
+/// [`Decoder::decode`]: ../struct.Decoder.html#method.decode
+pub fn decode(configuration: &Configuration, data: &[u8]) -> Result<Vec<u8>, LzwError> {
+    let sequential = || configuration.clone().build().decode(data);
+
+    if configuration.output_limit.is_some() || configuration.expansion_limit.is_some() {
+        return sequential();
+    }
+
+    // Without an end code the stream only ends with the data.
+    let has_end_code = configuration.dialect.codes(configuration.size).1.is_some();
+    let segments = match scan_stream(configuration, data) {
+        Ok((segments, ended)) if ended || !has_end_code => segments,
+        _ => return sequential(),
+    };
+
+    let total = segments
+        .last()
+        .map_or(0, |segment| segment.output_offset + segment.output_len);
+    let total = match usize::try_from(total) {
+        Ok(total) if segments.len() > 1 => total,
+        _ => return sequential(),
+    };
+
+    let mut output = vec![0; total];
+    let mut jobs = Vec::with_capacity(segments.len());
+    let mut rest = &mut output[..];
+    for segment in &segments {
+        let (out, tail) = rest.split_at_mut(segment.output_len as usize);
+        jobs.push((segment, out));
+        rest = tail;
+    }
+
+    let decoded = jobs::run(
+        jobs,
+        || (configuration.clone().build(), Vec::new()),
+        |(decoder, aligned), (segment, out)| {
+            align(configuration.order, data, segment, aligned);
+            decoder.reset();
+            decoder.decode_exact(aligned, out).is_ok()
+        },
+    );
+
+    // The scan accepted the stream so this is not expected, but the decoder has the last word.
+    if !decoded.iter().all(|&ok| ok) {
+        return sequential();
+    }
+
+    Ok(output)
+}
+
+/// Scan the segments, and whether the stream ended with an end code.
+fn scan_stream(
+    configuration: &Configuration,
+    data: &[u8],
+) -> Result<(Vec<Segment>, bool), LzwError> {
+    let mut segments = Vec::new();
+    let mut current: Option<Segment> = None;
+    let mut output = 0;
+
+    for token in configuration.clone().tokens(data) {
+        let token = token?;
+        let len = match token.kind {
+            TokenKind::Literal(_) => 1,
+            TokenKind::Reference { len, .. } => u64::from(len),
+            TokenKind::Clear | TokenKind::End => {
+                if let Some(mut segment) = current.take() {
+                    segment.bit_end = token.bit_offset;
+                    segments.push(segment);
+                }
+
+                if let TokenKind::End = token.kind {
+                    return Ok((segments, true));
+                }
+
+                continue;
+            }
+            TokenKind::CodeSize(_) => continue,
+        };
+
+        let segment = current.get_or_insert(Segment {
+            bit_offset: token.bit_offset,
+            bit_end: 0,
+            output_offset: output,
+            output_len: 0,
+        });
+        segment.output_len += len;
+        output += len;
+    }
+
+    if let Some(mut segment) = current {
+        segment.bit_end = data.len() as u64 * 8;
+        segments.push(segment);
+    }
+
+    Ok((segments, false))
+}
+
+/// Copy the bits of a segment such that they start at a byte boundary.
+fn align(order: BitOrder, data: &[u8], segment: &Segment, aligned: &mut Vec<u8>) {
+    let start = (segment.bit_offset / 8) as usize;
+    let end = segment.bit_end.div_ceil(8) as usize;
+    let bytes = &data[start..end];
+    let shift = (segment.bit_offset % 8) as u32;
+
+    aligned.clear();
+    if shift == 0 {
+        aligned.extend_from_slice(bytes);
+        return;
+    }
+
+    // The bits after the segment are those of the clear code, which is never read.
+    let rest = 8 - shift;
+    let tail = bytes[bytes.len() - 1];
+    match order {
+        BitOrder::Lsb => {
+            aligned.extend(bytes.windows(2).map(|w| w[0] >> shift | w[1] << rest));
+            aligned.push(tail >> shift);
+        }
+        BitOrder::Msb => {
+            aligned.extend(bytes.windows(2).map(|w| w[0] << shift | w[1] >> rest));
+            aligned.push(tail << shift);
+        }
+    }
+}
@@ -0,0 +1,371 @@
+//! Serialize a decoder with its complete state, such that decoding can be resumed elsewhere.
+//!
+//! The decoder is serialized as its configuration and a description of the state that does not
+//! depend on the layout in memory. Deserializing builds a decoder from the configuration and
+//! replays the state onto it, checking that it is consistent. A deserialized decoder thus never
+//! panics where the original would not, even for corrupt data, but it only decodes correctly if
+//! the data was serialized from a decoder.
+use super::{
+    ChunkedTable, ClassicTable, CodeBuffer, CodegenConstants, Configuration, DecodeState,
+    DecodeTable, Decoder, DerivationBase, EndMarker, GroupBuffer, Link, LsbBuffer, MsbBuffer,
+    TableStrategy, STREAMING_Q,
+};
+use crate::alloc::vec::Vec;
+use crate::{BitOrder, Code, Dialect, LARGEST_CODESIZE, MAX_CODESIZE};
+
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A decoder in the form it is serialized.
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    order: BitOrder,
+    size: u8,
+    tiff: bool,
+    yield_on_full: bool,
+    max_code_size: u8,
+    end_marker: EndMarker,
+    table_strategy: TableStrategy,
+    output_limit: Option<u64>,
+    expansion_limit: Option<u32>,
+    filled_output: bool,
+    decoded: u64,
+    consumed: u64,
+    state: State,
+}
+
+/// The state of a decoder, independent of its table strategy.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct State {
+    /// The derived entries of the table, or `None` before the table is initialized.
+    table: Option<Vec<Entry>>,
+    /// The last decoded word, kept until it was handed out completely.
+    word: Vec<u8>,
+    /// The length of the word that was already handed out.
+    read_mark: u32,
+    /// The code of the last word and its first byte.
+    last: Option<(Code, u8)>,
+    has_ended: bool,
+    bits: Bits,
+    bytes_read: u64,
+}
+
+/// The bits read from the input that were not yet decoded.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Bits {
+    bit_buffer: u64,
+    bits: u8,
+    code_size: u8,
+    /// The codes read in the current group and the padding to skip, for `compress`.
+    group: Option<(u8, u8)>,
+}
+
+/// A derived entry of the table.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Entry {
+    prev: Code,
+    first: u8,
+    depth: u16,
+    /// The last byte of the word, or its last partial chunk of up to eight bytes.
+    suffix: u64,
+}
+
+impl Serialize for Decoder {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (Some(configuration), Some(state)) = (&self.configuration, self.state.save()) else {
+            return Err(ser::Error::custom(
+                "only a decoder built from a `decode::Configuration` can be serialized",
+            ));
+        };
+
+        Snapshot {
+            order: configuration.order,
+            size: configuration.size,
+            tiff: configuration.tiff,
+            yield_on_full: configuration.yield_on_full,
+            max_code_size: configuration.max_code_size,
+            end_marker: configuration.end_marker,
+            table_strategy: configuration.table_strategy,
+            output_limit: configuration.output_limit,
+            expansion_limit: configuration.expansion_limit,
+            filled_output: self.filled_output,
+            decoded: self.decoded,
+            consumed: self.consumed,
+            state,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Decoder {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let snapshot = Snapshot::deserialize(deserializer)?;
+
+        // The same requirements as asserted by `Configuration`.
+        if snapshot.size > MAX_CODESIZE
+            || !(9..=LARGEST_CODESIZE).contains(&snapshot.max_code_size)
+            || (snapshot.max_code_size <= snapshot.size && snapshot.max_code_size != MAX_CODESIZE)
+        {
+            return Err(de::Error::custom("invalid code sizes"));
+        }
+
+        let configuration = Configuration {
+            order: snapshot.order,
+            size: snapshot.size,
+            tiff: snapshot.tiff,
+            yield_on_full: snapshot.yield_on_full,
+            max_code_size: snapshot.max_code_size,
+            dialect: Dialect::Standard,
+            end_marker: snapshot.end_marker,
+            table_strategy: snapshot.table_strategy,
+            output_limit: snapshot.output_limit,
+            expansion_limit: snapshot.expansion_limit,
+        };
+
+        let mut decoder = configuration.build();
+        decoder
+            .state
+            .load(&snapshot.state)
+            .map_err(de::Error::custom)?;
+        decoder.filled_output = snapshot.filled_output;
+        decoder.decoded = snapshot.decoded;
+        decoder.consumed = snapshot.consumed;
+        Ok(decoder)
+    }
+}
+
+pub(super) fn save<C: CodeBuffer, CgC: CodegenConstants, T: DecodeTable>(
+    state: &DecodeState<C, CgC, T>,
+) -> State {
+    let table = if state.table.is_empty() {
+        None
+    } else {
+        debug_assert_eq!(state.next_code, state.table.len() as u32);
+        Some(state.table.save(state.first_code))
+    };
+
+    State {
+        table,
+        word: state.buffer.bytes[..state.buffer.write_mark].to_vec(),
+        read_mark: state.buffer.read_mark as u32,
+        last: state.last.as_ref().map(|last| (last.code, last.first)),
+        has_ended: state.has_ended,
+        bits: state.code_buffer.save(),
+        bytes_read: state.bytes_read,
+    }
+}
+
+pub(super) fn load<C: CodeBuffer, CgC: CodegenConstants, T: DecodeTable>(
+    state: &mut DecodeState<C, CgC, T>,
+    saved: &State,
+) -> Result<(), &'static str> {
+    state.table = T::new(state.table.entries());
+    state.next_code = state.first_code.into();
+
+    if let Some(entries) = &saved.table {
+        state.table.init(state.min_size, state.first_code);
+        state.table.load(entries)?;
+        state.next_code = state.table.len() as u32;
+    }
+
+    let word = &saved.word;
+    let read_mark = saved.read_mark as usize;
+    if word.len() > state.buffer.bytes.len() || read_mark > word.len() {
+        return Err("invalid buffered word");
+    }
+
+    state.buffer.bytes[..word.len()].copy_from_slice(word);
+    state.buffer.write_mark = word.len();
+    state.buffer.read_mark = read_mark;
+
+    state.last = match saved.last {
+        Some((code, first)) if usize::from(code) < state.table.len() => {
+            Some(DerivationBase { code, first })
+        }
+        Some(_) => return Err("invalid last code"),
+        None => None,
+    };
+
+    // A symbol size of 12 starts above the default maximum of 12.
+    let code_size = saved.bits.code_size;
+    let max_code_size = state.max_code_size.max(state.min_size + 1);
+    if code_size <= state.min_size || code_size > max_code_size || saved.bits.bits > 64 {
+        return Err("invalid code size");
+    }
+
+    state.code_buffer.load(&saved.bits)?;
+    state.has_ended = saved.has_ended;
+    state.bytes_read = saved.bytes_read;
+    Ok(())
+}
+
+impl Bits {
+    fn new(bit_buffer: u64, bits: u8, code_size: u8) -> Self {
+        Bits {
+            bit_buffer,
+            bits,
+            code_size,
+            group: None,
+        }
+    }
+}
+
+impl MsbBuffer {
+    pub(super) fn save_bits(&self) -> Bits {
+        Bits::new(self.bit_buffer, self.bits, self.code_size)
+    }
+
+    pub(super) fn load_bits(&mut self, saved: &Bits) -> Result<(), &'static str> {
+        if saved.group.is_some() {
+            return Err("unexpected group of codes");
+        }
+
+        // The buffered bits are the high-order bits, all others must be zero.
+        self.bit_buffer = saved.bit_buffer & !u64::MAX.checked_shr(saved.bits.into()).unwrap_or(0);
+        self.bits = saved.bits;
+        self.code_size = saved.code_size;
+        self.code_mask = Code::MAX >> (16 - self.code_size);
+        Ok(())
+    }
+}
+
+impl LsbBuffer {
+    pub(super) fn save_bits(&self) -> Bits {
+        Bits::new(self.bit_buffer, self.bits, self.code_size)
+    }
+
+    pub(super) fn load_bits(&mut self, saved: &Bits) -> Result<(), &'static str> {
+        if saved.group.is_some() {
+            return Err("unexpected group of codes");
+        }
+
+        // The buffered bits are the low-order bits, all others must be zero.
+        self.bit_buffer = saved.bit_buffer & !u64::MAX.checked_shl(saved.bits.into()).unwrap_or(0);
+        self.bits = saved.bits;
+        self.code_size = saved.code_size;
+        self.code_mask = Code::MAX >> (16 - self.code_size);
+        Ok(())
+    }
+}
+
+impl<B: CodeBuffer> GroupBuffer<B> {
+    pub(super) fn save_bits(&self) -> Bits {
+        Bits {
+            group: Some((self.codes, self.skip)),
+            ..self.inner.save()
+        }
+    }
+
+    pub(super) fn load_bits(&mut self, saved: &Bits) -> Result<(), &'static str> {
+        let Some((codes, skip)) = saved.group else {
+            return Err("missing group of codes");
+        };
+
+        if codes >= 8 || usize::from(skip) >= 8 * usize::from(LARGEST_CODESIZE) {
+            return Err("invalid group of codes");
+        }
+
+        self.inner.load(&Bits {
+            group: None,
+            ..*saved
+        })?;
+        self.codes = codes;
+        self.skip = skip;
+        Ok(())
+    }
+}
+
+impl<const N: usize> ClassicTable<N> {
+    pub(super) fn save_entries(&self, first_code: Code) -> Vec<Entry> {
+        (usize::from(first_code)..self.len)
+            .map(|idx| Entry {
+                prev: self.chain[idx].prev,
+                first: self.chain[idx].first,
+                depth: self.depths[idx],
+                suffix: self.bytes[idx].into(),
+            })
+            .collect()
+    }
+
+    pub(super) fn load_entries(&mut self, entries: &[Entry]) -> Result<(), &'static str> {
+        if entries.len() > self.entries.saturating_sub(self.len) {
+            return Err("too many table entries");
+        }
+
+        for entry in entries {
+            let prev = usize::from(entry.prev);
+            // Each word extends the word of an earlier code by one byte.
+            let valid = prev < self.len
+                && self.depths[prev] > 0
+                && u32::from(entry.depth) == u32::from(self.depths[prev]) + 1
+                && entry.first == self.chain[prev].first
+                && entry.suffix <= u8::MAX.into();
+
+            if !valid {
+                return Err("invalid table entry");
+            }
+
+            let idx = self.len & Self::MASK;
+            self.bytes[idx] = entry.suffix as u8;
+            self.chain[idx] = Link {
+                prev: entry.prev,
+                first: entry.first,
+            };
+            self.depths[idx] = entry.depth;
+            self.len += 1;
+        }
+
+        Ok(())
+    }
+}
+
+impl<const N: usize> ChunkedTable<N> {
+    pub(super) fn save_entries(&self, first_code: Code) -> Vec<Entry> {
+        (usize::from(first_code)..self.len)
+            .map(|idx| Entry {
+                prev: self.chain[idx].prev,
+                first: self.chain[idx].first,
+                depth: self.depths[idx],
+                suffix: u64::from_le_bytes(self.suffixes[idx]),
+            })
+            .collect()
+    }
+
+    pub(super) fn load_entries(&mut self, entries: &[Entry]) -> Result<(), &'static str> {
+        if entries.len() > self.entries.saturating_sub(self.len) {
+            return Err("too many table entries");
+        }
+
+        for entry in entries {
+            let depth = usize::from(entry.depth);
+            let suffix = entry.suffix.to_le_bytes();
+            let tail_len = depth.wrapping_sub(1) % STREAMING_Q + 1;
+            let prev = usize::from(entry.prev);
+
+            // A word in a single chunk starts with its suffix. Longer words refer to the code
+            // of the word before their last partial chunk.
+            let valid = depth >= 2
+                && prev < self.len
+                && if depth <= STREAMING_Q {
+                    entry.first == suffix[0]
+                } else {
+                    usize::from(self.depths[prev]) == depth - tail_len
+                        && entry.first == self.chain[prev].first
+                };
+
+            if !valid {
+                return Err("invalid table entry");
+            }
+
+            let idx = self.len & Self::MASK;
+            self.suffixes[idx] = suffix;
+            self.chain[idx] = Link {
+                prev: entry.prev,
+                first: entry.first,
+            };
+            self.depths[idx] = entry.depth;
+            self.len += 1;
+        }
+
+        Ok(())
+    }
+}
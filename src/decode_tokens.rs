@@ -0,0 +1,304 @@
+//! Inspect the codes of a stream without decoding it.
+use super::{CodeBuffer, Configuration, GroupBuffer, LsbBuffer, MsbBuffer};
+use crate::alloc::{boxed::Box, vec, vec::Vec};
+use crate::error::{CodeError, LzwError};
+use crate::{BitOrder, Code, Dialect};
+
+/// An iterator over the codes of an LZW stream.
+///
+/// This yields one [`Token`] for each code in the stream, and one whenever the code size
+/// changes. It reads the bits exactly as a decoder with the same configuration does, and rejects
+/// the same codes with the same error. The iterator ends after an end code, after the first error
+/// or when the data has no complete code left. No data is decoded, only the length of each word
+/// is tracked.
+///
+/// Create one with [`Configuration::tokens`].
+///
+/// [`Configuration::tokens`]: struct.Configuration.html#method.tokens
+///
+/// # Examples
+///
+/// ```
+/// use weezl::{BitOrder, decode::{Configuration, TokenKind}, encode::Encoder};
+///
+/// let encoded = Encoder::new(BitOrder::Msb, 8).encode(b"TOBEORNOTTOBE").unwrap();
+/// let tokens = Configuration::new(BitOrder::Msb, 8)
+///     .tokens(&encoded)
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+///
+/// assert_eq!(tokens[0].kind, TokenKind::Clear);
+/// assert_eq!(tokens[1].kind, TokenKind::Literal(b'T'.into()));
+/// assert_eq!(tokens[1].bit_offset, 9);
+/// assert_eq!(tokens.last().unwrap().kind, TokenKind::End);
+/// ```
+pub struct Tokens<'d> {
+    inp: &'d [u8],
+    state: Box<dyn TokenState + Send + 'static>,
+}
+
+/// An item of [`Tokens`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Token {
+    /// The position of the first bit of the code, counted in bits from the start of the data.
+    ///
+    /// A change of the code size is positioned after the code that caused it.
+    pub bit_offset: u64,
+    /// The meaning of the code.
+    pub kind: TokenKind,
+}
+
+/// The meaning of a [`Token`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TokenKind {
+    /// A code for a single symbol of the alphabet, with the value of that symbol.
+    Literal(u16),
+    /// A code for a word of the table.
+    Reference {
+        /// The value of the code.
+        code: u16,
+        /// The length of the word that the code decodes to.
+        len: u16,
+    },
+    /// The clear code, which resets the table and the code size.
+    Clear,
+    /// The end code, no codes are read after it.
+    End,
+    /// The code size changes to the given number of bits, starting with the next code.
+    CodeSize(u8),
+}
+
+trait TokenState {
+    fn next_token(&mut self, inp: &mut &[u8]) -> Option<Result<Token, LzwError>>;
+}
+
+struct TokenReader<C> {
+    /// The original minimum code size.
+    min_size: u8,
+    /// The code size at which the code size no longer increases.
+    max_code_size: u8,
+    /// The length of the word of each code, such that the table is full at its end.
+    lengths: Vec<u16>,
+    /// The previous code, unless it started the stream or followed a clear code.
+    last: Option<Code>,
+    /// The next code entry.
+    next_code: u32,
+    /// The first code entry after a reset.
+    first_code: Code,
+    /// Code to reset all tables, if the stream has one.
+    clear_code: Option<Code>,
+    /// Code to signal the end of the stream, if the stream has one.
+    end_code: Option<Code>,
+    /// A change of the code size, yielded after the code that caused it.
+    pending: Option<Token>,
+    /// If an end code or an error was encountered.
+    is_done: bool,
+    /// Whether the code size switches one code early, as in TIFF.
+    is_tiff: bool,
+    code_buffer: C,
+    /// The number of bytes read so far.
+    bytes_read: u64,
+}
+
+impl Configuration {
+    /// Iterate over the codes of a stream instead of decoding it.
+    ///
+    /// This helps to understand why a stream fails to decode, see [`Tokens`] for details.
+    ///
+    /// [`Tokens`]: struct.Tokens.html
+    pub fn tokens(self, data: &[u8]) -> Tokens<'_> {
+        fn make<C: CodeBuffer + Send + 'static>(
+            configuration: &Configuration,
+        ) -> Box<dyn TokenState + Send + 'static> {
+            Box::new(TokenReader::<C>::new(configuration))
+        }
+
+        let state = match (self.order, self.dialect) {
+            (BitOrder::Lsb, Dialect::Standard) => make::<LsbBuffer>(&self),
+            (BitOrder::Lsb, Dialect::Compress { .. }) => make::<GroupBuffer<LsbBuffer>>(&self),
+            (BitOrder::Msb, Dialect::Standard) => make::<MsbBuffer>(&self),
+            (BitOrder::Msb, Dialect::Compress { .. }) => make::<GroupBuffer<MsbBuffer>>(&self),
+        };
+
+        Tokens { inp: data, state }
+    }
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = Result<Token, LzwError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.state.next_token(&mut self.inp)
+    }
+}
+
+impl<C: CodeBuffer> TokenReader<C> {
+    fn new(configuration: &Configuration) -> Self {
+        let min_size = configuration.size;
+        let (clear_code, end_code, first_code) = configuration.dialect.codes(min_size);
+        let mut lengths = vec![0; 1 << configuration.max_code_size];
+        lengths[..1 << min_size].fill(1);
+
+        let mut reader = TokenReader {
+            min_size,
+            max_code_size: configuration
+                .dialect
+                .code_size_limit(configuration.max_code_size),
+            lengths,
+            last: None,
+            next_code: first_code.into(),
+            first_code,
+            clear_code,
+            end_code,
+            pending: None,
+            is_done: false,
+            is_tiff: configuration.tiff,
+            code_buffer: C::new(min_size),
+            bytes_read: 0,
+        };
+
+        reader.bump_initial_code_size();
+        reader
+    }
+
+    /// Interpret a code, updating the table and code size as the decoder does.
+    ///
+    /// Returns `None` for a code that can not be decoded.
+    fn interpret(&mut self, code: Code) -> Option<TokenKind> {
+        if Some(code) == self.clear_code {
+            self.code_buffer.align_group();
+            self.code_buffer.reset(self.min_size);
+            self.next_code = self.first_code.into();
+            self.last = None;
+            self.bump_initial_code_size();
+            return Some(TokenKind::Clear);
+        }
+
+        if Some(code) == self.end_code {
+            self.is_done = true;
+            return Some(TokenKind::End);
+        }
+
+        let len = match self.last {
+            // The first code after a reset has no predecessor and derives no entry.
+            None => {
+                if u32::from(code) >= self.next_code {
+                    return None;
+                }
+
+                self.bump_post_initial_code_size();
+                self.lengths[usize::from(code)]
+            }
+            Some(prev) => {
+                if u32::from(code) > self.next_code {
+                    return None;
+                }
+
+                let derived = self.lengths[usize::from(prev)] + 1;
+                let len = if u32::from(code) == self.next_code {
+                    derived
+                } else {
+                    self.lengths[usize::from(code)]
+                };
+
+                if let Some(entry) = self.lengths.get_mut(self.next_code as usize) {
+                    *entry = derived;
+
+                    if self.next_code
+                        >= u32::from(self.code_buffer.max_code() - Code::from(self.is_tiff))
+                        && self.code_buffer.code_size() < self.max_code_size
+                    {
+                        self.code_buffer.bump_code_size();
+                    }
+
+                    self.next_code += 1;
+                } else if u32::from(code) == self.next_code {
+                    // There is no such entry in a full table.
+                    return None;
+                }
+
+                len
+            }
+        };
+
+        self.last = Some(code);
+
+        if code < 1 << self.min_size {
+            Some(TokenKind::Literal(code))
+        } else {
+            Some(TokenKind::Reference { code, len })
+        }
+    }
+
+    fn invalid_code(&self, code: Code, code_size: u8, start: u64) -> LzwError {
+        let err = CodeError {
+            code,
+            next_code: self.next_code,
+            code_size,
+            byte_offset: start / 8,
+            bit_offset: (start % 8) as u8,
+        };
+
+        if u32::from(code) > self.next_code {
+            LzwError::CodeOutOfRange(err)
+        } else {
+            LzwError::InvalidCode(err)
+        }
+    }
+
+    /// Bump the code size before any symbol is read, see `DecodeState`.
+    fn bump_initial_code_size(&mut self) {
+        if self.first_code - 1 > self.code_buffer.max_code() - Code::from(self.is_tiff)
+            && self.code_buffer.code_size() < self.max_code_size
+        {
+            self.code_buffer.bump_code_size();
+        }
+    }
+
+    /// Bump the code size after the first coded symbol is read, see `DecodeState`.
+    fn bump_post_initial_code_size(&mut self) {
+        if self.next_code > u32::from(self.code_buffer.max_code())
+            && self.code_buffer.code_size() < self.max_code_size
+        {
+            self.code_buffer.bump_code_size();
+        }
+    }
+}
+
+impl<C: CodeBuffer> TokenState for TokenReader<C> {
+    fn next_token(&mut self, inp: &mut &[u8]) -> Option<Result<Token, LzwError>> {
+        if let Some(token) = self.pending.take() {
+            return Some(Ok(token));
+        }
+
+        if self.is_done {
+            return None;
+        }
+
+        let o_in = inp.len();
+        let code_size = self.code_buffer.code_size();
+        let code = self.code_buffer.next_symbol(inp);
+        self.bytes_read += (o_in - inp.len()) as u64;
+        let code = code?;
+
+        let end = self.bytes_read * 8 - u64::from(self.code_buffer.buffered_bits());
+        let bit_offset = end - u64::from(code_size);
+
+        let Some(kind) = self.interpret(code) else {
+            self.is_done = true;
+            return Some(Err(self.invalid_code(code, code_size, bit_offset)));
+        };
+
+        let new_size = self.code_buffer.code_size();
+        if new_size != code_size {
+            self.pending = Some(Token {
+                bit_offset: end,
+                kind: TokenKind::CodeSize(new_size),
+            });
+        }
+
+        Some(Ok(Token { bit_offset, kind }))
+    }
+}
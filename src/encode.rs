@@ -1,12 +1,14 @@
 //! A module for all encoding needs.
 use crate::error::{BufferResult, LzwError, LzwStatus, VectorResult};
-use crate::{BitOrder, Code, StreamBuf, MAX_CODESIZE, MAX_ENTRIES, STREAM_BUF_SIZE};
+#[cfg(feature = "std")]
+use crate::WRITER_BUF_SIZE;
+use crate::{BitOrder, Code, Dialect, StreamBuf, MAX_CODESIZE, STREAM_BUF_SIZE};
 
 use crate::alloc::{boxed::Box, vec::Vec};
 #[cfg(feature = "std")]
 use crate::error::StreamResult;
 #[cfg(feature = "std")]
-use std::io::{self, BufRead, Write};
+use std::io::{self, BufRead, Read, Write};
 
 /// The state for encoding data with an LZW algorithm.
 ///
@@ -21,14 +23,23 @@ use std::io::{self, BufRead, Write};
 ///
 /// * [`encode`] for encoding once without any IO-loop.
 /// * [`into_async`] for encoding with the `futures` traits for asynchronous IO.
+/// * [`into_tokio_async`] for encoding with the `tokio` traits for asynchronous IO.
 /// * [`into_stream`] for encoding with the standard `io` traits.
+/// * [`into_read`] for pulling encoded data through the standard `io::Read` trait.
 /// * [`into_vec`] for in-memory encoding.
 ///
+/// The [`Writer`] takes ownership of an encoder to encode all data written to it.
+///
+/// A clone of an encoder continues independently from the same state, including its dictionary.
+///
 /// [`encode_bytes`]: #method.encode_bytes
 /// [`encode`]: #method.encode
 /// [`into_async`]: #method.into_async
+/// [`into_tokio_async`]: #method.into_tokio_async
 /// [`into_stream`]: #method.into_stream
+/// [`into_read`]: #method.into_read
 /// [`into_vec`]: #method.into_vec
+/// [`Writer`]: struct.Writer.html
 pub struct Encoder {
     /// Internally dispatch via a dynamic trait object. This did not have any significant
     /// performance impact as we batch data internally and this pointer does not change after
@@ -36,6 +47,29 @@ pub struct Encoder {
     state: Box<dyn Stateful + Send + 'static>,
 }
 
+/// When the encoder resets its dictionary with a clear code.
+///
+/// A dictionary adapted to older data may compress the following data poorly. Clearing it more
+/// often adapts faster to changing data but starts over with short matches each time, which of
+/// these is better depends on the data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ClearPolicy {
+    /// Clear the dictionary as soon as it is full.
+    WhenFull,
+    /// Never clear the dictionary, keep coding with it once it is full.
+    ///
+    /// This is the deferred clear of `giflib`. Decoders must not expect a clear code when their
+    /// table is full, which is the case for GIF but not for all TIFF readers.
+    Freeze,
+    /// Keep a full dictionary while the compression ratio increases, as `compress` does.
+    ///
+    /// The ratio is checked periodically and the dictionary is cleared when it dropped.
+    Adaptive,
+    /// Clear the dictionary after this many codes, or when it is full before that.
+    Interval(u32),
+}
+
 /// A encoding stream sink.
 ///
 /// See [`Encoder::into_stream`] on how to create this type.
@@ -66,6 +100,72 @@ pub struct IntoAsync<'d, W> {
     default_size: usize,
 }
 
+/// An async encoding sink for `tokio`.
+///
+/// See [`Encoder::into_tokio_async`] on how to create this type.
+///
+/// [`Encoder::into_tokio_async`]: struct.Encoder.html#method.into_tokio_async
+#[cfg(feature = "tokio")]
+pub struct IntoTokioAsync<'d, W> {
+    encoder: &'d mut Encoder,
+    writer: W,
+    buffer: Option<StreamBuf<'d>>,
+    default_size: usize,
+}
+
+/// An encoding source for reading encoded data.
+///
+/// See [`Encoder::into_read`] on how to create this type.
+///
+/// [`Encoder::into_read`]: struct.Encoder.html#method.into_read
+#[cfg(feature = "std")]
+pub struct IntoRead<'d, R> {
+    encoder: &'d mut Encoder,
+    reader: R,
+}
+
+/// An async encoding source for `tokio`, for reading encoded data.
+///
+/// See [`Encoder::into_tokio_read`] on how to create this type.
+///
+/// [`Encoder::into_tokio_read`]: struct.Encoder.html#method.into_tokio_read
+#[cfg(feature = "tokio")]
+pub struct IntoTokioRead<'d, R> {
+    encoder: &'d mut Encoder,
+    reader: R,
+}
+
+/// An encoder that owns its sink and encodes all data written to it.
+///
+/// In contrast to [`IntoStream`] the data need not be available at once in a reader. Instead it
+/// is passed in any number of calls to `write`. The stream must be completed with [`finish`],
+/// which writes the end code. Dropping the writer does not finish the stream.
+///
+/// # Example
+///
+/// ```
+/// use std::io::Write;
+/// use weezl::{BitOrder, decode::Decoder, encode::{Encoder, Writer}};
+///
+/// let mut writer = Writer::new(Encoder::new(BitOrder::Msb, 8), vec![]);
+/// for row in &[b"Hello", b"World"] {
+///     writer.write_all(&row[..]).unwrap();
+/// }
+/// let encoded = writer.finish().unwrap();
+///
+/// let decoded = Decoder::new(BitOrder::Msb, 8).decode(&encoded).unwrap();
+/// assert_eq!(decoded, b"HelloWorld");
+/// ```
+///
+/// [`IntoStream`]: struct.IntoStream.html
+/// [`finish`]: #method.finish
+#[cfg(feature = "std")]
+pub struct Writer<W> {
+    encoder: Encoder,
+    writer: W,
+    buffer: Vec<u8>,
+}
+
 /// A encoding sink into a vector.
 ///
 /// See [`Encoder::into_vec`] on how to create this type.
@@ -76,32 +176,74 @@ pub struct IntoVec<'d> {
     vector: &'d mut Vec<u8>,
 }
 
-trait Stateful {
+pub(crate) trait Stateful {
     fn advance(&mut self, inp: &[u8], out: &mut [u8]) -> BufferResult;
     fn mark_ended(&mut self) -> bool;
     /// Reset the state tracking if end code has been written.
     fn restart(&mut self);
     /// Reset the encoder to the beginning, dropping all buffers etc.
     fn reset(&mut self);
+    /// Copy the complete state, such that both copies continue independently.
+    fn clone_boxed(&self) -> Box<dyn Stateful + Send + 'static>;
 }
 
+#[derive(Clone)]
 struct EncodeState<B: Buffer> {
     /// The configured minimal code size.
     min_size: u8,
+    /// The code size at which the code size no longer increases.
+    max_code_size: u8,
     /// The current encoding symbol tree.
     tree: Tree,
-    /// If we have pushed the end code.
+    /// If we have been asked to finish the stream.
     has_ended: bool,
+    /// If we have pushed the last code, including the end code if there is one.
+    finished: bool,
     /// If tiff then bumps are a single code sooner.
     is_tiff: bool,
     /// The code corresponding to the currently read characters.
     current_code: Code,
     /// The clear code for resetting the dictionary.
+    ///
+    /// This is also the code of the empty string, the root of the tree.
     clear_code: Code,
+    /// The code to signal the end of the stream, if the stream has one.
+    end_code: Option<Code>,
+    /// The layout of codes in the stream.
+    dialect: Dialect,
+    /// The number of codes written in the current group of eight, for aligning code size changes.
+    codes: u8,
+    /// The number of zero bits to write before the next code.
+    padding: u16,
+    /// When to clear the dictionary.
+    clear_policy: ClearPolicy,
+    /// The number of codes written since the last clear code.
+    codes_since_clear: u32,
+    /// The state for clearing a full dictionary when the compression ratio drops.
+    ratio: Option<RatioCheck>,
     /// The bit buffer for encoding.
     buffer: B,
+    /// The number of input bytes consumed by previous calls, to locate errors.
+    bytes_read: u64,
 }
 
+/// The heuristic of `compress`: keep a full dictionary while the compression ratio increases.
+#[derive(Clone)]
+struct RatioCheck {
+    /// The number of input bytes consumed in previous calls.
+    bytes_in: u64,
+    /// The number of output bytes written in previous calls.
+    bytes_out: u64,
+    /// The count of input bytes at which the ratio is checked next.
+    checkpoint: u64,
+    /// The best ratio since the last clear, as input bytes per output byte in units of `1/256`.
+    ratio: u64,
+}
+
+/// The number of input bytes between two checks of the compression ratio, as in `compress`.
+const CHECK_GAP: u64 = 10_000;
+
+#[derive(Clone)]
 struct MsbBuffer {
     /// The current code length.
     code_size: u8,
@@ -111,6 +253,7 @@ struct MsbBuffer {
     bits_in_buffer: u8,
 }
 
+#[derive(Clone)]
 struct LsbBuffer {
     /// The current code length.
     code_size: u8,
@@ -120,7 +263,7 @@ struct LsbBuffer {
     bits_in_buffer: u8,
 }
 
-trait Buffer {
+trait Buffer: Clone + Send + 'static {
     fn new(size: u8) -> Self;
     /// Reset the code size in the buffer.
     fn reset(&mut self, min_size: u8);
@@ -134,22 +277,29 @@ trait Buffer {
     fn flush_out(&mut self, out: &mut &mut [u8]) -> bool;
     /// Pad the buffer to a full byte.
     fn buffer_pad(&mut self);
+    /// Insert some zero bits, at most 56 and only when the buffer holds less than a byte.
+    fn buffer_zeros(&mut self, bits: u8);
     /// Increase the maximum code size.
     fn bump_code_size(&mut self);
     /// Return the maximum code with the current code size.
     fn max_code(&self) -> Code;
     /// Return the current code size in bits.
     fn code_size(&self) -> u8;
+    /// Return the number of bits in the buffer.
+    #[cfg_attr(not(feature = "parallel"), allow(dead_code))]
+    fn buffered_bits(&self) -> u8;
 }
 
 /// One tree node for at most each code.
 /// To avoid using too much memory we keep nodes with few successors in optimized form. This form
 /// doesn't offer lookup by indexing but instead does a linear search.
-#[derive(Default)]
+#[derive(Clone, Default)]
 struct Tree {
     simples: Vec<Simple>,
     complex: Vec<Full>,
     keys: Vec<CompressedKey>,
+    /// The number of keys after which no more entries are added, the number of table entries.
+    limit: usize,
 }
 
 #[derive(Clone, Copy, Default)]
@@ -160,8 +310,9 @@ enum FullKey {
     Full(u16),
 }
 
+/// A `FullKey` packed with its tag above the 16 bits of the index.
 #[derive(Clone, Copy)]
-struct CompressedKey(u16);
+struct CompressedKey(u32);
 
 const SHORT: usize = 16;
 
@@ -183,6 +334,9 @@ pub struct Configuration {
     order: BitOrder,
     size: u8,
     tiff: bool,
+    max_code_size: u8,
+    dialect: Dialect,
+    clear_policy: Option<ClearPolicy>,
 }
 
 impl Configuration {
@@ -197,6 +351,9 @@ impl Configuration {
             order,
             size,
             tiff: false,
+            max_code_size: MAX_CODESIZE,
+            dialect: Dialect::Standard,
+            clear_policy: None,
         }
     }
 
@@ -211,15 +368,86 @@ impl Configuration {
             order,
             size,
             tiff: true,
+            max_code_size: MAX_CODESIZE,
+            dialect: Dialect::Standard,
+            clear_policy: None,
+        }
+    }
+
+    /// Set the maximum code size.
+    ///
+    /// The code size increases with the number of table entries until it reaches this size. The
+    /// encoder then clears the table before it would need another entry. Sizes below `12` save
+    /// memory for producers and consumers with small dictionaries. The decoder must be configured
+    /// with the same maximum code size.
+    ///
+    /// Default: `12`.
+    ///
+    /// # Panics
+    ///
+    /// The `size` needs to be in the interval `9..=16` and larger than the symbol size, except for
+    /// the default of `12` which also accepts a symbol size of `12`.
+    pub fn with_max_code_size(self, size: u8) -> Self {
+        super::assert_max_code_size(size, self.size);
+        Configuration {
+            max_code_size: size,
+            ..self
         }
     }
 
+    /// Choose when to clear the dictionary.
+    ///
+    /// Default: [`ClearPolicy::WhenFull`], or [`ClearPolicy::Adaptive`] for `.Z` files of
+    /// `compress`.
+    ///
+    /// # Panics
+    ///
+    /// The interval of [`ClearPolicy::Interval`] must not be `0`.
+    ///
+    /// [`ClearPolicy::WhenFull`]: enum.ClearPolicy.html#variant.WhenFull
+    /// [`ClearPolicy::Adaptive`]: enum.ClearPolicy.html#variant.Adaptive
+    /// [`ClearPolicy::Interval`]: enum.ClearPolicy.html#variant.Interval
+    pub fn with_clear_policy(self, policy: ClearPolicy) -> Self {
+        if let ClearPolicy::Interval(interval) = policy {
+            assert_ne!(interval, 0, "Attempted to clear after every 0 codes");
+        }
+
+        Configuration {
+            clear_policy: Some(policy),
+            ..self
+        }
+    }
+
+    /// Choose the layout of special codes and the packing of codes.
+    ///
+    /// The encoder requires a clear code, i.e. `compress` streams must use block mode.
+    pub(crate) fn with_dialect(self, dialect: Dialect) -> Self {
+        debug_assert!(dialect.codes(self.size).0.is_some());
+        Configuration { dialect, ..self }
+    }
+
     /// Create a new decoder with the define configuration.
     pub fn build(self) -> Encoder {
         Encoder {
             state: Encoder::from_configuration(&self),
         }
     }
+
+    /// The configured clear policy or the default of the dialect.
+    fn effective_clear_policy(&self) -> ClearPolicy {
+        self.clear_policy.unwrap_or(match self.dialect {
+            Dialect::Standard => ClearPolicy::WhenFull,
+            Dialect::Compress { .. } => ClearPolicy::Adaptive,
+        })
+    }
+}
+
+impl Clone for Encoder {
+    fn clone(&self) -> Self {
+        Encoder {
+            state: self.state.clone_boxed(),
+        }
+    }
 }
 
 impl Encoder {
@@ -249,21 +477,38 @@ impl Encoder {
         Configuration::with_tiff_size_switch(order, size).build()
     }
 
-    fn from_configuration(cfg: &Configuration) -> Box<dyn Stateful + Send + 'static> {
+    pub(crate) fn from_configuration(cfg: &Configuration) -> Box<dyn Stateful + Send + 'static> {
+        let clear_policy = cfg.effective_clear_policy();
+
         match cfg.order {
             BitOrder::Lsb => {
-                let mut state = EncodeState::<LsbBuffer>::new(cfg.size);
+                let mut state = EncodeState::<LsbBuffer>::new(
+                    cfg.size,
+                    cfg.max_code_size,
+                    cfg.dialect,
+                    clear_policy,
+                );
                 state.is_tiff = cfg.tiff;
                 Box::new(state)
             }
             BitOrder::Msb => {
-                let mut state = EncodeState::<MsbBuffer>::new(cfg.size);
+                let mut state = EncodeState::<MsbBuffer>::new(
+                    cfg.size,
+                    cfg.max_code_size,
+                    cfg.dialect,
+                    clear_policy,
+                );
                 state.is_tiff = cfg.tiff;
                 Box::new(state)
             }
         }
     }
 
+    /// Wrap a state that is not built from a `Configuration`.
+    pub(crate) fn from_state(state: Box<dyn Stateful + Send + 'static>) -> Self {
+        Encoder { state }
+    }
+
     /// Encode some bytes from `inp` into `out`.
     ///
     /// See [`into_stream`] for high-level functions (this interface is only available with the
@@ -317,6 +562,35 @@ impl Encoder {
         }
     }
 
+    /// Construct an encoder that is read from, pulling the data to encode from a reader.
+    ///
+    /// Each call to `read` encodes only as much data as necessary to fill its buffer. When the
+    /// reader is exhausted the stream is finished with an end code, after which EOF is signalled.
+    /// Bytes that are invalid for the symbol size are returned as an `InvalidData` error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::io::Read;
+    /// use weezl::{BitOrder, decode::Decoder, encode::Encoder};
+    ///
+    /// let data = b"Hello, world";
+    /// let mut encoder = Encoder::new(BitOrder::Msb, 8);
+    ///
+    /// let mut encoded = vec![];
+    /// encoder.into_read(&data[..]).read_to_end(&mut encoded).unwrap();
+    ///
+    /// let decoded = Decoder::new(BitOrder::Msb, 8).decode(&encoded).unwrap();
+    /// assert_eq!(decoded, data);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn into_read<R: BufRead>(&mut self, reader: R) -> IntoRead<'_, R> {
+        IntoRead {
+            encoder: self,
+            reader,
+        }
+    }
+
     /// Construct a encoder into an async writer.
     #[cfg(feature = "async")]
     pub fn into_async<W: futures::io::AsyncWrite>(&mut self, writer: W) -> IntoAsync<'_, W> {
@@ -328,6 +602,34 @@ impl Encoder {
         }
     }
 
+    /// Construct an encoder into a `tokio` async writer.
+    #[cfg(feature = "tokio")]
+    pub fn into_tokio_async<W: tokio::io::AsyncWrite>(
+        &mut self,
+        writer: W,
+    ) -> IntoTokioAsync<'_, W> {
+        IntoTokioAsync {
+            encoder: self,
+            writer,
+            buffer: None,
+            default_size: STREAM_BUF_SIZE,
+        }
+    }
+
+    /// Construct an encoder that is read from asynchronously, pulling data from a `tokio` reader.
+    ///
+    /// This is the async equivalent of [`into_read`](#method.into_read) with the same behavior.
+    #[cfg(feature = "tokio")]
+    pub fn into_tokio_read<R: tokio::io::AsyncBufRead>(
+        &mut self,
+        reader: R,
+    ) -> IntoTokioRead<'_, R> {
+        IntoTokioRead {
+            encoder: self,
+            reader,
+        }
+    }
+
     /// Construct an encoder into a vector.
     ///
     /// All encoded data is appended and the vector is __not__ cleared.
@@ -453,9 +755,7 @@ impl<'d, W: Write> IntoStream<'d, W> {
             *write_bytes += result.consumed_out;
             read.consume(result.consumed_in);
 
-            let done = result.status.map_err(|err| {
-                io::Error::new(io::ErrorKind::InvalidData, &*format!("{:?}", err))
-            })?;
+            let done = result.status.map_err(io::Error::from)?;
 
             if let LzwStatus::Done = done {
                 writer.write_all(&outbuf[..result.consumed_out])?;
@@ -491,6 +791,151 @@ impl<'d, W: Write> IntoStream<'d, W> {
     }
 }
 
+#[cfg(feature = "std")]
+impl<R> IntoRead<'_, R> {
+    /// Get a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Get a mutable reference to the underlying reader.
+    ///
+    /// Data read from it directly is not encoded.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Unwrap the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: BufRead> Read for IntoRead<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            let data = self.reader.fill_buf()?;
+
+            if data.is_empty() {
+                self.encoder.finish();
+            }
+
+            let result = self.encoder.encode_bytes(data, buf);
+            self.reader.consume(result.consumed_in);
+
+            // An invalid byte is not consumed, the error repeats on the next call. Return the data
+            // encoded up to that byte first.
+            if result.consumed_out > 0 {
+                return Ok(result.consumed_out);
+            }
+
+            let done = result.status.map_err(io::Error::from)?;
+
+            if let LzwStatus::Done = done {
+                return Ok(0);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> Writer<W> {
+    /// Create a writer that encodes into `writer`.
+    pub fn new(encoder: Encoder, writer: W) -> Self {
+        Writer::with_capacity(WRITER_BUF_SIZE, encoder, writer)
+    }
+
+    /// Create a writer with an intermediate buffer of the specified size.
+    ///
+    /// Encoded data is collected in this buffer before it is written to the sink. The default
+    /// size is 64 KiB.
+    ///
+    /// # Panics
+    /// This method panics if `size` is `0`.
+    pub fn with_capacity(size: usize, encoder: Encoder, writer: W) -> Self {
+        assert_ne!(size, 0, "Attempted to set empty buffer");
+        Writer {
+            encoder,
+            writer,
+            buffer: vec![0; size],
+        }
+    }
+
+    /// Get a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Get a mutable reference to the underlying writer.
+    ///
+    /// Data written to it directly is interleaved with the encoded stream.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Unwrap the underlying writer without finishing the stream.
+    ///
+    /// Any data that was encoded but not yet written, at most a few bytes, is discarded.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Finish the stream, writing all remaining data and the end code.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.encoder.finish();
+
+        loop {
+            let result = self.encoder.encode_bytes(&[], &mut self.buffer);
+            let done = result.status.map_err(io::Error::from)?;
+
+            self.writer.write_all(&self.buffer[..result.consumed_out])?;
+
+            if let LzwStatus::Done = done {
+                return Ok(self.writer);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> Write for Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut consumed = 0;
+
+        while consumed < buf.len() {
+            let result = self
+                .encoder
+                .encode_bytes(&buf[consumed..], &mut self.buffer);
+            consumed += result.consumed_in;
+            self.writer.write_all(&self.buffer[..result.consumed_out])?;
+
+            if let Err(err) = result.status {
+                // Report the bytes up to the invalid one first, the error repeats on next call.
+                if consumed > 0 {
+                    break;
+                }
+
+                return Err(io::Error::from(err));
+            }
+        }
+
+        Ok(consumed)
+    }
+
+    /// Flush the underlying writer.
+    ///
+    /// This does not write the encoded data that is still pending in the encoder, as it can only
+    /// be written with the end of the stream. See [`finish`](struct.Writer.html#method.finish).
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
 impl IntoVec<'_> {
     /// Encode data from a slice.
     pub fn encode(&mut self, read: &[u8]) -> VectorResult {
@@ -588,26 +1033,63 @@ impl IntoVec<'_> {
 #[path = "encode_into_async.rs"]
 mod impl_encode_into_async;
 
+#[cfg(feature = "tokio")]
+#[path = "encode_into_tokio_async.rs"]
+mod impl_encode_into_tokio_async;
+
+#[cfg(feature = "parallel")]
+#[path = "encode_parallel.rs"]
+pub mod parallel;
+
+#[path = "encode_code_writer.rs"]
+mod code_writer;
+pub use self::code_writer::CodeWriter;
+
 impl<B: Buffer> EncodeState<B> {
-    fn new(min_size: u8) -> Self {
+    fn new(min_size: u8, max_code_size: u8, dialect: Dialect, clear_policy: ClearPolicy) -> Self {
         let clear_code = 1 << min_size;
+        let (_, end_code, first_code) = dialect.codes(min_size);
+        let ratio = match clear_policy {
+            ClearPolicy::Adaptive => Some(RatioCheck::new()),
+            _ => None,
+        };
+
         let mut tree = Tree::default();
-        tree.init(min_size);
+        tree.init(min_size, first_code);
+        tree.limit = 1 << max_code_size;
+
         let mut state = EncodeState {
             min_size,
+            max_code_size: dialect.code_size_limit(max_code_size),
             tree,
             has_ended: false,
+            finished: false,
             is_tiff: false,
             current_code: clear_code,
             clear_code,
+            end_code,
+            dialect,
+            codes: 0,
+            padding: 0,
+            clear_policy,
+            codes_since_clear: 0,
+            ratio,
             buffer: B::new(min_size),
+            bytes_read: 0,
         };
 
         state.bump_initial_code_size();
-        state.buffer_code(clear_code);
+        state.buffer_initial_clear();
         state
     }
 
+    /// Streams of `compress` start without a clear code.
+    fn buffer_initial_clear(&mut self) {
+        if let Dialect::Standard = self.dialect {
+            self.buffer_code(self.clear_code);
+        }
+    }
+
     /// Initialize rather odd stream sizes.
     fn bump_initial_code_size(&mut self) {
         // At min_size 0 the alphabet plus clear/end codes already exhaust (or exceed) the starting
@@ -619,7 +1101,7 @@ impl<B: Buffer> EncodeState<B> {
         //
         // Normal-size (>= 2) streams fall straight through.
         if self.clear_code >= self.buffer.max_code() - Code::from(self.is_tiff)
-            && self.buffer.code_size() < MAX_CODESIZE
+            && self.buffer.code_size() < self.max_code_size
         {
             self.buffer.bump_code_size();
         }
@@ -638,34 +1120,42 @@ impl<B: Buffer> Stateful for EncodeState<B> {
             }
 
             if inp.is_empty() && self.has_ended {
-                let end = self.end_code();
-                if self.current_code != end {
+                if !self.finished {
                     if self.current_code != self.clear_code {
                         self.buffer_code(self.current_code);
 
                         // When reading this code, the decoder will add an extra entry to its table
                         // before reading th end code. Thusly, it may increase its code size based
                         // on this additional entry.
-                        if self.tree.keys.len() + usize::from(self.is_tiff)
-                            > usize::from(self.buffer.max_code())
-                            && self.buffer.code_size() < MAX_CODESIZE
+                        if self.end_code.is_some()
+                            && self.tree.keys.len() + usize::from(self.is_tiff)
+                                > usize::from(self.buffer.max_code())
+                            && self.buffer.code_size() < self.max_code_size
                         {
                             self.buffer.bump_code_size();
                         }
                     }
-                    self.buffer_code(end);
-                    self.current_code = end;
+                    if let Some(end) = self.end_code {
+                        self.buffer_code(end);
+                    }
+                    self.finished = true;
                     self.buffer_pad();
                 }
 
                 break;
             }
 
+            // Entries are only added until the tree is full, the next code is written without one.
+            let was_full = self.tree.is_full();
             let mut next_code = None;
             let mut bytes = inp.iter();
             while let Some(&byte) = bytes.next() {
                 if self.min_size < 8 && byte >= 1 << self.min_size {
-                    status = Err(LzwError::InvalidCode);
+                    status = Err(LzwError::InvalidSymbol {
+                        byte,
+                        symbol_size: self.min_size,
+                        offset: self.bytes_read + (c_in - inp.len()) as u64,
+                    });
                     break 'encoding;
                 }
 
@@ -686,29 +1176,53 @@ impl<B: Buffer> Stateful for EncodeState<B> {
                 None => break,
                 Some(code) => {
                     self.buffer_code(code);
+                    self.codes_since_clear += 1;
 
-                    if self.tree.keys.len() + usize::from(self.is_tiff)
+                    // A full tree counts one more entry, as the decoder still tries to add it.
+                    if self.tree.keys.len() + usize::from(was_full) + usize::from(self.is_tiff)
                         > usize::from(self.buffer.max_code()) + 1
-                        && self.buffer.code_size() < MAX_CODESIZE
+                        && self.buffer.code_size() < self.max_code_size
                     {
-                        self.buffer.bump_code_size();
+                        self.bump_code_size();
                     }
 
-                    if self.tree.keys.len() > MAX_ENTRIES {
-                        self.buffer_code(self.clear_code);
-                        self.tree.reset(self.min_size);
-                        self.buffer.clear(self.min_size);
+                    let clear = match self.clear_policy {
+                        // The decoder would add an entry which does not fit the table.
+                        ClearPolicy::WhenFull => was_full,
+                        ClearPolicy::Freeze => false,
+                        ClearPolicy::Interval(interval) => {
+                            was_full || self.codes_since_clear >= interval
+                        }
+                        ClearPolicy::Adaptive => match &mut self.ratio {
+                            Some(check) if was_full => {
+                                let bytes_in = check.bytes_in + (c_in - inp.len()) as u64;
+                                let bytes_out = check.bytes_out + (c_out - out.len()) as u64;
+                                check.should_clear(bytes_in, bytes_out)
+                            }
+                            _ => false,
+                        },
+                    };
+
+                    if clear {
+                        self.clear_tables();
                     }
                 }
             }
         }
 
-        if inp.is_empty() && self.current_code == self.end_code() {
+        if inp.is_empty() && self.finished {
             if !self.flush_out(&mut out) {
                 status = Ok(LzwStatus::Done);
             }
         }
 
+        if let Some(check) = &mut self.ratio {
+            check.bytes_in += (c_in - inp.len()) as u64;
+            check.bytes_out += (c_out - out.len()) as u64;
+        }
+
+        self.bytes_read += (c_in - inp.len()) as u64;
+
         BufferResult {
             consumed_in: c_in - inp.len(),
             consumed_out: c_out - out.len(),
@@ -726,24 +1240,52 @@ impl<B: Buffer> Stateful for EncodeState<B> {
 
     fn reset(&mut self) {
         self.restart();
+        self.finished = false;
         self.current_code = self.clear_code;
-        self.tree.reset(self.min_size);
+        self.codes = 0;
+        self.padding = 0;
+        self.codes_since_clear = 0;
+        self.bytes_read = 0;
+        if let Some(check) = &mut self.ratio {
+            *check = RatioCheck::new();
+        }
+        self.tree.reset(self.min_size, self.first_code());
         self.buffer.reset(self.min_size);
-        self.buffer_code(self.clear_code);
+        self.buffer_initial_clear();
+    }
+
+    fn clone_boxed(&self) -> Box<dyn Stateful + Send + 'static> {
+        Box::new(self.clone())
     }
 }
 
 impl<B: Buffer> EncodeState<B> {
     fn push_out(&mut self, out: &mut &mut [u8]) -> bool {
-        self.buffer.push_out(out)
+        self.push_padding(out) || self.buffer.push_out(out)
     }
 
     fn flush_out(&mut self, out: &mut &mut [u8]) -> bool {
-        self.buffer.flush_out(out)
+        self.push_padding(out) || self.buffer.flush_out(out)
+    }
+
+    /// Write all padding bits, returning if some of them remain.
+    fn push_padding(&mut self, out: &mut &mut [u8]) -> bool {
+        while self.padding > 0 {
+            if self.buffer.flush_out(out) {
+                return true;
+            }
+
+            // Less than a byte is buffered now so this fits.
+            let bits = self.padding.min(56);
+            self.buffer.buffer_zeros(bits as u8);
+            self.padding -= bits;
+        }
+
+        false
     }
 
-    fn end_code(&self) -> Code {
-        self.clear_code + 1
+    fn first_code(&self) -> Code {
+        self.dialect.codes(self.min_size).2
     }
 
     fn buffer_pad(&mut self) {
@@ -752,6 +1294,64 @@ impl<B: Buffer> EncodeState<B> {
 
     fn buffer_code(&mut self, code: Code) {
         self.buffer.buffer_code(code);
+        self.codes = (self.codes + 1) % 8;
+    }
+
+    fn bump_code_size(&mut self) {
+        self.align_group();
+        self.buffer.bump_code_size();
+    }
+
+    /// Write a clear code and reset the dictionary.
+    fn clear_tables(&mut self) {
+        self.buffer_code(self.clear_code);
+        self.align_group();
+        self.codes_since_clear = 0;
+        self.tree.reset(self.min_size, self.first_code());
+        self.buffer.clear(self.min_size);
+    }
+
+    /// Pad to the end of the group of eight codes before changing the code size, as `compress`.
+    ///
+    /// The padding is only written by the next call to `push_out` or `flush_out` so no other code
+    /// must be buffered in the meantime.
+    fn align_group(&mut self) {
+        if let Dialect::Compress { .. } = self.dialect {
+            let remaining = (8 - self.codes) % 8;
+            self.padding += u16::from(remaining) * u16::from(self.buffer.code_size());
+        }
+
+        self.codes = 0;
+    }
+}
+
+impl RatioCheck {
+    fn new() -> Self {
+        RatioCheck {
+            bytes_in: 0,
+            bytes_out: 0,
+            checkpoint: CHECK_GAP,
+            ratio: 0,
+        }
+    }
+
+    /// Check the compression ratio at each checkpoint, returning if the dictionary should be
+    /// cleared because the ratio dropped.
+    fn should_clear(&mut self, bytes_in: u64, bytes_out: u64) -> bool {
+        if bytes_in < self.checkpoint {
+            return false;
+        }
+
+        self.checkpoint = bytes_in + CHECK_GAP;
+        let ratio = (bytes_in << 8) / bytes_out.max(1);
+
+        if ratio >= self.ratio {
+            self.ratio = ratio;
+            false
+        } else {
+            self.ratio = 0;
+            true
+        }
     }
 }
 
@@ -808,17 +1408,26 @@ impl Buffer for MsbBuffer {
         self.bits_in_buffer += to_byte;
     }
 
+    fn buffer_zeros(&mut self, bits: u8) {
+        debug_assert!(self.bits_in_buffer < 8 && bits <= 56);
+        self.bits_in_buffer += bits;
+    }
+
     fn bump_code_size(&mut self) {
         self.code_size += 1;
     }
 
     fn max_code(&self) -> Code {
-        (1 << self.code_size) - 1
+        Code::MAX >> (16 - self.code_size)
     }
 
     fn code_size(&self) -> u8 {
         self.code_size
     }
+
+    fn buffered_bits(&self) -> u8 {
+        self.bits_in_buffer
+    }
 }
 
 impl Buffer for LsbBuffer {
@@ -873,25 +1482,34 @@ impl Buffer for LsbBuffer {
         self.bits_in_buffer += to_byte;
     }
 
+    fn buffer_zeros(&mut self, bits: u8) {
+        debug_assert!(self.bits_in_buffer < 8 && bits <= 56);
+        self.bits_in_buffer += bits;
+    }
+
     fn bump_code_size(&mut self) {
         self.code_size += 1;
     }
 
     fn max_code(&self) -> Code {
-        (1 << self.code_size) - 1
+        Code::MAX >> (16 - self.code_size)
     }
 
     fn code_size(&self) -> u8 {
         self.code_size
     }
+
+    fn buffered_bits(&self) -> u8 {
+        self.bits_in_buffer
+    }
 }
 
 impl Tree {
-    fn init(&mut self, min_size: u8) {
+    fn init(&mut self, min_size: u8, first_code: Code) {
         // We need a way to represent the state of a currently empty buffer. We use the clear code
         // for this, thus create one complex mapping that leads to the one-char base codes.
         self.keys
-            .resize((1 << min_size) + 2, FullKey::NoSuccessor.into());
+            .resize(usize::from(first_code), FullKey::NoSuccessor.into());
         self.complex.push(Full {
             char_continuation: [0; 256],
         });
@@ -902,18 +1520,22 @@ impl Tree {
         self.keys[1 << min_size] = FullKey::Full(0).into();
     }
 
-    fn reset(&mut self, min_size: u8) {
+    fn reset(&mut self, min_size: u8, first_code: Code) {
         self.simples.clear();
-        self.keys.truncate((1 << min_size) + 2);
+        self.keys.truncate(usize::from(first_code));
         // Keep entry for clear code.
         self.complex.truncate(1);
         // The first complex is not changed..
-        for k in self.keys[..(1 << min_size) + 2].iter_mut() {
+        for k in self.keys.iter_mut() {
             *k = FullKey::NoSuccessor.into();
         }
         self.keys[1 << min_size] = FullKey::Full(0).into();
     }
 
+    fn is_full(&self) -> bool {
+        self.keys.len() >= self.limit
+    }
+
     #[allow(clippy::needless_range_loop)]
     fn at_key(&self, code: Code, ch: u8) -> Option<Code> {
         let key = self.keys[usize::from(code)];
@@ -953,7 +1575,9 @@ impl Tree {
             FullKey::Full(idx) => {
                 let full = &self.complex[usize::from(idx)];
                 let precode = full.char_continuation[usize::from(ch)];
-                if usize::from(precode) < MAX_ENTRIES {
+                // With 16-bit codes the last code is indistinguishable from a missing one. Not
+                // finding it is harmless, the table is full when that code exists.
+                if precode != Code::MAX {
                     Some(precode)
                 } else {
                     None
@@ -964,11 +1588,14 @@ impl Tree {
 
     /// Iterate to the next char.
     /// Return Ok when it was already in the tree or creates a new entry for it and returns Err.
+    /// When the tree has reached its limit, no new entry is created.
     fn iterate(&mut self, code: Code, ch: u8) -> Result<Code, Code> {
         if let Some(next) = self.at_key(code, ch) {
             Ok(next)
-        } else {
+        } else if self.keys.len() < self.limit {
             Err(self.append(code, ch))
+        } else {
+            Err(code)
         }
     }
 
@@ -1027,9 +1654,9 @@ impl Default for Simple {
 
 impl From<CompressedKey> for FullKey {
     fn from(CompressedKey(key): CompressedKey) -> Self {
-        match (key >> MAX_CODESIZE) & 0xf {
-            0 => FullKey::Full(key & 0xfff),
-            1 => FullKey::Simple(key & 0xfff),
+        match (key >> 16) & 0xf {
+            0 => FullKey::Full(key as u16),
+            1 => FullKey::Simple(key as u16),
             _ => FullKey::NoSuccessor,
         }
     }
@@ -1038,9 +1665,9 @@ impl From<CompressedKey> for FullKey {
 impl From<FullKey> for CompressedKey {
     fn from(full: FullKey) -> Self {
         CompressedKey(match full {
-            FullKey::NoSuccessor => 0x2000,
-            FullKey::Simple(code) => 0x1000 | code,
-            FullKey::Full(code) => code,
+            FullKey::NoSuccessor => 0x2_0000,
+            FullKey::Simple(code) => 0x1_0000 | u32::from(code),
+            FullKey::Full(code) => u32::from(code),
         })
     }
 }
@@ -1063,11 +1690,14 @@ mod tests {
         encoder.finish();
         // We require simulation of normality, that is byte-for-byte compression.
         let result = encoder.encode_bytes(input, target);
-        assert!(if let Err(LzwError::InvalidCode) = result.status {
-            true
-        } else {
-            false
-        });
+        assert_eq!(
+            result.status.unwrap_err(),
+            LzwError::InvalidSymbol {
+                byte: 1 << BIT_LEN,
+                symbol_size: BIT_LEN,
+                offset: 1,
+            }
+        );
         assert_eq!(result.consumed_in, 1);
 
         let fixed = encoder.encode_bytes(&[1, 0], &mut target[result.consumed_out..]);
@@ -0,0 +1,141 @@
+//! Write codes of any width, with the bit packing of the encoder.
+use super::{Buffer, LsbBuffer, MsbBuffer};
+use crate::alloc::vec::Vec;
+use crate::{BitOrder, Code};
+
+/// Writes codes of arbitrary width as packed bits.
+///
+/// This packs bits exactly as the encoder does, in either bit order, but leaves the choice of each
+/// code and its width to the caller. It is meant for formats related to LZW which the encoder does
+/// not support, and for building test data such as deliberately malformed streams.
+///
+/// # Examples
+///
+/// ```
+/// use weezl::{BitOrder, encode::CodeWriter};
+///
+/// let mut writer = CodeWriter::new(BitOrder::Msb);
+/// writer.write_code(0b101, 3);
+/// writer.align_to_byte();
+/// writer.write_code(0xff, 8);
+/// assert_eq!(writer.finish(), [0b1010_0000, 0xff]);
+/// ```
+pub struct CodeWriter {
+    buffer: WriteBuffer,
+    out: Vec<u8>,
+    /// The number of bits written so far, including padding.
+    bits: u64,
+}
+
+enum WriteBuffer {
+    Msb(MsbBuffer),
+    Lsb(LsbBuffer),
+}
+
+impl CodeWriter {
+    /// Create a writer that packs bits in the given bit order.
+    pub fn new(order: BitOrder) -> Self {
+        let buffer = match order {
+            BitOrder::Msb => WriteBuffer::Msb(MsbBuffer::new(0)),
+            BitOrder::Lsb => WriteBuffer::Lsb(LsbBuffer::new(0)),
+        };
+
+        CodeWriter {
+            buffer,
+            out: Vec::new(),
+            bits: 0,
+        }
+    }
+
+    /// Append a code with `width` bits.
+    ///
+    /// # Panics
+    ///
+    /// The `width` needs to be in the interval `1..=16` and the code must fit into it.
+    pub fn write_code(&mut self, code: u16, width: u8) {
+        fn write<B: Buffer>(buffer: &mut B, out: &mut Vec<u8>, code: Code, width: u8) {
+            if buffer.code_size() != width {
+                buffer.clear(width - 1);
+            }
+
+            // With less than a byte left in the buffer any code fits.
+            flush(buffer, out);
+            buffer.buffer_code(code);
+        }
+
+        crate::assert_code_width(width);
+        assert!(
+            code <= Code::MAX >> (16 - width),
+            "Code {} does not fit into {} bits",
+            code,
+            width
+        );
+
+        match &mut self.buffer {
+            WriteBuffer::Msb(buffer) => write(buffer, &mut self.out, code, width),
+            WriteBuffer::Lsb(buffer) => write(buffer, &mut self.out, code, width),
+        }
+
+        self.bits += u64::from(width);
+    }
+
+    /// Pad with zero bits up to the next byte boundary.
+    pub fn align_to_byte(&mut self) {
+        match &mut self.buffer {
+            WriteBuffer::Msb(buffer) => buffer.buffer_pad(),
+            WriteBuffer::Lsb(buffer) => buffer.buffer_pad(),
+        }
+
+        self.bits = self.bits.next_multiple_of(8);
+    }
+
+    /// Append `bits` zero bits, as padding between codes.
+    pub fn pad(&mut self, mut bits: u64) {
+        while bits > 0 {
+            let width = bits.min(16) as u8;
+            self.write_code(0, width);
+            bits -= u64::from(width);
+        }
+    }
+
+    /// Pad with zero bits up to the next multiple of `boundary` bits, counted from the start.
+    ///
+    /// Groups that do not start at such a multiple, as the groups of eight codes in `compress`
+    /// after a change of the code size, can be completed with [`pad`] instead.
+    ///
+    /// # Panics
+    ///
+    /// The `boundary` must not be `0`.
+    ///
+    /// [`pad`]: #method.pad
+    pub fn align_to(&mut self, boundary: u64) {
+        assert_ne!(boundary, 0, "Attempted to align to a boundary of zero bits");
+        self.pad((boundary - self.bits % boundary) % boundary);
+    }
+
+    /// The number of bits written so far, including padding.
+    pub fn bit_len(&self) -> u64 {
+        self.bits
+    }
+
+    /// Pad to a full byte and return all written bytes.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.align_to_byte();
+
+        match &mut self.buffer {
+            WriteBuffer::Msb(buffer) => flush(buffer, &mut self.out),
+            WriteBuffer::Lsb(buffer) => flush(buffer, &mut self.out),
+        }
+
+        self.out
+    }
+}
+
+/// Move all full bytes of the buffer to the output.
+fn flush<B: Buffer>(buffer: &mut B, out: &mut Vec<u8>) {
+    let mut bytes = [0; 8];
+    let mut tail = &mut bytes[..];
+    buffer.flush_out(&mut tail);
+    let len = 8 - tail.len();
+    out.extend_from_slice(&bytes[..len]);
+}
@@ -105,10 +105,7 @@ impl<'d, W: futures::io::AsyncWrite + core::marker::Unpin> IntoAsync<'d, W> {
             let done = match result.status {
                 Ok(ok) => ok,
                 Err(err) => {
-                    break Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        &*format!("{:?}", err),
-                    ));
+                    break Err(io::Error::from(err));
                 }
             };
 
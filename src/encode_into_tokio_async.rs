@@ -0,0 +1,199 @@
+use crate::encode::{IntoTokioAsync, IntoTokioRead};
+use crate::error::LzwStatus;
+use crate::error::StreamResult;
+use crate::StreamBuf;
+use core::pin::Pin;
+use core::task::{ready, Context, Poll};
+use std::io;
+
+impl<'d, W: tokio::io::AsyncWrite + core::marker::Unpin> IntoTokioAsync<'d, W> {
+    /// Encode data from a reader.
+    ///
+    /// This will drain the supplied reader. It will not encode an end marker after all data has
+    /// been processed.
+    pub async fn encode(&mut self, read: impl tokio::io::AsyncBufRead) -> StreamResult {
+        self.encode_part(read, false).await
+    }
+
+    /// Encode data from a reader and an end marker.
+    pub async fn encode_all(mut self, read: impl tokio::io::AsyncBufRead) -> StreamResult {
+        self.encode_part(read, true).await
+    }
+
+    /// Set the size of the intermediate decode buffer.
+    ///
+    /// A buffer of this size is allocated to hold one part of the decoded stream when no buffer is
+    /// available and any decoding method is called. No buffer is allocated if `set_buffer` has
+    /// been called. The buffer is reused.
+    ///
+    /// # Panics
+    /// This method panics if `size` is `0`.
+    pub fn set_buffer_size(&mut self, size: usize) {
+        assert_ne!(size, 0, "Attempted to set empty buffer");
+        self.default_size = size;
+    }
+
+    /// Use a particular buffer as an intermediate decode buffer.
+    ///
+    /// Calling this sets or replaces the buffer. When a buffer has been set then it is used
+    /// instead of dynamically allocating a buffer. Note that the size of the buffer is critical
+    /// for efficient decoding. Some optimization techniques require the buffer to hold one or more
+    /// previous decoded words. There is also additional overhead from `write` calls each time the
+    /// buffer has been filled.
+    ///
+    /// # Panics
+    /// This method panics if the `buffer` is empty.
+    pub fn set_buffer(&mut self, buffer: &'d mut [u8]) {
+        assert_ne!(buffer.len(), 0, "Attempted to set empty buffer");
+        self.buffer = Some(StreamBuf::Borrowed(buffer));
+    }
+
+    async fn encode_part(
+        &mut self,
+        read: impl tokio::io::AsyncBufRead,
+        finish: bool,
+    ) -> StreamResult {
+        use tokio::io::AsyncBufReadExt;
+        use tokio::io::AsyncWriteExt;
+
+        let IntoTokioAsync {
+            encoder,
+            writer,
+            buffer,
+            default_size,
+        } = self;
+
+        let mut read = core::pin::pin!(read);
+
+        let mut bytes_read = 0;
+        let mut bytes_written = 0;
+
+        // Converting to mutable refs to move into the `once` closure.
+        let read_bytes = &mut bytes_read;
+        let write_bytes = &mut bytes_written;
+
+        let outbuf: &mut [u8] =
+            match buffer.get_or_insert_with(|| StreamBuf::Owned(vec![0u8; *default_size])) {
+                StreamBuf::Borrowed(slice) => slice,
+                StreamBuf::Owned(vec) => &mut *vec,
+            };
+        assert!(!outbuf.is_empty());
+
+        let status = loop {
+            // Try to grab one buffer of input data.
+            let mut filler = read.as_mut();
+            let data = match filler.fill_buf().await {
+                Ok(buf) => buf,
+                Err(err) => break Err(err),
+            };
+
+            if data.is_empty() {
+                if finish {
+                    encoder.finish();
+                } else {
+                    break Ok(());
+                }
+            }
+
+            // Decode as much of the buffer as fits.
+            let result = encoder.encode_bytes(data, &mut outbuf[..]);
+            // Do the bookkeeping and consume the buffer.
+            *read_bytes += result.consumed_in;
+            *write_bytes += result.consumed_out;
+            read.as_mut().consume(result.consumed_in);
+
+            // Handle an error status in the result.
+            let done = match result.status {
+                Ok(ok) => ok,
+                Err(err) => {
+                    break Err(io::Error::from(err));
+                }
+            };
+
+            if let LzwStatus::Done = done {
+                break writer.write_all(&outbuf[..result.consumed_out]).await;
+            }
+
+            if let LzwStatus::NoProgress = done {
+                break Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "No more data but no end marker detected",
+                ));
+            }
+
+            // And finish by writing our result.
+            // TODO: we may lose data on error (also on status error above) which we might want to
+            // deterministically handle so that we don't need to restart everything from scratch as
+            // the only recovery strategy. Any changes welcome.
+            match writer.write_all(&outbuf[..result.consumed_out]).await {
+                Ok(_) => {}
+                Err(err) => break Err(err),
+            }
+        };
+
+        StreamResult {
+            bytes_read,
+            bytes_written,
+            status,
+        }
+    }
+}
+
+impl<R> IntoTokioRead<'_, R> {
+    /// Get a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Get a mutable reference to the underlying reader.
+    ///
+    /// Data read from it directly is not encoded.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Unwrap the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: tokio::io::AsyncBufRead + core::marker::Unpin> tokio::io::AsyncRead
+    for IntoTokioRead<'_, R>
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let IntoTokioRead { encoder, reader } = self.get_mut();
+
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        loop {
+            let data = ready!(Pin::new(&mut *reader).poll_fill_buf(cx))?;
+
+            if data.is_empty() {
+                encoder.finish();
+            }
+
+            let result = encoder.encode_bytes(data, buf.initialize_unfilled());
+            Pin::new(&mut *reader).consume(result.consumed_in);
+            buf.advance(result.consumed_out);
+
+            // An invalid byte is not consumed, the error repeats on the next call. Return the data
+            // encoded up to that byte first.
+            if result.consumed_out > 0 {
+                return Poll::Ready(Ok(()));
+            }
+
+            let done = result.status.map_err(io::Error::from)?;
+
+            if let LzwStatus::Done = done {
+                return Poll::Ready(Ok(()));
+            }
+        }
+    }
+}
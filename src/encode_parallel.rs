@@ -0,0 +1,284 @@
+//! Encode large inputs on several threads.
+//!
+//! The input is split into chunks which are encoded independently, each starting from an empty
+//! dictionary. The encoded chunks are then joined into one stream with a clear code between them,
+//! written with the code size at the end of the preceding chunk, and with their bits shifted to
+//! continue the packing of the stream.
+//!
+//! The result is a valid stream for the configuration that any decoder accepts, but it is not the
+//! same as the output of an [`Encoder`]. Every chunk starts with an empty dictionary and so
+//! compresses slightly worse, which is negligible for chunks of a few megabytes.
+//!
+//! # Examples
+//!
+//! ```
+//! use weezl::{BitOrder, decode::Decoder, encode::{parallel, Configuration}};
+//!
+//! let data: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8 ^ (i / 1000) as u8).collect();
+//! let config = Configuration::new(BitOrder::Lsb, 8);
+//! let encoded = parallel::encode_chunks(&config, &data, 50_000).unwrap();
+//!
+//! let decoded = Decoder::new(BitOrder::Lsb, 8).decode(&encoded).unwrap();
+//! assert_eq!(decoded, data);
+//! ```
+//!
+//! [`Encoder`]: ../struct.Encoder.html
+use super::{Buffer, Configuration, EncodeState, LsbBuffer, MsbBuffer, Stateful};
+use crate::alloc::{vec, vec::Vec};
+use crate::error::LzwError;
+use crate::{jobs, BitOrder, Code, Dialect};
+
+/// The size of the chunks used by [`encode`].
+///
+/// [`encode`]: fn.encode.html
+pub const DEFAULT_CHUNK_SIZE: usize = 1 << 22;
+
+/// The output of one chunk, without the clear code before it and the code after it.
+struct Segment {
+    /// The packed codes, with zero bits up to the next byte.
+    bytes: Vec<u8>,
+    /// The number of bits of the codes.
+    bits: u64,
+    /// The code size of the code following the segment.
+    code_size: u8,
+    /// The number of codes written in the current group of eight.
+    codes: u8,
+}
+
+/// Appends packed bits at any bit offset.
+struct Stitch<'a> {
+    order: BitOrder,
+    out: &'a mut Vec<u8>,
+    /// The number of bits in `out`, whose last byte may be partial.
+    bits: u64,
+}
+
+/// Encode data on all available threads, in chunks of [`DEFAULT_CHUNK_SIZE`].
+///
+/// [`DEFAULT_CHUNK_SIZE`]: constant.DEFAULT_CHUNK_SIZE.html
+pub fn encode(configuration: &Configuration, data: &[u8]) -> Result<Vec<u8>, LzwError> {
+    encode_chunks(configuration, data, DEFAULT_CHUNK_SIZE)
+}
+
+/// Encode data on all available threads, in chunks of the given size.
+///
+/// An invalid symbol in the input is reported as by the [`Encoder`], with its offset in `data`.
+///
+/// # Panics
+///
+/// This method panics if `chunk_size` is `0`.
+///
+/// [`Encoder`]: ../struct.Encoder.html
+pub fn encode_chunks(
+    configuration: &Configuration,
+    data: &[u8],
+    chunk_size: usize,
+) -> Result<Vec<u8>, LzwError> {
+    let mut out = Vec::new();
+    encode_into(configuration, data, chunk_size, &mut out)?;
+    Ok(out)
+}
+
+/// Append the encoded stream to `out`.
+pub(crate) fn encode_into(
+    configuration: &Configuration,
+    data: &[u8],
+    chunk_size: usize,
+    out: &mut Vec<u8>,
+) -> Result<(), LzwError> {
+    assert_ne!(chunk_size, 0, "Attempted to encode empty chunks");
+
+    let segments = match configuration.order {
+        BitOrder::Lsb => encode_segments::<LsbBuffer>(configuration, data, chunk_size)?,
+        BitOrder::Msb => encode_segments::<MsbBuffer>(configuration, data, chunk_size)?,
+    };
+
+    let (clear_code, end_code, _) = configuration.dialect.codes(configuration.size);
+    let clear_code = clear_code.expect("The encoder requires a clear code");
+
+    let bits = out.len() as u64 * 8;
+    let mut stitch = Stitch {
+        order: configuration.order,
+        out,
+        bits,
+    };
+
+    let count = segments.len();
+    for (idx, segment) in segments.iter().enumerate() {
+        stitch.append(&segment.bytes, segment.bits);
+
+        if idx + 1 < count {
+            stitch.code(clear_code, segment.code_size);
+
+            // As `EncodeState::clear_tables` does.
+            if let Dialect::Compress { .. } = configuration.dialect {
+                let codes = (segment.codes + 1) % 8;
+                let padding = (8 - codes) % 8 * segment.code_size;
+                stitch.zeros(padding.into());
+            }
+        } else if let Some(end_code) = end_code {
+            stitch.code(end_code, segment.code_size);
+        }
+    }
+
+    Ok(())
+}
+
+fn encode_segments<B: Buffer>(
+    configuration: &Configuration,
+    data: &[u8],
+    chunk_size: usize,
+) -> Result<Vec<Segment>, LzwError> {
+    let chunks: Vec<&[u8]> = if data.is_empty() {
+        vec![data]
+    } else {
+        data.chunks(chunk_size).collect()
+    };
+
+    let last = chunks.len() - 1;
+    let jobs: Vec<_> = chunks.into_iter().enumerate().collect();
+
+    jobs::run(
+        jobs,
+        || (),
+        |(), (idx, chunk)| {
+            let offset = idx * chunk_size;
+            encode_segment::<B>(configuration, chunk, offset, idx == 0, idx == last)
+        },
+    )
+    .into_iter()
+    .collect()
+}
+
+/// Encode one chunk of the input, starting at `offset`.
+fn encode_segment<B: Buffer>(
+    configuration: &Configuration,
+    data: &[u8],
+    offset: usize,
+    first: bool,
+    last: bool,
+) -> Result<Segment, LzwError> {
+    const CHUNK: usize = 1 << 16;
+
+    let mut state = EncodeState::<B>::new(
+        configuration.size,
+        configuration.max_code_size,
+        configuration.dialect,
+        configuration.effective_clear_policy(),
+    );
+    state.is_tiff = configuration.tiff;
+    state.bytes_read = offset as u64;
+
+    if !first {
+        // The clear code is written with the code size of the previous segment instead.
+        let code_size = state.buffer.code_size();
+        state.buffer.reset(state.min_size);
+        while state.buffer.code_size() < code_size {
+            state.buffer.bump_code_size();
+        }
+        state.codes = 0;
+    }
+
+    let mut bytes = Vec::with_capacity(data.len() / 2);
+    let mut inp = data;
+    while !inp.is_empty() {
+        // Each byte of input produces at most a code of 16 bits.
+        let len = bytes.len();
+        bytes.resize(len + CHUNK.min(2 * inp.len() + 16), 0);
+        let result = state.advance(inp, &mut bytes[len..]);
+        bytes.truncate(len + result.consumed_out);
+        inp = &inp[result.consumed_in..];
+        result.status?;
+    }
+
+    // Finish as the encoder does, but without the end code. A clear code follows instead, so the
+    // decoder adds an entry for the last code and may increase the code size for it.
+    let followed = !last || state.end_code.is_some();
+    if state.current_code != state.clear_code {
+        state.buffer_code(state.current_code);
+
+        if followed
+            && state.tree.keys.len() + usize::from(state.is_tiff)
+                > usize::from(state.buffer.max_code())
+            && state.buffer.code_size() < state.max_code_size
+        {
+            state.bump_code_size();
+        }
+    }
+
+    // Write all padding and full bytes, then the partial byte.
+    while flush(&mut state, &mut bytes) {}
+    let bits = bytes.len() as u64 * 8 + u64::from(state.buffer.buffered_bits());
+    state.buffer_pad();
+    flush(&mut state, &mut bytes);
+
+    Ok(Segment {
+        bytes,
+        bits,
+        code_size: state.buffer.code_size(),
+        codes: state.codes,
+    })
+}
+
+/// Flush some bytes of the state, returning if more remain.
+fn flush<B: Buffer>(state: &mut EncodeState<B>, bytes: &mut Vec<u8>) -> bool {
+    const SPACE: usize = 64;
+
+    let len = bytes.len();
+    bytes.resize(len + SPACE, 0);
+    let mut out = &mut bytes[len..];
+    let more = state.flush_out(&mut out);
+    let written = SPACE - out.len();
+    bytes.truncate(len + written);
+    more
+}
+
+impl Stitch<'_> {
+    /// Append `bits` bits of `src`, in which all bits after these are zero.
+    fn append(&mut self, src: &[u8], bits: u64) {
+        let shift = (self.bits % 8) as u32;
+
+        if bits == 0 {
+            return;
+        } else if shift == 0 {
+            self.out.extend_from_slice(src);
+        } else {
+            let rest = 8 - shift;
+            let last = self.out.len() - 1;
+            let tail = src[src.len() - 1];
+
+            match self.order {
+                BitOrder::Lsb => {
+                    self.out[last] |= src[0] << shift;
+                    self.out
+                        .extend(src.windows(2).map(|w| w[0] >> rest | w[1] << shift));
+                    self.out.push(tail >> rest);
+                }
+                BitOrder::Msb => {
+                    self.out[last] |= src[0] >> shift;
+                    self.out
+                        .extend(src.windows(2).map(|w| w[0] << rest | w[1] >> shift));
+                    self.out.push(tail << rest);
+                }
+            }
+        }
+
+        self.bits += bits;
+        self.out.truncate(self.bits.div_ceil(8) as usize);
+    }
+
+    fn code(&mut self, code: Code, width: u8) {
+        let bytes = match self.order {
+            BitOrder::Lsb => u32::from(code).to_le_bytes(),
+            BitOrder::Msb => (u32::from(code) << (32 - width)).to_be_bytes(),
+        };
+
+        let len = usize::from(width).div_ceil(8);
+        self.append(&bytes[..len], width.into());
+    }
+
+    fn zeros(&mut self, bits: u64) {
+        self.bits += bits;
+        self.out.resize(self.bits.div_ceil(8) as usize, 0);
+    }
+}
@@ -52,24 +52,126 @@ pub enum LzwStatus {
     Done,
 }
 
-/// The error kind after unsuccessful coding of an LZW stream.
-#[derive(Debug, Clone, Copy)]
+/// The error after unsuccessful coding of an LZW stream.
+///
+/// More variants may be added in the future, to describe errors in more detail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum LzwError {
-    /// The input contained an invalid code.
+    /// The decoder read a code that can not be decoded at this point.
+    ///
+    /// This refers to a code that has no prior code to be derived from, such as the next code
+    /// directly after a clear code, or any code other than a clear code when the stream must start
+    /// with one. Usually this means the data is corrupt or was encoded with other parameters.
+    InvalidCode(CodeError),
+    /// The decoder read a code which exceeds its dictionary.
+    ///
+    /// The code is larger than the next code to be assigned, so it is not known through the prior
+    /// decoding stages. Usually this means the data is corrupt or was encoded with other
+    /// parameters.
+    CodeOutOfRange(CodeError),
+    /// The encoder was given a byte which has no code representation.
+    ///
+    /// The byte is larger than permitted by the `size` parameter given to the Encoder.
+    InvalidSymbol {
+        /// The offending byte.
+        byte: u8,
+        /// The symbol size of the encoder.
+        symbol_size: u8,
+        /// The offset of the byte in the input data.
+        offset: u64,
+    },
+    /// The input ended before the end of the stream.
+    ///
+    /// All codes up to this point were valid but the stream requires an end code which was not
+    /// found. The data may have been truncated.
+    UnexpectedEof,
+    /// The header of the stream was invalid.
+    ///
+    /// This is only reported for formats with a header, such as `.Z` files of `compress`.
+    InvalidHeader,
+    /// The decoded data exceeds a limit of the decoder configuration.
     ///
-    /// For decompression this refers to a code larger than those currently known through the prior
-    /// decoding stages. For compression this refers to a byte that has no code representation due
-    /// to being larger than permitted by the `size` parameter given to the Encoder.
-    InvalidCode,
+    /// The stream decodes to more data than permitted, either in total or relative to its input.
+    /// This is how streams are stopped that were crafted to expand enormously.
+    LimitExceeded,
+}
+
+/// The details of a code that could not be decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CodeError {
+    /// The value of the offending code.
+    pub code: u16,
+    /// The code that the next new table entry would have been assigned.
+    pub next_code: u32,
+    /// The code size at which the code was read.
+    pub code_size: u8,
+    /// The offset of the byte in the input data which holds the first bit of the code.
+    pub byte_offset: u64,
+    /// The index of the first bit of the code within that byte, counted in the bit order of the
+    /// stream from the first bit read.
+    pub bit_offset: u8,
+}
+
+impl LzwError {
+    /// Move the position of the error by the bytes preceding the stream, such as a header.
+    pub(crate) fn offset_by(mut self, bytes: u64) -> Self {
+        match &mut self {
+            LzwError::InvalidCode(err) | LzwError::CodeOutOfRange(err) => err.byte_offset += bytes,
+            LzwError::InvalidSymbol { offset, .. } => *offset += bytes,
+            _ => {}
+        }
+
+        self
+    }
 }
 
 impl core::fmt::Display for LzwError {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         match self {
-            LzwError::InvalidCode => f.write_str("invalid code in LZW stream"),
+            LzwError::InvalidCode(err) => write!(f, "invalid code in LZW stream, {}", err),
+            LzwError::CodeOutOfRange(err) => {
+                write!(f, "code exceeds the LZW dictionary, {}", err)
+            }
+            LzwError::InvalidSymbol {
+                byte,
+                symbol_size,
+                offset,
+            } => write!(
+                f,
+                "byte {} at offset {} exceeds the LZW symbol size of {} bits",
+                byte, offset, symbol_size
+            ),
+            LzwError::UnexpectedEof => f.write_str("unexpected end of LZW stream"),
+            LzwError::InvalidHeader => f.write_str("invalid header of LZW stream"),
+            LzwError::LimitExceeded => f.write_str("decoded LZW data exceeds the configured limit"),
         }
     }
 }
 
+impl core::fmt::Display for CodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(
+            f,
+            "code {} at byte {} bit {} with code size {} and next code {}",
+            self.code, self.byte_offset, self.bit_offset, self.code_size, self.next_code
+        )
+    }
+}
+
 #[cfg(feature = "std")]
 impl std::error::Error for LzwError {}
+
+#[cfg(feature = "std")]
+impl From<LzwError> for std::io::Error {
+    fn from(err: LzwError) -> Self {
+        let kind = match err {
+            LzwError::InvalidSymbol { .. } => std::io::ErrorKind::InvalidInput,
+            LzwError::UnexpectedEof => std::io::ErrorKind::UnexpectedEof,
+            _ => std::io::ErrorKind::InvalidData,
+        };
+
+        std::io::Error::new(kind, err)
+    }
+}
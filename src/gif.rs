@@ -0,0 +1,338 @@
+//! The image data of GIF, LZW codes framed in sub-blocks.
+//!
+//! The image data of a GIF frame starts with a byte holding the minimum code size, the symbol
+//! size of the LZW stream. The codes follow in sub-blocks, each a length byte and up to 255 bytes
+//! of data, and an empty sub-block terminates the data. The codes are packed with the least
+//! significant bit first.
+//!
+//! Decoding is lenient with streams found in the wild. A missing end code, a missing terminator
+//! and a last sub-block that is cut short are all accepted, and the data decoded up to there is
+//! returned.
+//!
+//! The indices of GIF are bytes, so the minimum code size is at most `8`. Some encoders write
+//! larger sizes regardless, these are decoded as written up to `11`, the largest symbol size that
+//! still fits the maximum code size of `12`. Only a larger size is reported as
+//! [`LzwError::InvalidHeader`]. The sizes `0` and `1` are below the `2` of the specification. They
+//! are decoded and encoded with that symbol size all the same, as with [`decode::Decoder`] and
+//! [`encode::Encoder`].
+//!
+//! # Examples
+//!
+//! ```
+//! use weezl::gif;
+//!
+//! let indices: Vec<u8> = (0..1000u32).map(|i| (i % 7) as u8).collect();
+//! let framed = gif::encode(3, &indices).unwrap();
+//! assert_eq!(framed[0], 3);
+//!
+//! let decoded = gif::decode(&framed).unwrap();
+//! assert_eq!(decoded, indices);
+//! ```
+//!
+//! [`LzwError::InvalidHeader`]: ../enum.LzwError.html#variant.InvalidHeader
+//! [`decode::Decoder`]: ../decode/struct.Decoder.html
+//! [`encode::Encoder`]: ../encode/struct.Encoder.html
+use crate::alloc::vec::Vec;
+use crate::decode::{self, EndMarker};
+use crate::encode;
+use crate::error::LzwError;
+use crate::BitOrder;
+
+#[cfg(feature = "std")]
+use std::io::{self, BufRead, Read, Write};
+
+/// The largest minimum code size of indices, which are bytes.
+const MAX_MIN_CODE_SIZE: u8 = 8;
+/// The largest minimum code size accepted when decoding.
+const MAX_DECODE_MIN_CODE_SIZE: u8 = 11;
+
+/// A reader of the indices decoded from GIF image data.
+///
+/// Use [`finish`] to skip the sub-blocks after the end code and reach the next block of the file.
+///
+/// [`finish`]: #method.finish
+#[cfg(feature = "std")]
+pub struct Reader<R> {
+    decoded: decode::Reader<SubBlocks<R>>,
+}
+
+/// A writer that encodes indices as GIF image data.
+///
+/// Use [`finish`] to write the end of the data.
+///
+/// [`finish`]: #method.finish
+#[cfg(feature = "std")]
+pub struct Writer<W: Write> {
+    encoded: encode::Writer<Framing<W>>,
+}
+
+/// Reads the data of consecutive sub-blocks.
+#[cfg(feature = "std")]
+struct SubBlocks<R> {
+    inner: R,
+    /// The bytes left in the current sub-block.
+    remaining: usize,
+    /// Set after the terminator or the end of the input.
+    ended: bool,
+}
+
+/// Splits written data into sub-blocks.
+#[cfg(feature = "std")]
+struct Framing<W> {
+    inner: W,
+    block: Vec<u8>,
+}
+
+/// Decode framed image data, starting with the minimum code size.
+///
+/// See [`decode_into`] for the accepted data.
+///
+/// [`decode_into`]: fn.decode_into.html
+pub fn decode(data: &[u8]) -> Result<Vec<u8>, LzwError> {
+    let mut out = Vec::new();
+    decode_into(data, &mut out)?;
+    Ok(out)
+}
+
+/// Decode framed image data, appending the indices to `out`.
+///
+/// Returns the number of bytes of the image data including its terminator, after which the next
+/// block of the file begins. All data decoded before an error is appended as well.
+///
+/// An empty input is reported as [`LzwError::UnexpectedEof`], a minimum code size above `11` as
+/// [`LzwError::InvalidHeader`].
+///
+/// [`LzwError::UnexpectedEof`]: ../enum.LzwError.html#variant.UnexpectedEof
+/// [`LzwError::InvalidHeader`]: ../enum.LzwError.html#variant.InvalidHeader
+pub fn decode_into(data: &[u8], out: &mut Vec<u8>) -> Result<usize, LzwError> {
+    let (&size, mut rest) = data.split_first().ok_or(LzwError::UnexpectedEof)?;
+    let mut decoder = decoder(size)?;
+
+    while let Some((&len, tail)) = rest.split_first() {
+        rest = tail;
+        if len == 0 {
+            break;
+        }
+
+        let (block, tail) = rest.split_at(rest.len().min(len.into()));
+        rest = tail;
+
+        // Skip the sub-blocks after the end code.
+        if !decoder.has_ended() {
+            decoder.into_vec(out).decode(block).status?;
+        }
+    }
+
+    Ok(data.len() - rest.len())
+}
+
+/// Encode indices as framed image data, starting with the minimum code size.
+///
+/// # Panics
+///
+/// This method panics if `min_code_size` is larger than `8`.
+pub fn encode(min_code_size: u8, data: &[u8]) -> Result<Vec<u8>, LzwError> {
+    let encoded = encoder(min_code_size).encode(data)?;
+
+    let mut framed = Vec::with_capacity(encoded.len() + encoded.len() / 255 + 3);
+    framed.push(min_code_size);
+    for block in encoded.chunks(255) {
+        framed.push(block.len() as u8);
+        framed.extend_from_slice(block);
+    }
+    framed.push(0);
+
+    Ok(framed)
+}
+
+fn decoder(min_code_size: u8) -> Result<decode::Decoder, LzwError> {
+    if min_code_size > MAX_DECODE_MIN_CODE_SIZE {
+        return Err(LzwError::InvalidHeader);
+    }
+
+    Ok(decode::Configuration::new(BitOrder::Lsb, min_code_size)
+        .with_end_marker(EndMarker::AcceptMissing)
+        .build())
+}
+
+fn encoder(min_code_size: u8) -> encode::Encoder {
+    assert!(
+        min_code_size <= MAX_MIN_CODE_SIZE,
+        "Minimum code size of at most 8 required, got {}",
+        min_code_size
+    );
+
+    encode::Encoder::new(BitOrder::Lsb, min_code_size)
+}
+
+#[cfg(feature = "std")]
+impl<R: BufRead> Reader<R> {
+    /// Create a reader of the image data in `reader`, reading the minimum code size right away.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut size = [0];
+        reader.read_exact(&mut size)?;
+
+        let blocks = SubBlocks {
+            inner: reader,
+            remaining: 0,
+            ended: false,
+        };
+
+        Ok(Reader {
+            decoded: decode::Reader::new(decoder(size[0])?, blocks),
+        })
+    }
+
+    /// Get a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.decoded.get_ref().inner
+    }
+
+    /// Skip the rest of the image data, then return the underlying reader.
+    ///
+    /// The reader is positioned after the terminator, at the next block of the file.
+    pub fn finish(self) -> io::Result<R> {
+        let mut blocks = self.decoded.into_inner();
+        loop {
+            let len = blocks.fill_buf()?.len();
+            if len == 0 {
+                return Ok(blocks.inner);
+            }
+            blocks.consume(len);
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: BufRead> Read for Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.decoded.read(buf)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> Writer<W> {
+    /// Create a writer that encodes into `writer`, writing the minimum code size right away.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `min_code_size` is larger than `8`.
+    pub fn new(mut writer: W, min_code_size: u8) -> io::Result<Self> {
+        let encoder = encoder(min_code_size);
+        writer.write_all(&[min_code_size])?;
+
+        let framing = Framing {
+            inner: writer,
+            block: Vec::with_capacity(255),
+        };
+
+        Ok(Writer {
+            encoded: encode::Writer::new(encoder, framing),
+        })
+    }
+
+    /// Get a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.encoded.get_ref().inner
+    }
+
+    /// Encode the end of the stream, write the last sub-blocks and the terminator, then return
+    /// the underlying writer.
+    pub fn finish(self) -> io::Result<W> {
+        let mut framing = self.encoded.finish()?;
+        framing.write_block()?;
+        framing.inner.write_all(&[0])?;
+        Ok(framing.inner)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> Write for Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.encoded.write(buf)
+    }
+
+    /// Flush the underlying writer.
+    ///
+    /// This does not write the encoded data that is still pending in the encoder, nor an
+    /// incomplete sub-block, as they can only be written with the end of the stream.
+    fn flush(&mut self) -> io::Result<()> {
+        self.encoded.flush()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: BufRead> Read for SubBlocks<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let data = self.fill_buf()?;
+        let len = data.len().min(buf.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: BufRead> BufRead for SubBlocks<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while !self.ended && self.remaining == 0 {
+            match self.inner.fill_buf()?.first() {
+                None => self.ended = true,
+                Some(0) => {
+                    self.ended = true;
+                    self.inner.consume(1);
+                }
+                Some(&len) => {
+                    self.remaining = len.into();
+                    self.inner.consume(1);
+                }
+            }
+        }
+
+        if self.ended {
+            return Ok(&[]);
+        }
+
+        let data = self.inner.fill_buf()?;
+        // A sub-block cut short by the end of the input ends the data.
+        if data.is_empty() {
+            self.ended = true;
+        }
+
+        let len = data.len().min(self.remaining);
+        Ok(&data[..len])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.remaining -= amt;
+        self.inner.consume(amt);
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> Framing<W> {
+    fn write_block(&mut self) -> io::Result<()> {
+        if !self.block.is_empty() {
+            self.inner.write_all(&[self.block.len() as u8])?;
+            self.inner.write_all(&self.block)?;
+            self.block.clear();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> Write for Framing<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(255 - self.block.len());
+        self.block.extend_from_slice(&buf[..len]);
+        if self.block.len() == 255 {
+            self.write_block()?;
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
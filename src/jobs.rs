@@ -0,0 +1,66 @@
+//! Run independent jobs on scoped threads.
+use crate::alloc::vec::Vec;
+
+use std::sync::Mutex;
+use std::{panic, thread};
+
+/// Run `work` for every job on all available threads and return the results in the order of the
+/// jobs.
+///
+/// Each thread creates its state with `init` once and passes it to all of its jobs. Jobs are
+/// handed out one at a time so that threads stay busy when the jobs differ in size. The threads
+/// only live for the duration of the call, a panic in any of them is resumed on the caller.
+pub(crate) fn run<J, S, R>(
+    jobs: Vec<J>,
+    init: impl Fn() -> S + Sync,
+    work: impl Fn(&mut S, J) -> R + Sync,
+) -> Vec<R>
+where
+    J: Send,
+    R: Send,
+{
+    let count = jobs.len();
+    let threads = thread::available_parallelism()
+        .map_or(1, usize::from)
+        .min(count);
+
+    let queue = Mutex::new(jobs.into_iter().enumerate());
+    let mut results: Vec<Option<R>> = (0..count).map(|_| None).collect();
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut state = init();
+                    let mut done = Vec::new();
+
+                    loop {
+                        // Do not hold the lock while working.
+                        let next = queue.lock().unwrap_or_else(|err| err.into_inner()).next();
+                        let Some((idx, job)) = next else {
+                            break;
+                        };
+
+                        done.push((idx, work(&mut state, job)));
+                    }
+
+                    done
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            let done = worker
+                .join()
+                .unwrap_or_else(|err| panic::resume_unwind(err));
+            for (idx, result) in done {
+                results[idx] = Some(result);
+            }
+        }
+    });
+
+    results
+        .into_iter()
+        .map(|result| result.expect("every job is run by a thread"))
+        .collect()
+}
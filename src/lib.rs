@@ -2,8 +2,8 @@
 //!
 //! This crates provides an `Encoder` and a `Decoder` in their respective modules. The code words
 //! are written from and to bit byte slices (or streams) where it is possible to write either the
-//! most or least significant bits first. The maximum possible code size is 12 bits, the smallest
-//! available code size is 2 bits.
+//! most or least significant bits first. The maximum code size is 12 bits by default and can be
+//! configured up to 16 bits, the smallest available code size is 2 bits.
 //!
 //! ## Example
 //!
@@ -64,6 +64,9 @@ use std as alloc;
 
 pub(crate) const MAX_CODESIZE: u8 = 12;
 pub(crate) const MAX_ENTRIES: usize = 1 << MAX_CODESIZE as usize;
+/// The largest code size that can be configured as the maximum.
+pub(crate) const LARGEST_CODESIZE: u8 = 16;
+pub(crate) const LARGEST_ENTRIES: usize = 1 << LARGEST_CODESIZE as usize;
 
 /// Alias for a LZW code point
 pub(crate) type Code = u16;
@@ -75,8 +78,16 @@ pub(crate) type Code = u16;
 /// break in the decoding loop. Note that the decoded size can be up to quadratic in code block.
 pub(crate) const STREAM_BUF_SIZE: usize = 1 << 24;
 
+/// The default size of the intermediate buffer of the owning writers.
+///
+/// Writes are usually much smaller than entire streams so they do not benefit from a buffer as
+/// large as `STREAM_BUF_SIZE`. It only needs to hold the output of a typical `write` call.
+#[cfg(feature = "std")]
+pub(crate) const WRITER_BUF_SIZE: usize = 1 << 16;
+
 /// The order of bits in bytes.
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BitOrder {
     /// The most significant bit is processed first.
     Msb,
@@ -84,6 +95,42 @@ pub enum BitOrder {
     Lsb,
 }
 
+/// The arrangement of special codes and the packing of codes in a stream.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Dialect {
+    /// Clear and end code follow the alphabet, codes are packed back-to-back.
+    ///
+    /// This is the variant used by GIF, TIFF and PDF.
+    Standard,
+    /// The variant of the `compress` utility.
+    ///
+    /// There is no end code and a clear code only in block mode. Codes are read and written in
+    /// groups of eight, and each change of the code size first pads to the end of the group.
+    Compress { block_mode: bool },
+}
+
+impl Dialect {
+    /// The clear code, end code and the first free code for an alphabet of the given size.
+    pub(crate) fn codes(self, min_size: u8) -> (Option<Code>, Option<Code>, Code) {
+        let alphabet: Code = 1 << min_size;
+        match self {
+            Dialect::Standard => (Some(alphabet), Some(alphabet + 1), alphabet + 2),
+            Dialect::Compress { block_mode: true } => (Some(alphabet), None, alphabet + 1),
+            Dialect::Compress { block_mode: false } => (None, None, alphabet),
+        }
+    }
+
+    /// The code size at which the code size stops increasing, for a table of the given size.
+    pub(crate) fn code_size_limit(self, max_code_size: u8) -> u8 {
+        match self {
+            Dialect::Standard => max_code_size,
+            // When the table is full `compress` increases the code size one final time, unless it
+            // already switched to the maximum size. With 9 bits it never did since it starts there.
+            Dialect::Compress { .. } => max_code_size.max(10),
+        }
+    }
+}
+
 /// An owned or borrowed buffer for stream operations.
 #[cfg(feature = "alloc")]
 pub(crate) enum StreamBuf<'d> {
@@ -109,15 +156,56 @@ fn assert_encode_size(size: u8) {
     );
 }
 
+#[cold]
+fn assert_max_code_size(size: u8, min_size: u8) {
+    assert!(
+        (9..=LARGEST_CODESIZE).contains(&size),
+        "Maximum code size in 9..=16 required, got {}",
+        size
+    );
+    // The default maximum also works with a symbol size of 12, as it always has.
+    assert!(
+        size > min_size || size == MAX_CODESIZE,
+        "Maximum code size must exceed the symbol size {}, got {}",
+        min_size,
+        size
+    );
+}
+
+#[cold]
+fn assert_code_width(width: u8) {
+    assert!(
+        (1..=LARGEST_CODESIZE).contains(&width),
+        "Code width in 1..=16 required, got {}",
+        width
+    );
+}
+
+#[cfg(feature = "alloc")]
+pub mod arc;
+#[cfg(feature = "alloc")]
+pub mod compress;
 #[cfg(feature = "alloc")]
 pub mod decode;
 #[cfg(feature = "alloc")]
 pub mod encode;
 mod error;
+#[cfg(feature = "alloc")]
+pub mod gif;
+#[cfg(feature = "parallel")]
+mod jobs;
+#[cfg(feature = "std")]
+pub mod pdf;
+#[cfg(feature = "alloc")]
+mod predictor;
+#[cfg(feature = "alloc")]
+pub mod tiff;
+#[cfg(feature = "alloc")]
+pub mod zip_shrink;
 
 #[cfg(feature = "std")]
 pub use self::error::StreamResult;
-pub use self::error::{BufferResult, LzwError, LzwStatus};
+pub use self::error::{BufferResult, CodeError, LzwError, LzwStatus};
 
 #[cfg(all(test, feature = "alloc"))]
 mod tests {
@@ -0,0 +1,391 @@
+//! The `LZWDecode` filter of PDF and PostScript, with its decode parameters.
+//!
+//! Streams of this filter are LZW coded with the most significant bit first and 8-bit symbols.
+//! The `EarlyChange` parameter selects whether the code size increases one code early, as in
+//! TIFF, which is the default. The remaining parameters describe a predictor that was applied to
+//! the rows of an image before encoding:
+//!
+//! * Predictor `1` leaves the data unchanged.
+//! * Predictor `2` is the horizontal differencing of TIFF. Samples of 16 bits are big endian.
+//! * Predictors `10` to `15` are the PNG filters. Each row starts with a byte that selects its
+//!   filter type, the predictor value only tells the encoder which type to choose.
+//!
+//! The [`Reader`] decodes and reverts the predictor row by row, the [`Writer`] applies it and
+//! encodes. Both take the parameters as they were parsed from the stream dictionary.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::io::{Read, Write};
+//! use weezl::pdf::{Parameters, Reader, Writer};
+//!
+//! let mut parameters = Parameters::default();
+//! parameters.predictor = 12;
+//! parameters.colors = 3;
+//! parameters.columns = 4;
+//!
+//! let image: Vec<u8> = (0..48u8).collect();
+//! let mut writer = Writer::new(vec![], &parameters).unwrap();
+//! writer.write_all(&image).unwrap();
+//! let encoded = writer.finish().unwrap();
+//!
+//! let mut decoded = vec![];
+//! Reader::new(&encoded[..], &parameters)
+//!     .unwrap()
+//!     .read_to_end(&mut decoded)
+//!     .unwrap();
+//! assert_eq!(decoded, image);
+//! ```
+//!
+//! [`Reader`]: struct.Reader.html
+//! [`Writer`]: struct.Writer.html
+use crate::alloc::{vec, vec::Vec};
+use crate::decode::{self, Decoder, EndMarker};
+use crate::encode::{self, Encoder};
+use crate::predictor;
+use crate::tiff::ByteOrder;
+use crate::BitOrder;
+
+use core::convert::TryFrom;
+
+use std::io::{self, BufRead, Read, Write};
+
+/// The decode parameters of an `LZWDecode` filter.
+///
+/// The default values are the defaults of the PDF specification for absent entries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Parameters {
+    /// The `EarlyChange` entry, which is `true` for a value of `1`.
+    pub early_change: bool,
+    /// The `Predictor` entry, one of `1`, `2` and `10` to `15`.
+    pub predictor: u8,
+    /// The `Colors` entry, the number of samples of each pixel.
+    pub colors: u8,
+    /// The `BitsPerComponent` entry, one of `1`, `2`, `4`, `8` and `16`.
+    pub bits_per_component: u8,
+    /// The `Columns` entry, the number of pixels in each row.
+    pub columns: u32,
+}
+
+/// A reader of the data decoded from an `LZWDecode` stream.
+///
+/// A stream that ends without its end code is accepted, as is common in practice. A row that is
+/// truncated by the end of the stream is returned as far as it was decoded.
+pub struct Reader<R> {
+    decoded: decode::Reader<R>,
+    rows: Rows,
+    /// The current row, behind the filter type byte for PNG predictors.
+    row: Vec<u8>,
+    /// The previous row for PNG predictors.
+    prev: Vec<u8>,
+    /// The range of `row` that was not yet read.
+    pos: usize,
+    end: usize,
+}
+
+/// A writer that encodes data as an `LZWDecode` stream.
+///
+/// Use [`finish`] to write the end of the stream, a final row may be incomplete.
+///
+/// [`finish`]: #method.finish
+pub struct Writer<W: Write> {
+    encoded: encode::Writer<W>,
+    rows: Rows,
+    /// The current row, which is encoded once it is complete.
+    row: Vec<u8>,
+    /// The previous row for PNG predictors.
+    prev: Vec<u8>,
+    /// The filtered row, with the filter type byte of PNG predictors.
+    out: Vec<u8>,
+}
+
+/// The layout of rows, from validated parameters.
+#[derive(Clone, Copy)]
+struct Rows {
+    predictor: Predictor,
+    /// The number of bytes in a row, without the filter type byte of PNG predictors.
+    len: usize,
+    colors: usize,
+    bits: u8,
+    /// The number of bytes of a pixel, for the PNG filters.
+    bpp: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Predictor {
+    None,
+    Tiff,
+    /// The PNG predictor, with the filter type for encoding or `None` to choose it per row.
+    Png(Option<u8>),
+}
+
+impl Default for Parameters {
+    fn default() -> Self {
+        Parameters {
+            early_change: true,
+            predictor: 1,
+            colors: 1,
+            bits_per_component: 8,
+            columns: 1,
+        }
+    }
+}
+
+impl Parameters {
+    /// Create a decoder for the LZW codes of the stream.
+    ///
+    /// It does not revert the predictor, for which a [`Reader`] is needed.
+    ///
+    /// [`Reader`]: struct.Reader.html
+    pub fn decoder(&self) -> Decoder {
+        let configuration = if self.early_change {
+            decode::Configuration::with_tiff_size_switch(BitOrder::Msb, 8)
+        } else {
+            decode::Configuration::new(BitOrder::Msb, 8)
+        };
+
+        configuration
+            .with_end_marker(EndMarker::AcceptMissing)
+            .build()
+    }
+
+    /// Create an encoder for the LZW codes of the stream.
+    ///
+    /// It does not apply the predictor, for which a [`Writer`] is needed.
+    ///
+    /// [`Writer`]: struct.Writer.html
+    pub fn encoder(&self) -> Encoder {
+        if self.early_change {
+            Encoder::with_tiff_size_switch(BitOrder::Msb, 8)
+        } else {
+            Encoder::new(BitOrder::Msb, 8)
+        }
+    }
+
+    fn rows(&self) -> io::Result<Rows> {
+        let predictor = match self.predictor {
+            1 => Predictor::None,
+            2 => Predictor::Tiff,
+            10..=14 => Predictor::Png(Some(self.predictor - 10)),
+            15 => Predictor::Png(None),
+            _ => return Err(invalid("unsupported predictor")),
+        };
+
+        if !matches!(self.bits_per_component, 1 | 2 | 4 | 8 | 16) {
+            return Err(invalid("unsupported bits per component"));
+        }
+
+        if self.colors == 0 || self.columns == 0 {
+            return Err(invalid("empty rows"));
+        }
+
+        let colors = usize::from(self.colors);
+        let pixel = colors * usize::from(self.bits_per_component);
+        let len = usize::try_from(self.columns)
+            .ok()
+            .and_then(|columns| columns.checked_mul(pixel))
+            .ok_or_else(|| invalid("rows too long"))?
+            .div_ceil(8);
+
+        Ok(Rows {
+            predictor,
+            len,
+            colors,
+            bits: self.bits_per_component,
+            bpp: pixel.div_ceil(8),
+        })
+    }
+}
+
+impl<R: BufRead> Reader<R> {
+    /// Create a reader of the data decoded from `reader`.
+    ///
+    /// Returns an error if the parameters are invalid or not supported.
+    pub fn new(reader: R, parameters: &Parameters) -> io::Result<Self> {
+        let rows = parameters.rows()?;
+        let (row, prev) = match rows.predictor {
+            Predictor::None => (vec![], vec![]),
+            Predictor::Tiff => (vec![0; rows.len], vec![]),
+            Predictor::Png(_) => (vec![0; rows.len + 1], vec![0; rows.len]),
+        };
+
+        Ok(Reader {
+            decoded: decode::Reader::new(parameters.decoder(), reader),
+            rows,
+            row,
+            prev,
+            pos: 0,
+            end: 0,
+        })
+    }
+
+    /// Get a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        self.decoded.get_ref()
+    }
+
+    /// Unwrap the underlying reader.
+    ///
+    /// Note that the decoder may have consumed some bytes beyond the end code.
+    pub fn into_inner(self) -> R {
+        self.decoded.into_inner()
+    }
+
+    /// Decode the next row and revert its predictor, returning `false` at the end of the stream.
+    fn next_row(&mut self) -> io::Result<bool> {
+        let mut filled = 0;
+        while filled < self.row.len() {
+            match self.decoded.read(&mut self.row[filled..])? {
+                0 => break,
+                count => filled += count,
+            }
+        }
+
+        match self.rows.predictor {
+            _ if filled == 0 => return Ok(false),
+            Predictor::None => unreachable!("Rows without predictor are read directly"),
+            Predictor::Tiff => {
+                let row = &mut self.row[..filled];
+                predictor::horizontal_accumulate(
+                    row,
+                    self.rows.colors,
+                    self.rows.bits,
+                    ByteOrder::BigEndian,
+                );
+                self.pos = 0;
+            }
+            Predictor::Png(_) => {
+                let (filter, row) = self.row[..filled].split_at_mut(1);
+                if !predictor::png_unfilter(filter[0], row, &self.prev, self.rows.bpp) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "invalid PNG filter type",
+                    ));
+                }
+
+                self.prev[..row.len()].copy_from_slice(row);
+                self.pos = 1;
+            }
+        }
+
+        self.end = filled;
+        Ok(true)
+    }
+}
+
+impl<R: BufRead> Read for Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.rows.predictor == Predictor::None {
+            return self.decoded.read(buf);
+        }
+
+        if self.pos == self.end && !self.next_row()? {
+            return Ok(0);
+        }
+
+        let count = buf.len().min(self.end - self.pos);
+        buf[..count].copy_from_slice(&self.row[self.pos..][..count]);
+        self.pos += count;
+        Ok(count)
+    }
+}
+
+impl<W: Write> Writer<W> {
+    /// Create a writer that encodes into `writer`.
+    ///
+    /// Returns an error if the parameters are invalid or not supported.
+    pub fn new(writer: W, parameters: &Parameters) -> io::Result<Self> {
+        let rows = parameters.rows()?;
+        let (prev, out) = match rows.predictor {
+            Predictor::Png(_) => (vec![0; rows.len], vec![0; rows.len + 1]),
+            _ => (vec![], vec![]),
+        };
+
+        Ok(Writer {
+            encoded: encode::Writer::new(parameters.encoder(), writer),
+            rows,
+            row: Vec::with_capacity(rows.len),
+            prev,
+            out,
+        })
+    }
+
+    /// Get a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        self.encoded.get_ref()
+    }
+
+    /// Encode a last incomplete row and the end of the stream, then return the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.row.is_empty() {
+            self.write_row()?;
+        }
+
+        self.encoded.finish()
+    }
+
+    /// Apply the predictor to the current row and encode it.
+    fn write_row(&mut self) -> io::Result<()> {
+        let len = self.row.len();
+
+        match self.rows.predictor {
+            Predictor::None => unreachable!("Rows without predictor are written directly"),
+            Predictor::Tiff => {
+                let row = &mut self.row;
+                predictor::horizontal_difference(
+                    row,
+                    self.rows.colors,
+                    self.rows.bits,
+                    ByteOrder::BigEndian,
+                );
+                self.encoded.write_all(row)?;
+            }
+            Predictor::Png(filter) => {
+                let (prev, row) = (&self.prev[..len], &self.row[..]);
+                let (kind, out) = self.out[..len + 1].split_at_mut(1);
+                kind[0] = match filter {
+                    Some(filter) => {
+                        predictor::png_filter(filter, row, prev, self.rows.bpp, out);
+                        filter
+                    }
+                    None => predictor::png_filter_adaptive(row, prev, self.rows.bpp, out),
+                };
+
+                self.encoded.write_all(&self.out[..len + 1])?;
+                self.prev[..len].copy_from_slice(&self.row);
+            }
+        }
+
+        self.row.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.rows.predictor == Predictor::None {
+            return self.encoded.write(buf);
+        }
+
+        let count = buf.len().min(self.rows.len - self.row.len());
+        self.row.extend_from_slice(&buf[..count]);
+
+        if self.row.len() == self.rows.len {
+            self.write_row()?;
+        }
+
+        Ok(count)
+    }
+
+    /// Flush the underlying writer.
+    ///
+    /// This does not write an incomplete row nor the data pending in the encoder, both can only be
+    /// written with the end of the stream.
+    fn flush(&mut self) -> io::Result<()> {
+        self.encoded.flush()
+    }
+}
+
+fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
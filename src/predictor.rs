@@ -0,0 +1,187 @@
+//! Prediction filters applied to rows of samples before they are encoded.
+//!
+//! Image formats that use LZW, such as TIFF and PDF, can transform each row of an image into the
+//! differences of its samples to some prediction. Such differences repeat far more often than the
+//! samples, which improves compression. All functions work on one row in place, rows that are
+//! truncated at the end of a stream are processed up to their last complete sample.
+
+// The PNG filters are only used by the `pdf` module, which requires `std`.
+#![cfg_attr(not(feature = "std"), allow(dead_code))]
+
+use crate::tiff::ByteOrder;
+
+/// The filter types of the PNG predictors, which are written as the first byte of each row.
+const PNG_NONE: u8 = 0;
+const PNG_SUB: u8 = 1;
+const PNG_UP: u8 = 2;
+const PNG_AVERAGE: u8 = 3;
+const PNG_PAETH: u8 = 4;
+
+/// Replace each sample with the difference to the sample of the same component before it.
+///
+/// This is the horizontal predictor of TIFF, predictor `2`. Samples are `bits` wide, which is one
+/// of `1`, `2`, `4`, `8`, `16` and `32`, and each pixel has `samples` of them.
+pub(crate) fn horizontal_difference(row: &mut [u8], samples: usize, bits: u8, order: ByteOrder) {
+    horizontal(row, samples, bits, order, false);
+}
+
+/// Revert [`horizontal_difference`] by adding up the differences.
+pub(crate) fn horizontal_accumulate(row: &mut [u8], samples: usize, bits: u8, order: ByteOrder) {
+    horizontal(row, samples, bits, order, true);
+}
+
+type ReadSample = fn(&[u8]) -> u32;
+type WriteSample = fn(&mut [u8], u32);
+
+fn horizontal(row: &mut [u8], samples: usize, bits: u8, order: ByteOrder, accumulate: bool) {
+    use ByteOrder::{BigEndian, LittleEndian};
+
+    let (read, write): (ReadSample, WriteSample) = match (bits, order) {
+        (8, _) => (|b| b[0].into(), |b, v| b[0] = v as u8),
+        (16, BigEndian) => (
+            |b| u16::from_be_bytes([b[0], b[1]]).into(),
+            |b, v| b.copy_from_slice(&(v as u16).to_be_bytes()),
+        ),
+        (16, LittleEndian) => (
+            |b| u16::from_le_bytes([b[0], b[1]]).into(),
+            |b, v| b.copy_from_slice(&(v as u16).to_le_bytes()),
+        ),
+        (32, BigEndian) => (
+            |b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]),
+            |b, v| b.copy_from_slice(&v.to_be_bytes()),
+        ),
+        (32, LittleEndian) => (
+            |b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            |b, v| b.copy_from_slice(&v.to_le_bytes()),
+        ),
+        _ => return horizontal_packed(row, samples, bits, accumulate),
+    };
+
+    let width = usize::from(bits / 8);
+    let count = row.len() / width;
+    let sample = |row: &[u8], idx: usize| read(&row[idx * width..][..width]);
+
+    // Accumulate from the start so that the left sample is already restored, and take
+    // differences from the end so that it is still the original.
+    if accumulate {
+        for idx in samples..count {
+            let value = sample(row, idx).wrapping_add(sample(row, idx - samples));
+            write(&mut row[idx * width..][..width], value);
+        }
+    } else {
+        for idx in (samples..count).rev() {
+            let value = sample(row, idx).wrapping_sub(sample(row, idx - samples));
+            write(&mut row[idx * width..][..width], value);
+        }
+    }
+}
+
+/// The horizontal predictor for samples of less than a byte, packed from the high bits.
+fn horizontal_packed(row: &mut [u8], samples: usize, bits: u8, accumulate: bool) {
+    debug_assert!(matches!(bits, 1 | 2 | 4));
+
+    let bits = usize::from(bits);
+    let mask = (1u32 << bits) - 1;
+    let count = row.len() * 8 / bits;
+
+    let shift = |idx: usize| 8 - bits - idx * bits % 8;
+    let get = |row: &[u8], idx: usize| (u32::from(row[idx * bits / 8]) >> shift(idx)) & mask;
+    let set = |row: &mut [u8], idx: usize, value: u32| {
+        let byte = &mut row[idx * bits / 8];
+        *byte &= !((mask as u8) << shift(idx));
+        *byte |= ((value & mask) as u8) << shift(idx);
+    };
+
+    if accumulate {
+        for idx in samples..count {
+            let value = get(row, idx).wrapping_add(get(row, idx - samples));
+            set(row, idx, value);
+        }
+    } else {
+        for idx in (samples..count).rev() {
+            let value = get(row, idx).wrapping_sub(get(row, idx - samples));
+            set(row, idx, value);
+        }
+    }
+}
+
+/// Filter a row with one of the PNG filter types into `out`.
+///
+/// The previous row is all zeros for the first row. Pixels are `bpp` bytes wide, rounded up to one
+/// byte for samples of less than a byte.
+pub(crate) fn png_filter(filter: u8, row: &[u8], prev: &[u8], bpp: usize, out: &mut [u8]) {
+    for idx in 0..row.len() {
+        let left = if idx >= bpp { row[idx - bpp] } else { 0 };
+        let up = prev[idx];
+        let up_left = if idx >= bpp { prev[idx - bpp] } else { 0 };
+
+        out[idx] = row[idx].wrapping_sub(match filter {
+            PNG_SUB => left,
+            PNG_UP => up,
+            PNG_AVERAGE => ((u16::from(left) + u16::from(up)) / 2) as u8,
+            PNG_PAETH => paeth(left, up, up_left),
+            _ => 0,
+        });
+    }
+}
+
+/// Revert [`png_filter`] in place, returning `false` if the filter type is unknown.
+pub(crate) fn png_unfilter(filter: u8, row: &mut [u8], prev: &[u8], bpp: usize) -> bool {
+    if filter > PNG_PAETH {
+        return false;
+    }
+
+    for idx in 0..row.len() {
+        let left = if idx >= bpp { row[idx - bpp] } else { 0 };
+        let up = prev[idx];
+        let up_left = if idx >= bpp { prev[idx - bpp] } else { 0 };
+
+        row[idx] = row[idx].wrapping_add(match filter {
+            PNG_SUB => left,
+            PNG_UP => up,
+            PNG_AVERAGE => ((u16::from(left) + u16::from(up)) / 2) as u8,
+            PNG_PAETH => paeth(left, up, up_left),
+            _ => 0,
+        });
+    }
+
+    true
+}
+
+/// Choose the filter type for a row by the smallest sum of the filtered bytes as signed values.
+///
+/// This is the heuristic recommended by the PNG specification, the filtered row is left in `out`.
+pub(crate) fn png_filter_adaptive(row: &[u8], prev: &[u8], bpp: usize, out: &mut [u8]) -> u8 {
+    let cost = |out: &[u8]| -> u64 {
+        out.iter()
+            .map(|&byte| u64::from((byte as i8).unsigned_abs()))
+            .sum()
+    };
+
+    let mut best = (u64::MAX, PNG_NONE);
+    for filter in PNG_NONE..=PNG_PAETH {
+        png_filter(filter, row, prev, bpp, out);
+        let sum = cost(out);
+        if sum < best.0 {
+            best = (sum, filter);
+        }
+    }
+
+    png_filter(best.1, row, prev, bpp, out);
+    best.1
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = i16::from(left) + i16::from(up) - i16::from(up_left);
+    let to_left = (estimate - i16::from(left)).abs();
+    let to_up = (estimate - i16::from(up)).abs();
+    let to_up_left = (estimate - i16::from(up_left)).abs();
+
+    if to_left <= to_up && to_left <= to_up_left {
+        left
+    } else if to_up <= to_up_left {
+        up
+    } else {
+        up_left
+    }
+}
@@ -0,0 +1,227 @@
+//! The LZW compression of TIFF strips, fused with the horizontal predictor.
+//!
+//! Most TIFF images that are LZW compressed also use the horizontal predictor, `Predictor=2`,
+//! which replaces each sample with its difference to the sample of the same component in the
+//! pixel before it. Decoding a strip and reverting the predictor in a separate pass reads all of
+//! the strip from memory twice. The coders here instead work one row at a time and revert or
+//! apply the predictor while the row is still in cache.
+//!
+//! The codes are written with the most significant bit first and with the early change of the
+//! code size of TIFF, as with [`Configuration::with_tiff_size_switch`].
+//!
+//! # Examples
+//!
+//! ```
+//! use weezl::tiff::{ByteOrder, Decoder, Encoder, Layout};
+//!
+//! // RGB pixels with 16 bits per sample, 100 pixels per row.
+//! let layout = Layout::new(100, 3, 16, ByteOrder::LittleEndian);
+//! let strip: Vec<u8> = (0..8 * layout.row_len()).map(|i| (i / 6) as u8).collect();
+//!
+//! let encoded = Encoder::new(layout).encode_strip(&strip).unwrap();
+//! let mut decoded = vec![0; strip.len()];
+//! Decoder::new(layout).decode_strip(&encoded, &mut decoded).unwrap();
+//! assert_eq!(decoded, strip);
+//! ```
+//!
+//! [`Configuration::with_tiff_size_switch`]: ../decode/struct.Configuration.html#method.with_tiff_size_switch
+use crate::alloc::vec::Vec;
+use crate::error::LzwError;
+use crate::{decode, encode, predictor, BitOrder, LzwStatus};
+
+use core::convert::TryFrom;
+
+/// The byte order of samples wider than a byte, as given by the TIFF header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ByteOrder {
+    /// Least significant byte first, a header starting with `II`.
+    LittleEndian,
+    /// Most significant byte first, a header starting with `MM`.
+    BigEndian,
+}
+
+/// The layout of the rows of a strip.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layout {
+    width: u32,
+    samples_per_pixel: u16,
+    bits_per_sample: u8,
+    byte_order: ByteOrder,
+}
+
+/// A decoder of strips that reverts the horizontal predictor.
+pub struct Decoder {
+    decoder: decode::Decoder,
+    layout: Layout,
+}
+
+/// An encoder of strips that applies the horizontal predictor.
+pub struct Encoder {
+    encoder: encode::Encoder,
+    layout: Layout,
+    /// The current row with the predictor applied.
+    row: Vec<u8>,
+}
+
+impl Layout {
+    /// Describe rows of `width` pixels, each of `samples_per_pixel` samples.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `bits_per_sample` is not one of `8`, `16` and `32`, or if
+    /// `samples_per_pixel` is `0`.
+    pub fn new(
+        width: u32,
+        samples_per_pixel: u16,
+        bits_per_sample: u8,
+        byte_order: ByteOrder,
+    ) -> Self {
+        assert!(
+            matches!(bits_per_sample, 8 | 16 | 32),
+            "Bits per sample of 8, 16 or 32 required, got {}",
+            bits_per_sample
+        );
+        assert_ne!(
+            samples_per_pixel, 0,
+            "Attempted to use pixels without samples"
+        );
+
+        Layout {
+            width,
+            samples_per_pixel,
+            bits_per_sample,
+            byte_order,
+        }
+    }
+
+    /// The number of bytes in a row.
+    pub fn row_len(&self) -> usize {
+        let bytes = u64::from(self.width)
+            * u64::from(self.samples_per_pixel)
+            * u64::from(self.bits_per_sample / 8);
+        usize::try_from(bytes).unwrap_or(usize::MAX)
+    }
+
+    /// Revert the predictor of a row, or the start of one.
+    fn accumulate(&self, row: &mut [u8]) {
+        predictor::horizontal_accumulate(
+            row,
+            self.samples_per_pixel.into(),
+            self.bits_per_sample,
+            self.byte_order,
+        );
+    }
+
+    /// Apply the predictor to a row.
+    fn difference(&self, row: &mut [u8]) {
+        predictor::horizontal_difference(
+            row,
+            self.samples_per_pixel.into(),
+            self.bits_per_sample,
+            self.byte_order,
+        );
+    }
+}
+
+impl Decoder {
+    /// Create a decoder for strips with the given layout.
+    pub fn new(layout: Layout) -> Self {
+        Decoder {
+            decoder: decode::Configuration::with_tiff_size_switch(BitOrder::Msb, 8).build(),
+            layout,
+        }
+    }
+
+    /// The layout of the decoded rows.
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Decode a strip whose decoded size is known, and revert the predictor.
+    ///
+    /// The strip is decoded into `out` as with [`decode::Decoder::decode_exact`], with the same
+    /// return value and errors, and each row is reverted as soon as it has been decoded. The last
+    /// row may be shorter than the others. Each call decodes a separate strip, so a decoder can be
+    /// reused for all strips of an image.
+    ///
+    /// [`decode::Decoder::decode_exact`]: ../decode/struct.Decoder.html#method.decode_exact
+    pub fn decode_strip(&mut self, data: &[u8], out: &mut [u8]) -> Result<usize, LzwError> {
+        let layout = self.layout;
+        self.decoder.reset();
+        self.decoder
+            .decode_exact_rows(data, out, layout.row_len().max(1), |row| {
+                layout.accumulate(row)
+            })
+    }
+}
+
+impl Encoder {
+    /// Create an encoder for strips with the given layout.
+    pub fn new(layout: Layout) -> Self {
+        Encoder {
+            encoder: encode::Encoder::with_tiff_size_switch(BitOrder::Msb, 8),
+            layout,
+            row: Vec::new(),
+        }
+    }
+
+    /// The layout of the rows to encode.
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Apply the predictor to a strip and encode it, with an end code.
+    ///
+    /// The strip need not end on a complete row. Each call encodes a separate strip, so an encoder
+    /// can be reused for all strips of an image.
+    pub fn encode_strip(&mut self, strip: &[u8]) -> Result<Vec<u8>, LzwError> {
+        let mut out = Vec::with_capacity(strip.len() / 2);
+        self.encoder.reset();
+
+        let row_len = self.layout.row_len().max(1);
+        let mut rows = strip.chunks(row_len).peekable();
+
+        if rows.peek().is_none() {
+            self.encoder.finish();
+            return encode_row(&mut self.encoder, &[], &mut out, true).map(|()| out);
+        }
+
+        while let Some(row) = rows.next() {
+            self.row.clear();
+            self.row.extend_from_slice(row);
+            self.layout.difference(&mut self.row);
+
+            let last = rows.peek().is_none();
+            if last {
+                self.encoder.finish();
+            }
+
+            encode_row(&mut self.encoder, &self.row, &mut out, last)?;
+        }
+
+        Ok(out)
+    }
+}
+
+/// Encode all of a row, and until the end code if it is the last.
+fn encode_row(
+    encoder: &mut encode::Encoder,
+    mut row: &[u8],
+    out: &mut Vec<u8>,
+    last: bool,
+) -> Result<(), LzwError> {
+    loop {
+        // Each byte of input produces at most a code of 16 bits.
+        let len = out.len();
+        out.resize(len + (2 * row.len() + 16).min(1 << 12), 0);
+        let result = encoder.encode_bytes(row, &mut out[len..]);
+        out.truncate(len + result.consumed_out);
+        row = &row[result.consumed_in..];
+
+        match result.status? {
+            LzwStatus::Done => return Ok(()),
+            _ if row.is_empty() && !last => return Ok(()),
+            _ => {}
+        }
+    }
+}
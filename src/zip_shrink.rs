@@ -0,0 +1,364 @@
+//! The `Shrink` compression method of ZIP archives, method `1`.
+//!
+//! Shrink is an LZW variant used by PKZIP 1.x. Codes are packed with the least significant bit
+//! first and start at 9 bits for an alphabet of bytes. There is no end code, the data simply ends.
+//! Instead the code `256` is followed by a sub-code: `1` increases the code size by one bit, up to
+//! 13 bits, and `2` performs a partial clear.
+//!
+//! A partial clear does not reset the dictionary but frees all of its leaves, the codes which are
+//! not the prefix of another code. New codes are then assigned from the freed codes in ascending
+//! order, so the dictionary stays full of the strings that were worth extending. This is also
+//! why the stream can not be decoded with a [`Decoder`] from the `decode` module, which assigns
+//! codes strictly in sequence.
+//!
+//! The codes are read and written with [`CodeReader`] and [`CodeWriter`]. A code that is not
+//! assigned at the point it is read, or a sub-code other than the two above, is reported as
+//! [`LzwError::InvalidCode`].
+//!
+//! # Examples
+//!
+//! ```
+//! use weezl::zip_shrink;
+//!
+//! let data = b"TOBEORNOTTOBEORTOBEORNOT";
+//! let shrunk = zip_shrink::encode(data);
+//!
+//! let mut unshrunk = [0; 24];
+//! zip_shrink::decode_exact(&shrunk, &mut unshrunk).unwrap();
+//! assert_eq!(&unshrunk, data);
+//! ```
+//!
+//! [`Decoder`]: ../decode/struct.Decoder.html
+//! [`CodeReader`]: ../decode/struct.CodeReader.html
+//! [`CodeWriter`]: ../encode/struct.CodeWriter.html
+//! [`LzwError::InvalidCode`]: ../enum.LzwError.html#variant.InvalidCode
+use crate::alloc::{vec, vec::Vec};
+use crate::decode::CodeReader;
+use crate::encode::CodeWriter;
+use crate::error::{CodeError, LzwError};
+use crate::{BitOrder, Code};
+
+/// The code introducing a sub-code.
+const CONTROL_CODE: Code = 256;
+/// The sub-code which increases the code size.
+const INCREASE_CODE_SIZE: Code = 1;
+/// The sub-code which frees all leaves of the dictionary.
+const PARTIAL_CLEAR: Code = 2;
+/// The first code which is not a byte or the control code.
+const FIRST_CODE: Code = 257;
+/// The code size at the start of the stream.
+const MIN_CODE_SIZE: u8 = 9;
+/// The code size up to which the sub-code `1` increases it.
+const MAX_CODE_SIZE: u8 = 13;
+/// The number of codes at the maximum code size.
+const TABLE_SIZE: usize = 1 << MAX_CODE_SIZE;
+/// Marks a code without a string, or a missing link of the encoder.
+const FREE: Code = Code::MAX;
+
+/// The dictionary structure common to both directions.
+///
+/// Only the prefix of each code is kept, which is what a partial clear looks at. The decoder and
+/// the encoder keep the rest of each entry in their own form.
+struct Table {
+    /// The prefix of each code, or `FREE`. Bytes are their own prefix.
+    prefix: Vec<Code>,
+    /// The free codes, the next one to be assigned last.
+    free: Vec<Code>,
+}
+
+/// The entries of the decoder.
+///
+/// Strings are not reconstructed from their prefixes but copied from the output, where each one
+/// was first written. A partial clear may free the prefix of the entry that is assigned next, and
+/// the string of that prefix only remains in the output.
+struct Decoder {
+    table: Table,
+    /// The position of the string of each entry in the output.
+    pos: Vec<usize>,
+    /// The length of the string of each code.
+    len: Vec<usize>,
+}
+
+/// The entries of the encoder, linked into a tree for searching the longest match.
+struct Encoder {
+    table: Table,
+    /// The last byte of the string of each code.
+    byte: Vec<u8>,
+    /// The first of the codes extending each code by one byte.
+    first_child: Vec<Code>,
+    /// The next code extending the same prefix.
+    next_sibling: Vec<Code>,
+    /// If the code is part of the tree, that is its prefix was assigned when it was added.
+    linked: Vec<bool>,
+}
+
+/// Decode all codes of a shrunk stream.
+///
+/// As the stream has no end code, this decodes until less than a full code remains.
+pub fn decode(data: &[u8]) -> Result<Vec<u8>, LzwError> {
+    let mut out = Vec::new();
+    Decoder::new().decode(data, &mut out, usize::MAX)?;
+    Ok(out)
+}
+
+/// Decode a shrunk stream whose decoded size is known, such as from a ZIP header.
+///
+/// Decoding stops as soon as `out` is full, any further codes are ignored. An error is returned
+/// if the stream ends before that.
+pub fn decode_exact(data: &[u8], out: &mut [u8]) -> Result<(), LzwError> {
+    let mut decoded = Vec::with_capacity(out.len());
+    Decoder::new().decode(data, &mut decoded, out.len())?;
+
+    if decoded.len() < out.len() {
+        return Err(LzwError::UnexpectedEof);
+    }
+
+    out.copy_from_slice(&decoded);
+    Ok(())
+}
+
+/// Shrink all of `data`.
+///
+/// The code size is increased just before the first code that needs it, and the dictionary is
+/// partially cleared whenever a new entry does not fit.
+pub fn encode(data: &[u8]) -> Vec<u8> {
+    Encoder::new().encode(data)
+}
+
+impl Table {
+    fn new() -> Self {
+        let mut prefix = vec![FREE; TABLE_SIZE];
+        for (code, prefix) in prefix.iter_mut().enumerate().take(CONTROL_CODE.into()) {
+            *prefix = code as Code;
+        }
+
+        Table {
+            prefix,
+            free: (FIRST_CODE..TABLE_SIZE as Code).rev().collect(),
+        }
+    }
+
+    /// If the code currently has a string.
+    fn is_assigned(&self, code: Code) -> bool {
+        self.prefix[usize::from(code)] != FREE
+    }
+
+    /// The code that the next entry is assigned.
+    fn next_code(&self) -> Option<Code> {
+        self.free.last().copied()
+    }
+
+    /// Assign the next free code to a string extending `prefix`.
+    fn assign(&mut self, prefix: Code) -> Option<Code> {
+        let code = self.free.pop()?;
+        self.prefix[usize::from(code)] = prefix;
+        Some(code)
+    }
+
+    /// Free every code that is not the prefix of another.
+    fn partial_clear(&mut self) {
+        let mut is_prefix = vec![false; TABLE_SIZE];
+        for &prefix in &self.prefix[usize::from(FIRST_CODE)..] {
+            if prefix != FREE {
+                is_prefix[usize::from(prefix)] = true;
+            }
+        }
+
+        // A code freed by an earlier clear stays free, even if a later entry extends its string.
+        self.free.clear();
+        for code in (FIRST_CODE..TABLE_SIZE as Code).rev() {
+            let idx = usize::from(code);
+            if !is_prefix[idx] || self.prefix[idx] == FREE {
+                self.prefix[idx] = FREE;
+                self.free.push(code);
+            }
+        }
+    }
+}
+
+impl Decoder {
+    fn new() -> Self {
+        let mut len = vec![0; TABLE_SIZE];
+        len[..usize::from(CONTROL_CODE)].fill(1);
+
+        Decoder {
+            table: Table::new(),
+            pos: vec![0; TABLE_SIZE],
+            len,
+        }
+    }
+
+    /// Decode into `out` until the input ends or `limit` bytes are decoded.
+    fn decode(&mut self, data: &[u8], out: &mut Vec<u8>, limit: usize) -> Result<(), LzwError> {
+        let mut reader = CodeReader::new(BitOrder::Lsb, data);
+        let mut code_size = MIN_CODE_SIZE;
+        // The previous code and the position its string was written to.
+        let mut prev: Option<(Code, usize)> = None;
+
+        while out.len() < limit {
+            let start = reader.bit_position();
+            let Some(code) = reader.read_code(code_size) else {
+                break;
+            };
+
+            if code == CONTROL_CODE {
+                let start = reader.bit_position();
+                match reader.read_code(code_size) {
+                    Some(INCREASE_CODE_SIZE) if code_size < MAX_CODE_SIZE => code_size += 1,
+                    Some(PARTIAL_CLEAR) => self.table.partial_clear(),
+                    Some(sub_code) => return Err(self.invalid_code(sub_code, code_size, start)),
+                    None => return Err(LzwError::UnexpectedEof),
+                }
+                continue;
+            }
+
+            let pos = out.len();
+            let Some((prev_code, prev_pos)) = prev else {
+                // Only a byte has a string before any entry is added.
+                if code >= CONTROL_CODE {
+                    return Err(self.invalid_code(code, code_size, start));
+                }
+
+                out.push(code as u8);
+                prev = Some((code, pos));
+                continue;
+            };
+
+            let prev_len = self.len[usize::from(prev_code)];
+            if Some(code) == self.table.next_code() {
+                // The code is the entry derived from the previous code and its own first byte.
+                out.extend_from_within(prev_pos..prev_pos + prev_len);
+                out.push(out[prev_pos]);
+            } else if code < CONTROL_CODE {
+                out.push(code as u8);
+            } else if self.table.is_assigned(code) {
+                let (start, len) = (self.pos[usize::from(code)], self.len[usize::from(code)]);
+                out.extend_from_within(start..start + len);
+            } else {
+                return Err(self.invalid_code(code, code_size, start));
+            }
+
+            // The previous string is directly followed by the first byte of this one.
+            if let Some(new_code) = self.table.assign(prev_code) {
+                self.pos[usize::from(new_code)] = prev_pos;
+                self.len[usize::from(new_code)] = prev_len + 1;
+            }
+
+            prev = Some((code, pos));
+        }
+
+        out.truncate(limit);
+        Ok(())
+    }
+
+    fn invalid_code(&self, code: Code, code_size: u8, start: u64) -> LzwError {
+        LzwError::InvalidCode(CodeError {
+            code,
+            next_code: self.table.next_code().map_or(TABLE_SIZE as u32, u32::from),
+            code_size,
+            byte_offset: start / 8,
+            bit_offset: (start % 8) as u8,
+        })
+    }
+}
+
+impl Encoder {
+    fn new() -> Self {
+        Encoder {
+            table: Table::new(),
+            byte: vec![0; TABLE_SIZE],
+            first_child: vec![FREE; TABLE_SIZE],
+            next_sibling: vec![FREE; TABLE_SIZE],
+            linked: vec![false; TABLE_SIZE],
+        }
+    }
+
+    fn encode(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut writer = CodeWriter::new(BitOrder::Lsb);
+        let mut code_size = MIN_CODE_SIZE;
+
+        let Some((&first, data)) = data.split_first() else {
+            return writer.finish();
+        };
+
+        let mut current = Code::from(first);
+        for &byte in data {
+            if let Some(code) = self.find(current, byte) {
+                current = code;
+                continue;
+            }
+
+            write_code(&mut writer, &mut code_size, current);
+
+            // The decoder adds the new entry only with the next code, after any control codes.
+            if self.table.next_code().is_none() {
+                writer.write_code(CONTROL_CODE, code_size);
+                writer.write_code(PARTIAL_CLEAR, code_size);
+                self.partial_clear();
+            }
+
+            self.add(current, byte);
+            current = byte.into();
+        }
+
+        write_code(&mut writer, &mut code_size, current);
+        writer.finish()
+    }
+
+    /// Find the code extending `prefix` by `byte`.
+    fn find(&self, prefix: Code, byte: u8) -> Option<Code> {
+        let mut code = self.first_child[usize::from(prefix)];
+        while code != FREE {
+            if self.byte[usize::from(code)] == byte {
+                return Some(code);
+            }
+            code = self.next_sibling[usize::from(code)];
+        }
+        None
+    }
+
+    fn add(&mut self, prefix: Code, byte: u8) {
+        // A partial clear may have just freed the prefix. The decoder still assigns the entry but
+        // its string can not be found from the prefix any more.
+        let linked = self.table.is_assigned(prefix);
+        let Some(code) = self.table.assign(prefix) else {
+            return;
+        };
+
+        let idx = usize::from(code);
+        self.byte[idx] = byte;
+        self.first_child[idx] = FREE;
+        self.linked[idx] = linked;
+
+        if linked {
+            self.next_sibling[idx] = self.first_child[usize::from(prefix)];
+            self.first_child[usize::from(prefix)] = code;
+        }
+    }
+
+    fn partial_clear(&mut self) {
+        self.table.partial_clear();
+
+        // Rebuild the tree from the remaining entries.
+        self.first_child.fill(FREE);
+        for code in FIRST_CODE..TABLE_SIZE as Code {
+            let idx = usize::from(code);
+            let prefix = self.table.prefix[idx];
+            if prefix != FREE && self.linked[idx] {
+                self.next_sibling[idx] = self.first_child[usize::from(prefix)];
+                self.first_child[usize::from(prefix)] = code;
+            }
+        }
+    }
+}
+
+/// Write a code, increasing the code size first if it does not fit.
+fn write_code(writer: &mut CodeWriter, code_size: &mut u8, code: Code) {
+    while code >> *code_size != 0 {
+        writer.write_code(CONTROL_CODE, *code_size);
+        writer.write_code(INCREASE_CODE_SIZE, *code_size);
+        *code_size += 1;
+    }
+
+    writer.write_code(code, *code_size);
+}
@@ -0,0 +1,109 @@
+use std::convert::TryInto;
+use weezl::{arc, compress, LzwError};
+
+mod common;
+use common::test_data;
+
+/// A crunched member with the codes of `compress`.
+fn crunch(max_code_size: u8, packed: &[u8]) -> Vec<u8> {
+    let compressed = compress::Configuration::new()
+        .with_max_code_size(max_code_size)
+        .build_encoder()
+        .encode(packed)
+        .unwrap();
+
+    let mut crunched = vec![max_code_size];
+    crunched.extend_from_slice(&compressed[3..]);
+    crunched
+}
+
+#[test]
+fn decoder_keeps_runs_packed() {
+    let mut packed = test_data(1 << 16);
+    // Escaped bytes and a run, in case the test data has none.
+    packed.extend_from_slice(b"\x90\x00x\x90\x20");
+
+    for max_code_size in 9..=12 {
+        let crunched = crunch(max_code_size, &packed);
+        let decoded = arc::build_decoder().decode(&crunched).unwrap();
+        assert!(decoded == packed, "{}", max_code_size);
+
+        let unpacked = arc::decode(&crunched).unwrap();
+        assert!(unpacked == arc::unpack(&packed), "{}", max_code_size);
+        assert!(unpacked.ends_with(&[b'x'; 32]), "{}", max_code_size);
+    }
+}
+
+#[test]
+fn crunched_member() {
+    // A complete member of an archive, its header followed by the crunched data.
+    #[rustfmt::skip]
+    let member = [
+        // The marker, method 8 and the name `TOBE.TXT`.
+        0x1a, 0x08, 0x54, 0x4f, 0x42, 0x45, 0x2e, 0x54, 0x58, 0x54, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // The compressed size, date, time, CRC-16 and original size.
+        0x1e, 0x00, 0x00, 0x00, 0x21, 0x5a, 0x00, 0x60, 0xd6, 0x26, 0x25, 0x00, 0x00, 0x00,
+        // The data, with runs packed as `!\x90\x08` and `\x90\x00`.
+        0x0c, 0x54, 0x9e, 0x08, 0x29, 0xf2, 0x44, 0x8a, 0x93, 0x27, 0x54, 0x02, 0x0e, 0x2c, 0xa8,
+        0x90, 0xa0, 0x41, 0x84, 0x21, 0x20, 0x21, 0x80, 0x04, 0x00, 0x44, 0x10, 0x29, 0x43, 0x00,
+    ];
+
+    let (header, data) = member.split_at(29);
+    let field = |at: usize| u32::from_le_bytes(header[at..at + 4].try_into().unwrap());
+    assert_eq!(header[1], 8);
+    assert_eq!(field(15), data.len() as u32);
+
+    let decoded = arc::decode(data).unwrap();
+    assert_eq!(decoded, b"TOBEORNOTTOBEORTOBEORNOT!!!!!!!!\x90 ARC");
+    assert_eq!(field(25), decoded.len() as u32);
+    assert_eq!(crc16(&decoded).to_le_bytes(), header[23..25]);
+}
+
+#[test]
+fn unpack_runs() {
+    let cases: &[(&[u8], &[u8])] = &[
+        (b"ab\x90\x04c", b"abbbbc"),
+        (b"a\x90\x01", b"a"),
+        // An escaped `0x90` is not repeated by a run after it.
+        (b"a\x90\x00\x90\x03", b"a\x90aa"),
+        (b"\x90\x03a", b"\x00\x00a"),
+        (b"a\x90", b"a"),
+    ];
+
+    for &(packed, unpacked) in cases {
+        assert_eq!(arc::unpack(packed), unpacked, "{:?}", packed);
+        assert_eq!(arc::decode(&crunch(12, packed)).unwrap(), unpacked);
+    }
+}
+
+#[test]
+fn invalid_header() {
+    for size in [0, 8, 13, 16] {
+        let decoded = arc::decode(&[size, 0x41, 0x00]);
+        assert_eq!(decoded, Err(LzwError::InvalidHeader), "{}", size);
+    }
+
+    assert_eq!(arc::decode(&[]), Err(LzwError::UnexpectedEof));
+
+    // Codes that only fit into the table of a larger maximum code size.
+    let data = test_data(1 << 16);
+    let mut crunched = crunch(12, &data);
+    crunched[0] = 11;
+    assert!(arc::decode(&crunched).is_err());
+}
+
+/// The CRC-16 of ARC headers, with the polynomial `0xa001` in reflected form.
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for &byte in data {
+        crc ^= u16::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                crc >> 1 ^ 0xa001
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
@@ -0,0 +1,79 @@
+use weezl::decode::{batch, Configuration, Decoder};
+use weezl::{encode, BitOrder, LzwError};
+
+mod common;
+use common::test_data;
+
+/// Strips of the test data in varying sizes.
+fn strips() -> Vec<Vec<u8>> {
+    let data = test_data(1 << 15);
+    let mut strips = vec![];
+    let mut rest = &data[..];
+    let mut len = 1;
+    while !rest.is_empty() {
+        let (strip, tail) = rest.split_at(len.min(rest.len()));
+        strips.push(strip.to_vec());
+        rest = tail;
+        len = len * 3 % 2000 + 1;
+    }
+    strips
+}
+
+#[test]
+fn decode_strips() {
+    for &order in &[BitOrder::Lsb, BitOrder::Msb] {
+        let strips = strips();
+        let encoded: Vec<_> = strips
+            .iter()
+            .map(|strip| {
+                encode::Encoder::with_tiff_size_switch(order, 8)
+                    .encode(strip)
+                    .unwrap()
+            })
+            .collect();
+
+        let mut decoded: Vec<_> = strips.iter().map(|strip| vec![0; strip.len()]).collect();
+        let jobs = encoded
+            .iter()
+            .map(|data| &data[..])
+            .zip(decoded.iter_mut().map(|out| &mut out[..]));
+        let results = batch::decode_exact(&Configuration::with_tiff_size_switch(order, 8), jobs);
+
+        for (idx, result) in results.iter().enumerate() {
+            assert_eq!(*result, Ok(encoded[idx].len()), "{:?} {}", order, idx);
+        }
+        assert!(decoded == strips, "{:?}", order);
+    }
+}
+
+#[test]
+fn errors_per_job() {
+    let data = b"TOBEORNOTTOBEORTOBEORNOT";
+    let encoded = encode::Encoder::new(BitOrder::Lsb, 8).encode(data).unwrap();
+
+    let mut ok = [0; 24];
+    let mut too_long = [0; 30];
+    let mut invalid = [0; 24];
+    let garbage = [0xff; 16];
+    let jobs = vec![
+        (&encoded[..], &mut ok[..]),
+        (&encoded[..], &mut too_long[..]),
+        (&garbage[..], &mut invalid[..]),
+    ];
+
+    let results = batch::decode_exact(&Configuration::new(BitOrder::Lsb, 8), jobs);
+    assert_eq!(results[0], Ok(encoded.len()));
+    assert_eq!(results[1], Err(LzwError::UnexpectedEof));
+    assert!(results[2].is_err());
+    assert_eq!(&ok, data);
+
+    // The same errors as a single decoder.
+    let single = Decoder::new(BitOrder::Lsb, 8).decode_exact(&garbage, &mut [0; 24]);
+    assert_eq!(results[2], single);
+}
+
+#[test]
+fn no_jobs() {
+    let results = batch::decode_exact(&Configuration::new(BitOrder::Msb, 8), vec![]);
+    assert!(results.is_empty());
+}
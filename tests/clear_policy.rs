@@ -0,0 +1,175 @@
+use std::fmt::Debug;
+use weezl::decode::TokenKind;
+use weezl::encode::{ClearPolicy, Configuration as EncodeConfig};
+use weezl::{decode, BitOrder};
+
+mod common;
+use common::{decoder, encoder, test_data, Flavor};
+
+const POLICIES: &[ClearPolicy] = &[
+    ClearPolicy::WhenFull,
+    ClearPolicy::Freeze,
+    ClearPolicy::Adaptive,
+    ClearPolicy::Interval(1),
+    ClearPolicy::Interval(100),
+    ClearPolicy::Interval(253),
+    ClearPolicy::Interval(254),
+    ClearPolicy::Interval(255),
+    ClearPolicy::Interval(256),
+    ClearPolicy::Interval(510),
+    ClearPolicy::Interval(3838),
+    ClearPolicy::Interval(1 << 20),
+];
+
+#[test]
+fn codes_between_clears() {
+    let data = test_data(1 << 15);
+
+    for &policy in POLICIES {
+        for &flavor in &[Flavor::Gif, Flavor::Tiff] {
+            for &order in &[BitOrder::Lsb, BitOrder::Msb] {
+                for &max_code_size in &[9, 12, 16] {
+                    let encoded = encoder(flavor, order, 8)
+                        .with_max_code_size(max_code_size)
+                        .with_clear_policy(policy)
+                        .build()
+                        .encode(&data)
+                        .unwrap();
+                    let decoder = decoder(flavor, order, 8).with_max_code_size(max_code_size);
+                    let context = (policy, flavor, order, max_code_size);
+
+                    let full = (1 << max_code_size) - 257;
+                    assert_segments(policy, &segments(&decoder, &encoded), full, context);
+                    assert!(
+                        decoder.build().decode(&encoded).unwrap() == data,
+                        "{:?}",
+                        context
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn codes_between_clears_small_alphabet() {
+    let data: Vec<u8> = (0..1u32 << 16)
+        .map(|i| (i.wrapping_mul(0x9e37_79b9) >> 30) as u8)
+        .collect();
+
+    for &policy in POLICIES {
+        for &order in &[BitOrder::Lsb, BitOrder::Msb] {
+            let encoded = EncodeConfig::new(order, 2)
+                .with_clear_policy(policy)
+                .build()
+                .encode(&data)
+                .unwrap();
+            let decoder = decode::Configuration::new(order, 2);
+
+            // The alphabet of 4 symbols and the clear and end code.
+            let full = (1 << 12) - 5;
+            assert_segments(policy, &segments(&decoder, &encoded), full, (policy, order));
+            assert!(
+                decoder.build().decode(&encoded).unwrap() == data,
+                "{:?}",
+                policy
+            );
+        }
+    }
+}
+
+#[test]
+fn adaptive_clears_when_data_changes() {
+    // Repetitive data, then noise which a table built for the repetition does not fit.
+    let mut state = 1u32;
+    let mut data: Vec<u8> = (0..1 << 14).map(|i| b"abcabd"[i % 6]).collect();
+    data.extend((0..1 << 16).map(|_| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        (state >> 24) as u8
+    }));
+
+    for &max_code_size in &[9, 12] {
+        let encoded = encoder(Flavor::Gif, BitOrder::Lsb, 8)
+            .with_max_code_size(max_code_size)
+            .with_clear_policy(ClearPolicy::Adaptive)
+            .build()
+            .encode(&data)
+            .unwrap();
+        let decoder = decoder(Flavor::Gif, BitOrder::Lsb, 8).with_max_code_size(max_code_size);
+        let segments = segments(&decoder, &encoded);
+
+        // The table is kept for a while after it is full but not for the whole data.
+        let full = (1 << max_code_size) - 257;
+        assert!(segments.len() > 2, "{}", max_code_size);
+        for &count in &segments[1..segments.len() - 1] {
+            assert!(count > full, "{} {:?}", max_code_size, segments);
+        }
+        assert!(decoder.build().decode(&encoded).unwrap() == data);
+    }
+}
+
+#[test]
+fn policies_differ() {
+    let data = test_data(1 << 15);
+    let encode = |policy| {
+        EncodeConfig::new(BitOrder::Lsb, 8)
+            .with_clear_policy(policy)
+            .build()
+            .encode(&data)
+            .unwrap()
+    };
+
+    let when_full = encode(ClearPolicy::WhenFull);
+    assert_eq!(
+        when_full,
+        weezl::encode::Encoder::new(BitOrder::Lsb, 8)
+            .encode(&data)
+            .unwrap()
+    );
+    assert_ne!(encode(ClearPolicy::Freeze), when_full);
+    // Clearing after every code never builds a dictionary, each byte is one 9-bit code.
+    assert!(encode(ClearPolicy::Interval(1)).len() > data.len() * 9 / 8);
+}
+
+#[test]
+#[should_panic]
+fn zero_interval() {
+    let _ = EncodeConfig::new(BitOrder::Lsb, 8).with_clear_policy(ClearPolicy::Interval(0));
+}
+
+/// The number of codes before and after each clear code.
+fn segments(decoder: &decode::Configuration, encoded: &[u8]) -> Vec<usize> {
+    let mut segments = vec![0];
+    for token in decoder.clone().tokens(encoded) {
+        match token.unwrap().kind {
+            TokenKind::Clear => segments.push(0),
+            TokenKind::Literal(_) | TokenKind::Reference { .. } => {
+                *segments.last_mut().unwrap() += 1
+            }
+            _ => {}
+        }
+    }
+    segments
+}
+
+/// Check where the policy placed the clear codes, given the number of codes that fill the table.
+fn assert_segments(policy: ClearPolicy, segments: &[usize], full: usize, context: impl Debug) {
+    // The stream always starts with a clear code.
+    assert_eq!(segments[0], 0, "{:?}", context);
+    let (last, cleared) = segments[1..].split_last().unwrap();
+
+    let interval = match policy {
+        ClearPolicy::WhenFull => full,
+        ClearPolicy::Interval(codes) => full.min(codes as usize),
+        // Only the initial clear.
+        ClearPolicy::Freeze => return assert!(cleared.is_empty(), "{:?}", context),
+        _ => return,
+    };
+
+    assert!(*last <= interval, "{:?}", context);
+    for &count in cleared {
+        assert_eq!(count, interval, "{:?}", context);
+    }
+}
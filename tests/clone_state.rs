@@ -0,0 +1,120 @@
+use weezl::decode::{Configuration, Decoder, TableStrategy};
+use weezl::{compress, encode, BitOrder, LzwStatus};
+
+mod common;
+use common::test_data;
+
+const STRATEGIES: &[TableStrategy] = &[
+    TableStrategy::Classic,
+    TableStrategy::Chunked,
+    TableStrategy::Streaming,
+];
+
+#[test]
+fn clone_decoder_midway() {
+    let data = test_data(1 << 15);
+
+    for &order in &[BitOrder::Lsb, BitOrder::Msb] {
+        let encoded = encode::Encoder::new(order, 8).encode(&data).unwrap();
+
+        for &strategy in STRATEGIES {
+            let mut decoder = Configuration::new(order, 8)
+                .with_table_strategy(strategy)
+                .build();
+            let (mut decoded, consumed) = decode_part(&mut decoder, &encoded, data.len() / 2);
+
+            // Both continue from the same state.
+            let mut clone = decoder.clone();
+            let mut rest = decoder.decode(&encoded[consumed..]).unwrap();
+            let clone_rest = clone.decode(&encoded[consumed..]).unwrap();
+            assert!(rest == clone_rest, "{:?} {:?}", order, strategy);
+
+            decoded.append(&mut rest);
+            assert!(decoded == data, "{:?} {:?}", order, strategy);
+        }
+    }
+}
+
+#[test]
+fn speculative_decoding() {
+    let data = test_data(1 << 15);
+    let encoded = encode::Encoder::new(BitOrder::Lsb, 8)
+        .encode(&data)
+        .unwrap();
+
+    let mut decoder = Decoder::new(BitOrder::Lsb, 8);
+    let (mut decoded, consumed) = decode_part(&mut decoder, &encoded, data.len() / 3);
+
+    // A failed attempt on the clone does not affect the original.
+    let mut attempt = decoder.clone();
+    assert!(attempt.decode(&[0xff; 64]).is_err());
+
+    decoded.append(&mut decoder.decode(&encoded[consumed..]).unwrap());
+    assert!(decoded == data);
+}
+
+#[test]
+fn clone_encoder_midway() {
+    let data = test_data(1 << 15);
+    let (first, second) = data.split_at(data.len() / 2);
+
+    for &order in &[BitOrder::Lsb, BitOrder::Msb] {
+        let expected = encode::Encoder::new(order, 8).encode(&data).unwrap();
+
+        let mut encoder = encode::Encoder::new(order, 8);
+        let mut encoded = vec![0; data.len()];
+        let result = encoder.encode_bytes(first, &mut encoded);
+        assert_eq!(result.consumed_in, first.len());
+        encoded.truncate(result.consumed_out);
+
+        let mut clone = encoder.clone();
+        let mut clone_encoded = encoded.clone();
+        encoder
+            .into_vec(&mut encoded)
+            .encode_all(second)
+            .status
+            .unwrap();
+        clone
+            .into_vec(&mut clone_encoded)
+            .encode_all(second)
+            .status
+            .unwrap();
+
+        assert!(encoded == expected, "{:?}", order);
+        assert!(clone_encoded == expected, "{:?}", order);
+    }
+}
+
+#[test]
+fn clone_compress() {
+    let data = test_data(1 << 15);
+    let encoded = compress::Configuration::new()
+        .build_encoder()
+        .encode(&data)
+        .unwrap();
+
+    let mut decoder = compress::Configuration::new().build_decoder();
+    let (mut decoded, consumed) = decode_part(&mut decoder, &encoded, data.len() / 2);
+    let mut clone = decoder.clone();
+
+    let mut rest = clone.decode(&encoded[consumed..]).unwrap();
+    assert!(rest == decoder.decode(&encoded[consumed..]).unwrap());
+    decoded.append(&mut rest);
+    assert!(decoded == data);
+}
+
+/// Decode about `len` bytes, returning them and the consumed input.
+fn decode_part(decoder: &mut Decoder, encoded: &[u8], len: usize) -> (Vec<u8>, usize) {
+    let mut decoded = vec![0; len];
+    let mut filled = 0;
+    let mut consumed = 0;
+
+    while filled < len {
+        let result = decoder.decode_bytes(&encoded[consumed..], &mut decoded[filled..]);
+        consumed += result.consumed_in;
+        filled += result.consumed_out;
+        assert!(matches!(result.status, Ok(LzwStatus::Ok)));
+    }
+
+    (decoded, consumed)
+}
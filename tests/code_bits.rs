@@ -0,0 +1,191 @@
+use weezl::decode::{CodeReader, Decoder};
+use weezl::encode::{CodeWriter, Encoder};
+use weezl::{BitOrder, LzwError};
+
+/// Codes with a varying width, in the pattern of a simple generator.
+fn codes() -> Vec<(u16, u8)> {
+    (0..1000u32)
+        .map(|i| {
+            let width = (i * 7 % 16) as u8 + 1;
+            let code = (i.wrapping_mul(2_654_435_761) >> 16) as u16 & (u16::MAX >> (16 - width));
+            (code, width)
+        })
+        .collect()
+}
+
+#[test]
+fn roundtrip_widths() {
+    for &order in &[BitOrder::Lsb, BitOrder::Msb] {
+        let mut writer = CodeWriter::new(order);
+        for &(code, width) in &codes() {
+            writer.write_code(code, width);
+        }
+
+        let bits = writer.bit_len();
+        let data = writer.finish();
+        assert_eq!(data.len() as u64, bits.div_ceil(8));
+
+        let mut reader = CodeReader::new(order, &data);
+        for &(code, width) in &codes() {
+            assert_eq!(reader.read_code(width), Some(code), "{:?}", order);
+        }
+        assert_eq!(reader.bit_position(), bits);
+    }
+}
+
+#[test]
+fn alignment() {
+    for &order in &[BitOrder::Lsb, BitOrder::Msb] {
+        let mut writer = CodeWriter::new(order);
+        writer.write_code(1, 1);
+        writer.align_to_byte();
+        assert_eq!(writer.bit_len(), 8);
+        // Aligning twice adds nothing.
+        writer.align_to_byte();
+        writer.write_code(0x1234, 16);
+        writer.write_code(3, 2);
+        let data = writer.finish();
+        assert_eq!(data.len(), 4);
+
+        let mut reader = CodeReader::new(order, &data);
+        assert_eq!(reader.read_code(1), Some(1));
+        reader.align_to_byte();
+        reader.align_to_byte();
+        assert_eq!(reader.bit_position(), 8);
+        assert_eq!(reader.read_code(16), Some(0x1234));
+        assert_eq!(reader.read_code(2), Some(3));
+        reader.align_to_byte();
+        assert_eq!(reader.bit_position(), 32);
+        assert_eq!(reader.read_code(1), None);
+    }
+}
+
+#[test]
+fn padding() {
+    for &order in &[BitOrder::Lsb, BitOrder::Msb] {
+        let mut writer = CodeWriter::new(order);
+        writer.write_code(0x1ff, 9);
+        writer.pad(37);
+        assert_eq!(writer.bit_len(), 46);
+        writer.write_code(5, 3);
+        writer.align_to(7 * 8);
+        assert_eq!(writer.bit_len(), 56);
+        // Aligning twice adds nothing.
+        writer.align_to(7 * 8);
+        writer.write_code(0xff, 8);
+        let data = writer.finish();
+        assert_eq!(data.len(), 8);
+        // Padding consists of zero bits.
+        assert_eq!(data.iter().map(|b| b.count_ones()).sum::<u32>(), 9 + 2 + 8);
+
+        let mut reader = CodeReader::new(order, &data);
+        assert_eq!(reader.read_code(9), Some(0x1ff));
+        assert!(reader.skip(37));
+        assert_eq!(reader.read_code(3), Some(5));
+        assert!(reader.align_to(7 * 8));
+        assert!(reader.align_to(7 * 8));
+        assert_eq!(reader.bit_position(), 56);
+        assert_eq!(reader.read_code(8), Some(0xff));
+
+        // Skipping beyond the end skips the rest.
+        let mut reader = CodeReader::new(order, &data);
+        assert!(!reader.skip(100));
+        assert_eq!(reader.bit_position(), 64);
+        let mut reader = CodeReader::new(order, &data);
+        assert_eq!(reader.read_code(1), Some(1));
+        assert!(!reader.align_to(100));
+        assert_eq!(reader.bit_position(), 64);
+    }
+}
+
+#[test]
+fn compress_groups() {
+    // Codes of `compress` come in groups of eight, and after a clear code the rest of the group is
+    // padding. Build a stream with and without that padding.
+    let stream = |padded: bool| {
+        let mut writer = CodeWriter::new(BitOrder::Lsb);
+        writer.write_code(b'a'.into(), 9);
+        writer.write_code(b'b'.into(), 9);
+        writer.write_code(256, 9);
+        if padded {
+            writer.pad(5 * 9);
+            assert_eq!(writer.bit_len() % (8 * 9), 0);
+        }
+        writer.write_code(b'c'.into(), 9);
+
+        let mut stream = weezl::compress::MAGIC.to_vec();
+        stream.push(0x90);
+        stream.extend(writer.finish());
+        stream
+    };
+
+    let decode = |stream: &[u8]| {
+        weezl::compress::Configuration::new()
+            .build_decoder()
+            .decode(stream)
+    };
+
+    assert_eq!(decode(&stream(true)).unwrap(), b"abc");
+    assert_ne!(decode(&stream(false)).ok().as_deref(), Some(&b"abc"[..]));
+
+    // The reader skips the padding the same way.
+    let padded = stream(true);
+    let mut reader = CodeReader::new(BitOrder::Lsb, &padded[3..]);
+    for _ in 0..3 {
+        reader.read_code(9);
+    }
+    assert!(reader.align_to(8 * 9));
+    assert_eq!(reader.read_code(9), Some(b'c'.into()));
+}
+
+#[test]
+fn short_data() {
+    let mut reader = CodeReader::new(BitOrder::Lsb, &[0xff]);
+    assert_eq!(reader.read_code(9), None);
+    // The remaining bits can still be read in smaller codes.
+    assert_eq!(reader.read_code(5), Some(0x1f));
+    assert_eq!(reader.read_code(3), Some(0x7));
+    assert_eq!(reader.read_code(1), None);
+}
+
+#[test]
+fn matches_encoder() {
+    let data = b"TOBEORNOTTOBEORTOBEORNOT";
+
+    for &order in &[BitOrder::Lsb, BitOrder::Msb] {
+        let encoded = Encoder::new(order, 8).encode(data).unwrap();
+
+        // All codes of this short stream have 9 bits.
+        let mut reader = CodeReader::new(order, &encoded);
+        let mut writer = CodeWriter::new(order);
+        while let Some(code) = reader.read_code(9) {
+            writer.write_code(code, 9);
+        }
+
+        assert_eq!(writer.finish(), encoded);
+    }
+}
+
+#[test]
+fn malformed_stream() {
+    // A clear code followed by a code that is not yet in the table.
+    let mut writer = CodeWriter::new(BitOrder::Lsb);
+    writer.write_code(256, 9);
+    writer.write_code(b'A'.into(), 9);
+    writer.write_code(300, 9);
+    let data = writer.finish();
+
+    match Decoder::new(BitOrder::Lsb, 8).decode(&data) {
+        Err(LzwError::CodeOutOfRange(err)) => {
+            assert_eq!(err.code, 300);
+            assert_eq!((err.byte_offset, err.bit_offset), (2, 2));
+        }
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+#[should_panic]
+fn code_too_wide() {
+    CodeWriter::new(BitOrder::Msb).write_code(512, 9);
+}
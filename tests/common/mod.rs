@@ -0,0 +1,71 @@
+//! Helpers shared by the integration tests.
+// Not every test uses every helper.
+#![allow(dead_code)]
+
+use std::io::Read;
+use std::{env, fs};
+use weezl::{decode, encode, BitOrder, LzwStatus};
+
+/// The variants of the code size switch.
+#[derive(Clone, Copy, Debug)]
+pub enum Flavor {
+    Gif,
+    Tiff,
+}
+
+/// Up to `len` bytes of the test binary itself, data that is neither random nor trivial.
+pub fn test_data(len: usize) -> Vec<u8> {
+    let file = env::args().next().unwrap();
+    let mut data = fs::read(file).unwrap();
+    data.truncate(len);
+    data
+}
+
+pub fn encoder(flavor: Flavor, order: BitOrder, size: u8) -> encode::Configuration {
+    match flavor {
+        Flavor::Gif => encode::Configuration::new(order, size),
+        Flavor::Tiff => encode::Configuration::with_tiff_size_switch(order, size),
+    }
+}
+
+pub fn decoder(flavor: Flavor, order: BitOrder, size: u8) -> decode::Configuration {
+    match flavor {
+        Flavor::Gif => decode::Configuration::new(order, size),
+        Flavor::Tiff => decode::Configuration::with_tiff_size_switch(order, size),
+    }
+}
+
+/// Decode the whole stream into an output buffer of `size` bytes at a time.
+pub fn decode_in_parts(config: decode::Configuration, mut encoded: &[u8], size: usize) -> Vec<u8> {
+    let mut decoder = config.build();
+    let mut decoded = vec![];
+    let mut out = vec![0; size];
+
+    loop {
+        let result = decoder.decode_bytes(encoded, &mut out);
+        encoded = &encoded[result.consumed_in..];
+        decoded.extend_from_slice(&out[..result.consumed_out]);
+        match result.status.unwrap() {
+            LzwStatus::Done => break,
+            LzwStatus::NoProgress => panic!("Decoding did not finish"),
+            LzwStatus::Ok => {}
+        }
+    }
+
+    decoded
+}
+
+/// Read everything with reads of at most `size` bytes.
+pub fn read_in_parts(mut reader: impl Read, size: usize) -> Vec<u8> {
+    let mut data = vec![];
+    let mut buf = vec![0; size];
+
+    loop {
+        match reader.read(&mut buf).unwrap() {
+            0 => break,
+            len => data.extend_from_slice(&buf[..len]),
+        }
+    }
+
+    data
+}
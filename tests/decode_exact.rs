@@ -0,0 +1,132 @@
+use weezl::decode::{Configuration, Decoder, TableStrategy};
+use weezl::{encode::Encoder, BitOrder, LzwError};
+
+/// The codes `clear, A, B, C, D` with 9 bits each and no end code.
+const MISSING_END: &[u8] = &[0x00, 0x83, 0x08, 0x19, 0x42, 0x04];
+
+const STRATEGIES: &[TableStrategy] = &[
+    TableStrategy::Classic,
+    TableStrategy::Chunked,
+    TableStrategy::Streaming,
+];
+
+fn decoders(order: BitOrder) -> impl Iterator<Item = Decoder> {
+    STRATEGIES.iter().flat_map(move |&strategy| {
+        [false, true].iter().map(move |&do_yield| {
+            Configuration::new(order, 8)
+                .with_table_strategy(strategy)
+                .with_yield_on_full_buffer(do_yield)
+                .build()
+        })
+    })
+}
+
+fn text() -> Vec<u8> {
+    b"TOBEORNOTTOBEORTOBEORNOT".repeat(50)
+}
+
+#[test]
+fn with_end_code() {
+    let data = text();
+    let encoded = Encoder::new(BitOrder::Msb, 8).encode(&data).unwrap();
+
+    for mut decoder in decoders(BitOrder::Msb) {
+        let mut out = vec![0; data.len()];
+        let len = decoder.decode_exact(&encoded, &mut out).unwrap();
+        assert_eq!(len, encoded.len());
+        assert!(decoder.has_ended());
+        assert!(out == data);
+    }
+}
+
+#[test]
+fn trailing_garbage() {
+    let data = text();
+    let mut encoded = Encoder::new(BitOrder::Lsb, 8).encode(&data).unwrap();
+    let stream_len = encoded.len();
+    encoded.extend_from_slice(&[0xff; 16]);
+
+    for mut decoder in decoders(BitOrder::Lsb) {
+        let mut out = vec![0; data.len()];
+        let len = decoder.decode_exact(&encoded, &mut out).unwrap();
+        assert_eq!(len, stream_len);
+        assert!(decoder.has_ended());
+        assert!(out == data);
+    }
+}
+
+#[test]
+fn without_end_code() {
+    for mut decoder in decoders(BitOrder::Lsb) {
+        let mut out = [0; 4];
+        let len = decoder.decode_exact(MISSING_END, &mut out).unwrap();
+        assert_eq!(len, MISSING_END.len());
+        assert!(!decoder.has_ended());
+        assert_eq!(&out, b"ABCD");
+    }
+
+    // Garbage in place of the end code is not interpreted.
+    let mut garbage = MISSING_END.to_vec();
+    garbage.extend_from_slice(&[0xff; 4]);
+    for mut decoder in decoders(BitOrder::Lsb) {
+        let mut out = [0; 4];
+        let len = decoder.decode_exact(&garbage, &mut out).unwrap();
+        assert!(len < garbage.len());
+        assert!(!decoder.has_ended());
+        assert_eq!(&out, b"ABCD");
+    }
+}
+
+#[test]
+fn longer_stream() {
+    let data = text();
+    let encoded = Encoder::new(BitOrder::Msb, 8).encode(&data).unwrap();
+
+    for mut decoder in decoders(BitOrder::Msb) {
+        let mut out = vec![0; data.len() / 2];
+        let len = decoder.decode_exact(&encoded, &mut out).unwrap();
+        assert!(len < encoded.len());
+        assert!(!decoder.has_ended());
+        assert!(data.starts_with(&out));
+    }
+}
+
+#[test]
+fn short_stream() {
+    let data = text();
+    let encoded = Encoder::new(BitOrder::Msb, 8).encode(&data).unwrap();
+
+    // The end code appears too early.
+    for mut decoder in decoders(BitOrder::Msb) {
+        let mut out = vec![0; data.len() + 1];
+        let err = decoder.decode_exact(&encoded, &mut out).unwrap_err();
+        assert_eq!(err, LzwError::UnexpectedEof);
+        assert!(out[..data.len()] == data[..]);
+    }
+
+    // The input ends early.
+    for mut decoder in decoders(BitOrder::Msb) {
+        let mut out = vec![0; data.len()];
+        let err = decoder
+            .decode_exact(&encoded[..encoded.len() / 2], &mut out)
+            .unwrap_err();
+        assert_eq!(err, LzwError::UnexpectedEof);
+    }
+}
+
+#[test]
+fn tiff_strips() {
+    let data = text();
+    let encoded = Encoder::with_tiff_size_switch(BitOrder::Msb, 8)
+        .encode(&data)
+        .unwrap();
+
+    let mut decoder = Decoder::with_tiff_size_switch(BitOrder::Msb, 8);
+    for _ in 0..3 {
+        let mut out = vec![0; data.len()];
+        let len = decoder.decode_exact(&encoded, &mut out).unwrap();
+        assert_eq!(len, encoded.len());
+        assert!(out == data);
+        decoder.reset();
+    }
+}
@@ -0,0 +1,146 @@
+use std::io::{self, Read, Write};
+
+use weezl::decode::{self, Configuration, EndMarker};
+use weezl::{BitOrder, LzwError};
+
+/// The codes `clear, A, B, C, D` with 9 bits each and no end code.
+const MISSING_END: &[u8] = &[0x00, 0x83, 0x08, 0x19, 0x42, 0x04];
+
+fn decoder(end_marker: EndMarker) -> decode::Decoder {
+    Configuration::new(BitOrder::Lsb, 8)
+        .with_end_marker(end_marker)
+        .build()
+}
+
+#[test]
+fn decode_require() {
+    let err = decoder(EndMarker::Require).decode(MISSING_END).unwrap_err();
+    assert_eq!(err, LzwError::UnexpectedEof);
+
+    let mut decoded = vec![];
+    let result = decoder(EndMarker::Require)
+        .into_vec(&mut decoded)
+        .decode_all(MISSING_END);
+    assert_eq!(result.status.unwrap_err(), LzwError::UnexpectedEof);
+    assert_eq!(result.consumed_in, MISSING_END.len());
+    assert_eq!(decoded, b"ABCD");
+}
+
+#[test]
+fn decode_accept_missing() {
+    let decoded = decoder(EndMarker::AcceptMissing)
+        .decode(MISSING_END)
+        .unwrap();
+    assert_eq!(decoded, b"ABCD");
+}
+
+#[test]
+fn decode_accept_missing_after_full_buffer() {
+    // The expected length is indicated through the capacity.
+    let mut decoded = Vec::with_capacity(4);
+    let result = decoder(EndMarker::AcceptMissingAfterFullBuffer)
+        .into_vec(&mut decoded)
+        .decode_all(MISSING_END);
+    assert!(result.status.is_ok());
+    assert_eq!(decoded, b"ABCD");
+
+    let mut decoded = Vec::with_capacity(5);
+    let result = decoder(EndMarker::AcceptMissingAfterFullBuffer)
+        .into_vec(&mut decoded)
+        .decode_all(MISSING_END);
+    assert_eq!(result.status.unwrap_err(), LzwError::UnexpectedEof);
+    assert_eq!(decoded, b"ABCD");
+}
+
+#[test]
+fn decode_accept_missing_after_large_buffer() {
+    let data: Vec<u8> = (0..20_000u32).map(|i| (i * i / 7) as u8).collect();
+    let encoded = weezl::encode::Encoder::new(BitOrder::Lsb, 8)
+        .encode(&data)
+        .unwrap();
+    // Cut off the end code and the last codes before it.
+    let truncated = &encoded[..encoded.len() - 4];
+    let expected = decoder(EndMarker::AcceptMissing).decode(truncated).unwrap();
+    assert!(expected.len() > 1 << 13);
+
+    // The capacity is filled over several calls of the decoder.
+    let mut decoded = Vec::with_capacity(expected.len());
+    let result = decoder(EndMarker::AcceptMissingAfterFullBuffer)
+        .into_vec(&mut decoded)
+        .decode_all(truncated);
+    assert!(result.status.is_ok());
+    assert_eq!(decoded, expected);
+
+    let mut decoded = Vec::with_capacity(expected.len() + 1);
+    let result = decoder(EndMarker::AcceptMissingAfterFullBuffer)
+        .into_vec(&mut decoded)
+        .decode_all(truncated);
+    assert_eq!(result.status.unwrap_err(), LzwError::UnexpectedEof);
+    assert_eq!(decoded, expected);
+}
+
+#[test]
+fn stream_keeps_partial_output() {
+    let mut decoded = vec![];
+    let mut dec = decoder(EndMarker::Require);
+    let result = dec.into_stream(&mut decoded).decode_all(MISSING_END);
+    let err = result.status.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert_eq!(decoded, b"ABCD");
+
+    // An invalid code after the valid ones.
+    let mut corrupt = MISSING_END.to_vec();
+    corrupt.extend_from_slice(&[0xff, 0xff]);
+    let mut decoded = vec![];
+    let mut dec = decoder(EndMarker::AcceptMissing);
+    let result = dec.into_stream(&mut decoded).decode_all(&corrupt[..]);
+    let err = result.status.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(decoded, b"ABCD");
+}
+
+#[test]
+fn stream_end_marker_policy() {
+    let mut decoded = vec![];
+    let mut dec = decoder(EndMarker::AcceptMissing);
+    let result = dec.into_stream(&mut decoded).decode_all(MISSING_END);
+    result.status.unwrap();
+    assert_eq!(decoded, b"ABCD");
+
+    for (size, ok) in [(4, true), (2, true), (3, false), (8, false)] {
+        let mut decoded = vec![];
+        let mut dec = decoder(EndMarker::AcceptMissingAfterFullBuffer);
+        let mut stream = dec.into_stream(&mut decoded);
+        stream.set_buffer_size(size);
+        let result = stream.decode_all(MISSING_END);
+        assert_eq!(result.status.is_ok(), ok, "{}", size);
+        assert_eq!(decoded, b"ABCD");
+    }
+}
+
+#[test]
+fn read_and_write_end_marker_policy() {
+    let mut decoded = vec![];
+    let err = decoder(EndMarker::Require)
+        .into_read(MISSING_END)
+        .read_to_end(&mut decoded)
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert_eq!(decoded, b"ABCD");
+
+    let mut decoded = vec![];
+    decoder(EndMarker::AcceptMissing)
+        .into_read(MISSING_END)
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, b"ABCD");
+
+    let mut writer = decode::Writer::new(decoder(EndMarker::Require), vec![]);
+    writer.write_all(MISSING_END).unwrap();
+    let err = writer.finish().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+    let mut writer = decode::Writer::new(decoder(EndMarker::AcceptMissing), vec![]);
+    writer.write_all(MISSING_END).unwrap();
+    assert_eq!(writer.finish().unwrap(), b"ABCD");
+}
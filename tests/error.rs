@@ -0,0 +1,166 @@
+use std::io;
+
+use weezl::{decode::Decoder, encode::Encoder, BitOrder, CodeError, LzwError};
+
+/// Pack 9-bit codes into a stream of the given bit order.
+fn pack(order: BitOrder, codes: &[u16]) -> Vec<u8> {
+    let mut out = vec![];
+    let (mut buffer, mut bits) = (0u32, 0);
+
+    for &code in codes {
+        match order {
+            BitOrder::Lsb => buffer |= u32::from(code) << bits,
+            BitOrder::Msb => buffer |= u32::from(code) << (32 - 9 - bits),
+        }
+        bits += 9;
+
+        while bits >= 8 {
+            match order {
+                BitOrder::Lsb => {
+                    out.push(buffer as u8);
+                    buffer >>= 8;
+                }
+                BitOrder::Msb => {
+                    out.push((buffer >> 24) as u8);
+                    buffer <<= 8;
+                }
+            }
+            bits -= 8;
+        }
+    }
+
+    if bits > 0 {
+        match order {
+            BitOrder::Lsb => out.push(buffer as u8),
+            BitOrder::Msb => out.push((buffer >> 24) as u8),
+        }
+    }
+
+    out
+}
+
+fn code_error(err: LzwError) -> CodeError {
+    match err {
+        LzwError::InvalidCode(err) | LzwError::CodeOutOfRange(err) => err,
+        other => panic!("Expected an error with a code, got {:?}", other),
+    }
+}
+
+#[test]
+fn code_out_of_range_position() {
+    for order in [BitOrder::Lsb, BitOrder::Msb] {
+        // Clear, `A`, then a code far beyond the dictionary.
+        let data = pack(order, &[256, 65, 66, 300, 257]);
+        let err = Decoder::new(order, 8).decode(&data).unwrap_err();
+        assert!(matches!(err, LzwError::CodeOutOfRange(_)), "{:?}", err);
+
+        let err = code_error(err);
+        assert_eq!(err.code, 300);
+        assert_eq!(err.next_code, 259);
+        assert_eq!(err.code_size, 9);
+        assert_eq!((err.byte_offset, err.bit_offset), (3, 3));
+    }
+}
+
+#[test]
+fn invalid_code_position() {
+    for order in [BitOrder::Lsb, BitOrder::Msb] {
+        // The first code after a clear has no predecessor to derive an entry from.
+        let data = pack(order, &[256, 258, 257]);
+        let err = Decoder::new(order, 8).decode(&data).unwrap_err();
+        assert!(matches!(err, LzwError::InvalidCode(_)), "{:?}", err);
+
+        let err = code_error(err);
+        assert_eq!(err.code, 258);
+        assert_eq!(err.next_code, 258);
+        assert_eq!((err.byte_offset, err.bit_offset), (1, 1));
+    }
+}
+
+#[test]
+fn position_across_calls() {
+    let mut codes = vec![256];
+    codes.extend((0..40).map(|i| i % 4 + 65));
+    codes.push(400);
+    let data = pack(BitOrder::Lsb, &codes);
+
+    let mut decoder = Decoder::new(BitOrder::Lsb, 8);
+    let mut out = [0; 256];
+    let mut consumed = 0;
+
+    let err = loop {
+        let end = (consumed + 1).min(data.len());
+        let result = decoder.decode_bytes(&data[consumed..end], &mut out);
+        consumed += result.consumed_in;
+        if let Err(err) = result.status {
+            break err;
+        }
+    };
+
+    let err = code_error(err);
+    assert_eq!(err.code, 400);
+    assert_eq!(err.byte_offset, 41 * 9 / 8);
+    assert_eq!(err.bit_offset, (41 * 9 % 8) as u8);
+}
+
+#[test]
+fn invalid_symbol_offset() {
+    let mut encoder = Encoder::new(BitOrder::Lsb, 4);
+    let mut out = [0; 64];
+
+    let result = encoder.encode_bytes(&[1, 2, 3], &mut out);
+    assert_eq!(result.consumed_in, 3);
+
+    let result = encoder.encode_bytes(&[4, 0x15, 6], &mut out);
+    assert_eq!(result.consumed_in, 1);
+    assert_eq!(
+        result.status.unwrap_err(),
+        LzwError::InvalidSymbol {
+            byte: 0x15,
+            symbol_size: 4,
+            offset: 4,
+        }
+    );
+}
+
+#[test]
+fn compress_position_includes_header() {
+    let mut data = vec![0x1f, 0x9d, 0x90];
+    data.extend(pack(BitOrder::Lsb, &[65, 300]));
+
+    let err = weezl::compress::Configuration::new()
+        .build_decoder()
+        .decode(&data)
+        .unwrap_err();
+    let err = code_error(err);
+    assert_eq!((err.byte_offset, err.bit_offset), (4, 1));
+}
+
+#[test]
+fn io_error_kinds() {
+    let data = pack(BitOrder::Lsb, &[256, 300]);
+    let err = Decoder::new(BitOrder::Lsb, 8).decode(&data).unwrap_err();
+    let io_err = io::Error::from(err);
+    assert_eq!(io_err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(io_err.to_string(), err.to_string());
+
+    let err = Encoder::new(BitOrder::Lsb, 4).encode(&[0x10]).unwrap_err();
+    assert_eq!(io::Error::from(err).kind(), io::ErrorKind::InvalidInput);
+
+    let err = io::Error::from(LzwError::UnexpectedEof);
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn io_error_from_writer() {
+    use io::Write;
+
+    let data = pack(BitOrder::Msb, &[256, 65, 300]);
+    let mut writer = weezl::decode::Writer::new(Decoder::new(BitOrder::Msb, 8), vec![]);
+    let err = writer.write_all(&data).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    let inner = err.into_inner().unwrap();
+    let err = inner.downcast_ref::<LzwError>().unwrap();
+    assert!(matches!(err, LzwError::CodeOutOfRange(_)));
+}
@@ -0,0 +1,143 @@
+use std::io::{Read, Write};
+use weezl::{encode::Encoder, gif, BitOrder, LzwError};
+
+mod common;
+use common::test_data;
+
+/// Indices of the test data that fit the minimum code size.
+fn indices(size: u8) -> Vec<u8> {
+    let mask = ((1u16 << size) - 1) as u8;
+    test_data(1 << 15).iter().map(|b| b & mask).collect()
+}
+
+/// Frame raw codes in sub-blocks of the given length.
+fn frame(size: u8, codes: &[u8], block: usize) -> Vec<u8> {
+    let mut framed = vec![size];
+    for chunk in codes.chunks(block) {
+        framed.push(chunk.len() as u8);
+        framed.extend_from_slice(chunk);
+    }
+    framed.push(0);
+    framed
+}
+
+#[test]
+fn roundtrip() {
+    // Including the sizes below the specification.
+    for size in 0..=8 {
+        let data = indices(size);
+        let framed = gif::encode(size, &data).unwrap();
+        assert_eq!(gif::decode(&framed).unwrap(), data);
+
+        // The same as framing the output of a plain encoder.
+        let codes = Encoder::new(BitOrder::Lsb, size).encode(&data).unwrap();
+        assert_eq!(framed, frame(size, &codes, 255));
+    }
+}
+
+#[test]
+fn reader_and_writer() {
+    for size in 0..=8 {
+        let data = indices(size);
+
+        let mut writer = gif::Writer::new(vec![], size).unwrap();
+        for chunk in data.chunks(100) {
+            writer.write_all(chunk).unwrap();
+        }
+        let framed = writer.finish().unwrap();
+        assert_eq!(framed, gif::encode(size, &data).unwrap());
+
+        let mut decoded = vec![];
+        gif::Reader::new(&framed[..])
+            .unwrap()
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, data);
+    }
+}
+
+#[test]
+fn next_block_after_terminator() {
+    let data = indices(8);
+    let codes = Encoder::new(BitOrder::Lsb, 8).encode(&data).unwrap();
+    // Small sub-blocks, then sub-blocks after the end code and the GIF trailer.
+    let mut framed = frame(8, &codes, 17);
+    let len = framed.len();
+    framed.splice(len - 1..len - 1, [3, 0xaa, 0xbb, 0xcc]);
+    framed.push(0x3b);
+
+    let mut decoded = vec![];
+    let consumed = gif::decode_into(&framed, &mut decoded).unwrap();
+    assert_eq!(decoded, data);
+    assert_eq!(consumed, framed.len() - 1);
+
+    let mut reader = gif::Reader::new(&framed[..]).unwrap();
+    let mut decoded = vec![];
+    reader.read_to_end(&mut decoded).unwrap();
+    assert_eq!(decoded, data);
+    assert_eq!(reader.finish().unwrap(), [0x3b]);
+}
+
+#[test]
+fn lenient_endings() {
+    let data = indices(8);
+    let codes = Encoder::new(BitOrder::Lsb, 8).encode(&data).unwrap();
+    let framed = frame(8, &codes, 255);
+
+    // A missing terminator.
+    let unterminated = &framed[..framed.len() - 1];
+    assert_eq!(gif::decode(unterminated).unwrap(), data);
+    let mut decoded = vec![];
+    gif::Reader::new(unterminated)
+        .unwrap()
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, data);
+
+    // A sub-block cut short, the stream ends without its end code.
+    let truncated = &framed[..framed.len() / 2];
+    let partial = gif::decode(truncated).unwrap();
+    assert!(!partial.is_empty());
+    assert_eq!(partial[..], data[..partial.len()]);
+    let mut decoded = vec![];
+    gif::Reader::new(truncated)
+        .unwrap()
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, partial);
+}
+
+#[test]
+fn oversized_min_code_size() {
+    let data = indices(8);
+    for size in 9..=11 {
+        // Decoded with the symbol size as written.
+        let codes = Encoder::new(BitOrder::Lsb, size).encode(&data).unwrap();
+        let framed = frame(size, &codes, 255);
+        assert_eq!(gif::decode(&framed).unwrap(), data);
+
+        let mut decoded = vec![];
+        gif::Reader::new(&framed[..])
+            .unwrap()
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, data);
+    }
+}
+
+#[test]
+#[should_panic]
+fn encode_oversized_min_code_size() {
+    let _ = gif::encode(9, &[0]);
+}
+
+#[test]
+fn invalid_min_code_size() {
+    for size in 12..=u8::MAX {
+        let framed = [size, 1, 0, 0];
+        assert_eq!(gif::decode(&framed), Err(LzwError::InvalidHeader));
+        assert!(gif::Reader::new(&framed[..]).is_err());
+    }
+
+    assert_eq!(gif::decode(&[]), Err(LzwError::UnexpectedEof));
+}
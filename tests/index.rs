@@ -0,0 +1,105 @@
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use weezl::decode::{Configuration, Decoder, Index, IndexedReader, TableStrategy};
+use weezl::{compress, encode, BitOrder};
+
+mod common;
+use common::test_data;
+
+#[test]
+fn seek_anywhere() {
+    let data = test_data(1 << 15);
+
+    for &order in &[BitOrder::Lsb, BitOrder::Msb] {
+        let encoded = encode::Encoder::new(order, 8).encode(&data).unwrap();
+
+        for &strategy in &[
+            TableStrategy::Classic,
+            TableStrategy::Chunked,
+            TableStrategy::Streaming,
+        ] {
+            let decoder = Configuration::new(order, 8)
+                .with_table_strategy(strategy)
+                .build();
+            let index = Index::build(decoder, 1000, &encoded[..]).unwrap();
+            assert_eq!(index.len(), data.len() as u64);
+
+            let mut reader = IndexedReader::new(&index, Cursor::new(&encoded)).unwrap();
+            // Forward and backward, within and across checkpoints.
+            for &offset in &[5000, 0, 999, 1000, 17_333, 17_400, 2500, data.len() - 7] {
+                reader.seek(SeekFrom::Start(offset as u64)).unwrap();
+                let mut part = vec![0; 300.min(data.len() - offset)];
+                reader.read_exact(&mut part).unwrap();
+                assert!(
+                    part == data[offset..][..part.len()],
+                    "{:?} {:?}",
+                    order,
+                    strategy
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn read_to_end_after_seek() {
+    let data = test_data(1 << 15);
+    let encoded = encode::Encoder::new(BitOrder::Msb, 8)
+        .encode(&data)
+        .unwrap();
+    let index = Index::build(Decoder::new(BitOrder::Msb, 8), 4096, &encoded[..]).unwrap();
+    let mut reader = IndexedReader::new(&index, Cursor::new(&encoded)).unwrap();
+
+    let mut rest = vec![];
+    assert_eq!(
+        reader.seek(SeekFrom::End(-10_000)).unwrap(),
+        data.len() as u64 - 10_000
+    );
+    reader.read_to_end(&mut rest).unwrap();
+    assert!(rest == data[data.len() - 10_000..]);
+
+    assert_eq!(
+        reader.seek(SeekFrom::Current(-20)).unwrap(),
+        data.len() as u64 - 20
+    );
+    let mut tail = vec![];
+    reader.read_to_end(&mut tail).unwrap();
+    assert!(tail == data[data.len() - 20..]);
+
+    // Beyond the end there is nothing to read, and before the start is an error.
+    reader.seek(SeekFrom::End(100)).unwrap();
+    assert_eq!(reader.read(&mut [0; 16]).unwrap(), 0);
+    assert!(reader.seek(SeekFrom::Current(-1_000_000)).is_err());
+}
+
+#[test]
+fn stream_inside_file() {
+    let data = test_data(1 << 15);
+    let mut file = b"some header".to_vec();
+    let start = file.len() as u64;
+    file.extend(
+        compress::Configuration::new()
+            .build_encoder()
+            .encode(&data)
+            .unwrap(),
+    );
+
+    let decoder = compress::Configuration::new().build_decoder();
+    let index = Index::build(decoder, 3000, &file[start as usize..]).unwrap();
+
+    let mut cursor = Cursor::new(&file);
+    cursor.set_position(start);
+    let mut reader = IndexedReader::new(&index, cursor).unwrap();
+    reader.seek(SeekFrom::Start(20_000)).unwrap();
+    let mut part = [0; 5000];
+    reader.read_exact(&mut part).unwrap();
+    assert!(part[..] == data[20_000..25_000]);
+}
+
+#[test]
+fn invalid_stream() {
+    let encoded = encode::Encoder::new(BitOrder::Lsb, 8)
+        .encode(&test_data(1 << 15))
+        .unwrap();
+    let truncated = &encoded[..encoded.len() / 2];
+    assert!(Index::build(Decoder::new(BitOrder::Lsb, 8), 1000, truncated).is_err());
+}
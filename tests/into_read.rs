@@ -0,0 +1,198 @@
+use std::io::{self, BufRead, Read};
+use weezl::{decode, encode, BitOrder};
+
+mod common;
+use common::{decoder, encoder, read_in_parts, test_data, Flavor};
+
+#[test]
+fn decode_read_all() {
+    let data = test_data(1 << 18);
+
+    for &flavor in &[Flavor::Gif, Flavor::Tiff] {
+        for &bit_order in &[BitOrder::Lsb, BitOrder::Msb] {
+            let encoded = encoder(flavor, bit_order, 8).build().encode(&data).unwrap();
+
+            let mut decoder = decoder(flavor, bit_order, 8).build();
+            let mut decoded = vec![];
+            decoder
+                .into_read(encoded.as_slice())
+                .read_to_end(&mut decoded)
+                .unwrap();
+            assert!(data == decoded, "{:?}, {:?}", flavor, bit_order);
+        }
+    }
+}
+
+#[test]
+fn decode_read_small_buffers() {
+    let data = test_data(1 << 18);
+    let encoded = encoder(Flavor::Gif, BitOrder::Msb, 8)
+        .build()
+        .encode(&data)
+        .unwrap();
+
+    for &chunk in &[1, 3, 17, 4096] {
+        let mut decoder = decoder(Flavor::Gif, BitOrder::Msb, 8).build();
+        let decoded = read_in_parts(
+            decoder.into_read(io::BufReader::with_capacity(7, encoded.as_slice())),
+            chunk,
+        );
+        assert!(data == decoded, "{}", chunk);
+    }
+}
+
+#[test]
+fn decode_read_stops_at_end_code() {
+    let data = b"Hello, world";
+    let mut encoded = encoder(Flavor::Gif, BitOrder::Lsb, 8)
+        .build()
+        .encode(data)
+        .unwrap();
+    encoded.extend_from_slice(b"trailing");
+
+    let mut decoder = decoder(Flavor::Gif, BitOrder::Lsb, 8).build();
+    let mut reader = decoder.into_read(encoded.as_slice());
+    let mut decoded = vec![];
+    reader.read_to_end(&mut decoded).unwrap();
+    assert_eq!(decoded, data);
+    assert_eq!(reader.read(&mut [0; 8]).unwrap(), 0);
+    // The decoder may have consumed some bytes beyond the end code.
+    assert!(b"trailing".ends_with(reader.into_inner()));
+}
+
+#[test]
+fn decode_read_does_not_wait_after_end_code() {
+    /// A source that is never exhausted, like an open socket.
+    struct Open<'a>(&'a [u8]);
+
+    impl Read for Open<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = self.fill_buf()?.len().min(buf.len());
+            buf[..len].copy_from_slice(&self.0[..len]);
+            self.consume(len);
+            Ok(len)
+        }
+    }
+
+    impl BufRead for Open<'_> {
+        fn fill_buf(&mut self) -> io::Result<&[u8]> {
+            if self.0.is_empty() {
+                Err(io::ErrorKind::WouldBlock.into())
+            } else {
+                Ok(self.0)
+            }
+        }
+
+        fn consume(&mut self, amt: usize) {
+            self.0 = &self.0[amt..];
+        }
+    }
+
+    // Short streams are consumed by the decoder in one go, with the end code still buffered.
+    for data in [test_data(1 << 18), vec![], b"TOBEORNOT".to_vec()] {
+        let encoded = encoder(Flavor::Gif, BitOrder::Lsb, 8)
+            .build()
+            .encode(&data)
+            .unwrap();
+
+        for &chunk in &[1, 3, 4096] {
+            let mut decoder = decoder(Flavor::Gif, BitOrder::Lsb, 8).build();
+            let decoded = read_in_parts(decoder.into_read(Open(&encoded)), chunk);
+            assert!(data == decoded, "{} {}", data.len(), chunk);
+        }
+    }
+}
+
+#[test]
+fn decode_read_missing_end_code() {
+    let data = test_data(1 << 18);
+    let encoded = encoder(Flavor::Gif, BitOrder::Msb, 8)
+        .build()
+        .encode(&data)
+        .unwrap();
+
+    let mut decoder = decoder(Flavor::Gif, BitOrder::Msb, 8).build();
+    let mut decoded = vec![];
+    let err = decoder
+        .into_read(&encoded[..encoded.len() / 2])
+        .read_to_end(&mut decoded)
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert!(data.starts_with(&decoded));
+}
+
+#[test]
+fn decode_read_invalid_code() {
+    // A clear code followed by the (yet undefined) first free code.
+    let mut decoder = decode::Decoder::new(BitOrder::Msb, 8);
+    let err = decoder
+        .into_read(&[0x80, 0x40, 0x80, 0x00][..])
+        .read_to_end(&mut vec![])
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn decode_reader_owned() {
+    fn decompress(data: impl BufRead) -> impl Read {
+        decode::Reader::new(decoder(Flavor::Tiff, BitOrder::Msb, 8).build(), data)
+    }
+
+    let data = test_data(1 << 18);
+    let encoded = encoder(Flavor::Tiff, BitOrder::Msb, 8)
+        .build()
+        .encode(&data)
+        .unwrap();
+
+    let mut decoded = vec![];
+    decompress(encoded.as_slice())
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert!(data == decoded);
+}
+
+#[test]
+fn encode_read_all() {
+    let data = test_data(1 << 18);
+
+    for &flavor in &[Flavor::Gif, Flavor::Tiff] {
+        for &bit_order in &[BitOrder::Lsb, BitOrder::Msb] {
+            let expected = encoder(flavor, bit_order, 8).build().encode(&data).unwrap();
+
+            let mut encoder = encoder(flavor, bit_order, 8).build();
+            let mut encoded = vec![];
+            encoder
+                .into_read(data.as_slice())
+                .read_to_end(&mut encoded)
+                .unwrap();
+            assert!(expected == encoded, "{:?}, {:?}", flavor, bit_order);
+        }
+    }
+}
+
+#[test]
+fn encode_read_small_buffers() {
+    let data = test_data(1 << 18);
+    let expected = encoder(Flavor::Tiff, BitOrder::Lsb, 8)
+        .build()
+        .encode(&data)
+        .unwrap();
+
+    for &chunk in &[1, 3, 17, 4096] {
+        let mut encoder = encoder(Flavor::Tiff, BitOrder::Lsb, 8).build();
+        let encoded = read_in_parts(
+            encoder.into_read(io::BufReader::with_capacity(7, data.as_slice())),
+            chunk,
+        );
+        assert!(expected == encoded, "{}", chunk);
+    }
+}
+
+#[test]
+fn encode_read_invalid_byte() {
+    let mut encoder = encode::Encoder::new(BitOrder::Msb, 7);
+    let mut reader = encoder.into_read(&b"valid\x80"[..]);
+    let err = reader.read_to_end(&mut vec![]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(reader.into_inner(), b"\x80");
+}
@@ -0,0 +1,155 @@
+use std::io::{self, Read};
+use weezl::decode::{Configuration, Decoder};
+use weezl::{encode, BitOrder, LzwError, LzwStatus};
+
+/// Data that expands from very little input.
+fn bomb() -> (Vec<u8>, Vec<u8>) {
+    let data = vec![0; 1 << 20];
+    let encoded = encode::Encoder::new(BitOrder::Lsb, 8)
+        .encode(&data)
+        .unwrap();
+    (data, encoded)
+}
+
+fn decoder() -> Configuration {
+    Configuration::new(BitOrder::Lsb, 8)
+}
+
+#[test]
+fn output_limit_decode() {
+    let (data, encoded) = bomb();
+    let len = data.len() as u64;
+
+    let decoded = decoder()
+        .with_output_limit(len)
+        .build()
+        .decode(&encoded)
+        .unwrap();
+    assert!(decoded == data);
+
+    let err = decoder()
+        .with_output_limit(len - 1)
+        .build()
+        .decode(&encoded)
+        .unwrap_err();
+    assert_eq!(err, LzwError::LimitExceeded);
+}
+
+#[test]
+fn output_limit_into_vec() {
+    let (_, encoded) = bomb();
+
+    let mut decoder = decoder().with_output_limit(1000).build();
+    let mut decoded = vec![];
+    let result = decoder.into_vec(&mut decoded).decode_all(&encoded);
+    assert_eq!(result.status.unwrap_err(), LzwError::LimitExceeded);
+    assert_eq!(decoded.len(), 1000);
+    // The vector does not grow beyond the limit.
+    assert!(decoded.capacity() <= 1 << 12);
+
+    // The decoder refuses to continue.
+    let result = decoder.into_vec(&mut decoded).decode(&encoded);
+    assert_eq!(result.status.unwrap_err(), LzwError::LimitExceeded);
+    assert_eq!(result.consumed_in, 0);
+    assert_eq!(decoded.len(), 1000);
+}
+
+#[test]
+fn output_limit_decode_bytes() {
+    let (data, encoded) = bomb();
+    let mut decoder = decoder().with_output_limit(100).build();
+    let mut out = vec![0; 1 << 16];
+
+    let mut inp = &encoded[..];
+    let mut filled = 0;
+    let status = loop {
+        let result = decoder.decode_bytes(inp, &mut out[filled..]);
+        inp = &inp[result.consumed_in..];
+        filled += result.consumed_out;
+        match result.status {
+            Ok(LzwStatus::Ok) => assert!(filled <= 100),
+            other => break other,
+        }
+    };
+    assert_eq!(status.unwrap_err(), LzwError::LimitExceeded);
+    assert_eq!(filled, 100);
+    assert!(out[..100] == data[..100]);
+
+    let result = decoder.decode_bytes(inp, &mut out);
+    assert_eq!(result.status.unwrap_err(), LzwError::LimitExceeded);
+    assert_eq!(result.consumed_in, 0);
+    assert_eq!(result.consumed_out, 0);
+}
+
+#[test]
+fn output_limit_after_reset() {
+    let data = b"Hello, world";
+    let encoded = encode::Encoder::new(BitOrder::Lsb, 8).encode(data).unwrap();
+
+    let mut decoder = decoder().with_output_limit(12).build();
+    for _ in 0..3 {
+        assert_eq!(decoder.decode(&encoded).unwrap(), data);
+        decoder.reset();
+    }
+}
+
+#[test]
+fn output_limit_stream() {
+    let (_, encoded) = bomb();
+
+    let mut decoded = vec![];
+    let mut dec = decoder().with_output_limit(5000).build();
+    let result = dec.into_stream(&mut decoded).decode_all(&encoded[..]);
+    let err = result.status.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(decoded.len(), 5000);
+
+    let mut decoded = vec![];
+    let err = decoder()
+        .with_output_limit(5000)
+        .build()
+        .into_read(&encoded[..])
+        .read_to_end(&mut decoded)
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(decoded.len(), 5000);
+}
+
+#[test]
+fn expansion_limit() {
+    let (data, encoded) = bomb();
+    let ratio = data.len() / encoded.len();
+
+    let decoded = decoder()
+        .with_expansion_limit(ratio as u32 + 1)
+        .build()
+        .decode(&encoded)
+        .unwrap();
+    assert!(decoded == data);
+
+    let err = decoder()
+        .with_expansion_limit(ratio as u32 / 2)
+        .build()
+        .decode(&encoded)
+        .unwrap_err();
+    assert_eq!(err, LzwError::LimitExceeded);
+
+    // Ordinary data is far from any sensible limit.
+    let text = b"TOBEORNOTTOBEORTOBEORNOT".repeat(100);
+    let encoded = encode::Encoder::new(BitOrder::Lsb, 8)
+        .encode(&text)
+        .unwrap();
+    let decoded = decoder()
+        .with_expansion_limit(100)
+        .build()
+        .decode(&encoded)
+        .unwrap();
+    assert_eq!(decoded, text);
+}
+
+#[test]
+fn no_limit_by_default() {
+    let (data, encoded) = bomb();
+    let decoded = Decoder::new(BitOrder::Lsb, 8).decode(&encoded).unwrap();
+    assert!(decoded == data);
+}
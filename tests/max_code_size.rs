@@ -0,0 +1,124 @@
+use weezl::decode::TokenKind;
+use weezl::{decode, encode, BitOrder};
+
+mod common;
+use common::{decoder, encoder, test_data, Flavor};
+
+#[test]
+fn codes_grow_to_max_code_size() {
+    let data = test_data(1 << 19);
+
+    for &order in &[BitOrder::Lsb, BitOrder::Msb] {
+        for &flavor in &[Flavor::Gif, Flavor::Tiff] {
+            for max_code_size in 9..=16 {
+                assert_code_sizes(&data, flavor, order, max_code_size);
+            }
+        }
+    }
+}
+
+#[test]
+fn default_is_12_bits() {
+    let data = test_data(1 << 19);
+
+    for &order in &[BitOrder::Lsb, BitOrder::Msb] {
+        let default = encode::Encoder::new(order, 8).encode(&data).unwrap();
+        let explicit = encode::Configuration::new(order, 8)
+            .with_max_code_size(12)
+            .build()
+            .encode(&data)
+            .unwrap();
+        assert!(default == explicit, "{:?}", order);
+    }
+}
+
+#[test]
+#[should_panic]
+fn max_code_size_too_large() {
+    let _ = decode::Configuration::new(BitOrder::Msb, 8).with_max_code_size(17);
+}
+
+#[test]
+#[should_panic]
+fn max_code_size_below_symbol_size() {
+    let _ = encode::Configuration::new(BitOrder::Msb, 10).with_max_code_size(10);
+}
+
+#[test]
+fn default_max_code_size_with_symbol_size_12() {
+    let data: Vec<u8> = test_data(1 << 19)[..1 << 12].to_vec();
+
+    for &order in &[BitOrder::Lsb, BitOrder::Msb] {
+        let default = encode::Encoder::new(order, 12).encode(&data).unwrap();
+        let encoded = encode::Configuration::new(order, 12)
+            .with_max_code_size(12)
+            .build()
+            .encode(&data)
+            .unwrap();
+        assert!(default == encoded, "{:?}", order);
+
+        let decoded = decode::Configuration::new(order, 12)
+            .with_max_code_size(12)
+            .build()
+            .decode(&encoded)
+            .unwrap();
+        assert!(decoded == data, "{:?}", order);
+    }
+}
+
+#[test]
+fn small_decoder_does_not_accept_wider_codes() {
+    let data = test_data(1 << 19);
+
+    for &order in &[BitOrder::Lsb, BitOrder::Msb] {
+        let encoded = encode::Encoder::new(order, 8).encode(&data).unwrap();
+
+        for &max_code_size in &[9, 10, 11] {
+            let result = decode::Configuration::new(order, 8)
+                .with_max_code_size(max_code_size)
+                .build()
+                .decode(&encoded);
+            // Usually an `InvalidCode` error but misaligned codes may also be valid in a full table.
+            assert!(
+                !matches!(result, Ok(ref decoded) if *decoded == data),
+                "{:?}, {}",
+                order,
+                max_code_size
+            );
+        }
+    }
+}
+
+/// The codes reach the maximum size but never exceed it, and the full table is cleared.
+fn assert_code_sizes(data: &[u8], flavor: Flavor, order: BitOrder, max_code_size: u8) {
+    let (enc, dec) = (encoder(flavor, order, 8), decoder(flavor, order, 8));
+
+    let encoded = enc
+        .with_max_code_size(max_code_size)
+        .build()
+        .encode(data)
+        .unwrap();
+    let dec = dec.with_max_code_size(max_code_size);
+
+    // The code size at the start, with symbols of 8 bits.
+    let mut largest_size = 9;
+    let mut clears = 0;
+    for token in dec.clone().tokens(&encoded) {
+        match token.unwrap().kind {
+            TokenKind::CodeSize(size) => largest_size = largest_size.max(size),
+            TokenKind::Reference { code, .. } => assert!(u32::from(code) < 1 << max_code_size),
+            TokenKind::Clear => clears += 1,
+            _ => {}
+        }
+    }
+
+    let context = (flavor, order, max_code_size);
+    assert_eq!(largest_size, max_code_size, "{:?}", context);
+    // One clear at the start, the others after the table was full.
+    assert!(clears > 1, "{:?}", context);
+    assert!(
+        dec.build().decode(&encoded).unwrap() == data,
+        "{:?}",
+        context
+    );
+}
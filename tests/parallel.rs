@@ -0,0 +1,203 @@
+use weezl::encode::{parallel, ClearPolicy, Configuration};
+use weezl::{compress, decode, BitOrder, LzwError};
+
+mod common;
+use common::test_data;
+
+/// Configurations of matching encoders and decoders, with their symbol size.
+fn configurations() -> Vec<(u8, Configuration, decode::Configuration)> {
+    let mut configs = vec![];
+    for &order in &[BitOrder::Lsb, BitOrder::Msb] {
+        for &size in &[2, 5, 8] {
+            configs.push((
+                size,
+                Configuration::new(order, size),
+                decode::Configuration::new(order, size),
+            ));
+            configs.push((
+                size,
+                Configuration::with_tiff_size_switch(order, size),
+                decode::Configuration::with_tiff_size_switch(order, size),
+            ));
+        }
+
+        for &max in &[9, 16] {
+            configs.push((
+                8,
+                Configuration::new(order, 8).with_max_code_size(max),
+                decode::Configuration::new(order, 8).with_max_code_size(max),
+            ));
+        }
+
+        configs.push((
+            8,
+            Configuration::new(order, 8).with_clear_policy(ClearPolicy::Freeze),
+            decode::Configuration::new(order, 8),
+        ));
+    }
+    configs
+}
+
+#[test]
+fn single_chunk_is_sequential() {
+    for (size, encode, _) in configurations() {
+        let data = symbols(size);
+        let expected = encode.clone().build().encode(&data).unwrap();
+        let encoded = parallel::encode_chunks(&encode, &data, data.len()).unwrap();
+        assert!(encoded == expected, "{:?}", encode);
+    }
+}
+
+#[test]
+fn clear_code_between_chunks() {
+    for (size, encode, decode) in configurations() {
+        let data = symbols(size);
+
+        for &chunk_size in &[7, 1000, 5000, 20_000] {
+            let encoded = parallel::encode_chunks(&encode, &data, chunk_size).unwrap();
+            let offsets = segment_offsets(&decode, &encoded);
+
+            // Each chunk starts a segment, a chunk may be split further when its table is full.
+            for offset in (0..data.len() as u64).step_by(chunk_size) {
+                assert!(offsets.contains(&offset), "{:?} {}", encode, chunk_size);
+            }
+
+            let decoded = decode.clone().build().decode(&encoded).unwrap();
+            assert!(decoded == data, "{:?} {}", encode, chunk_size);
+        }
+    }
+
+    // Without clearing full tables, the chunks are exactly the segments.
+    let data = symbols(8);
+    for &order in &[BitOrder::Lsb, BitOrder::Msb] {
+        let encode = Configuration::new(order, 8).with_clear_policy(ClearPolicy::Freeze);
+        let encoded = parallel::encode_chunks(&encode, &data, 5000).unwrap();
+        let offsets = segment_offsets(&decode::Configuration::new(order, 8), &encoded);
+        let chunks: Vec<u64> = (0..data.len() as u64).step_by(5000).collect();
+        assert_eq!(offsets, chunks, "{:?}", order);
+    }
+}
+
+#[test]
+fn compress_chunks() {
+    let data = test_data(1 << 15);
+
+    for &max in &[9, 12, 16] {
+        let config = compress::Configuration::new().with_max_code_size(max);
+        let sequential = config.clone().build_encoder().encode(&data).unwrap();
+
+        // A single chunk is the stream of the sequential encoder.
+        let single = config.clone().encode_parallel(&data, data.len()).unwrap();
+        assert!(single == sequential, "{}", max);
+
+        // Several chunks are joined with clear codes, including the padding of their group.
+        for &chunk_size in &[5, 1000, 3000, 20_000] {
+            let encoded = config.clone().encode_parallel(&data, chunk_size).unwrap();
+            assert!(encoded != sequential, "{} {}", max, chunk_size);
+            let decoded = config.clone().build_decoder().decode(&encoded).unwrap();
+            assert!(decoded == data, "{} {}", max, chunk_size);
+        }
+    }
+}
+
+#[test]
+fn empty_input() {
+    let config = Configuration::new(BitOrder::Msb, 8);
+    let encoded = parallel::encode_chunks(&config, &[], 16).unwrap();
+    assert_eq!(encoded, config.build().encode(&[]).unwrap());
+}
+
+#[test]
+fn invalid_symbol() {
+    let mut data = vec![0; 10_000];
+    data[7777] = 4;
+
+    let config = Configuration::new(BitOrder::Lsb, 2);
+    match parallel::encode_chunks(&config, &data, 1000) {
+        Err(LzwError::InvalidSymbol { byte, offset, .. }) => assert_eq!((byte, offset), (4, 7777)),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn decode_segments() {
+    for (size, encode, decode) in configurations() {
+        let data = symbols(size);
+        let encoded = encode
+            .with_clear_policy(ClearPolicy::Interval(300))
+            .build()
+            .encode(&data)
+            .unwrap();
+
+        let segments = decode::parallel::scan(&decode, &encoded).unwrap();
+        assert!(segments.len() > 1);
+        let mut output = 0;
+        for segment in &segments {
+            assert_eq!(segment.output_offset, output);
+            assert!(segment.bit_offset < segment.bit_end);
+            output += segment.output_len;
+        }
+        assert_eq!(output, data.len() as u64);
+
+        let decoded = decode::parallel::decode(&decode, &encoded).unwrap();
+        assert!(decoded == data, "{:?}", decode);
+    }
+}
+
+#[test]
+fn decode_compress() {
+    let data = test_data(1 << 15);
+
+    for &max in &[9, 12, 16] {
+        let config = compress::Configuration::new().with_max_code_size(max);
+        let sequential = config.clone().build_encoder().encode(&data).unwrap();
+        let parallel = config.clone().encode_parallel(&data, 2000).unwrap();
+
+        for encoded in &[sequential, parallel] {
+            let decoded = config.clone().decode_parallel(encoded).unwrap();
+            assert!(decoded == data, "{}", max);
+        }
+    }
+}
+
+#[test]
+fn decode_same_as_sequential() {
+    let data = test_data(1 << 15);
+    let config = Configuration::new(BitOrder::Msb, 8).with_clear_policy(ClearPolicy::Interval(100));
+    let encoded = config.build().encode(&data).unwrap();
+    let decode = decode::Configuration::new(BitOrder::Msb, 8);
+
+    let mut corrupt = encoded.clone();
+    corrupt[encoded.len() / 2] ^= 0xff;
+    let truncated = &encoded[..encoded.len() / 2];
+    let trailing = [&encoded[..], &[0xff; 8]].concat();
+
+    for stream in &[&corrupt[..], truncated, &trailing] {
+        let expected = decode.clone().build().decode(stream);
+        assert_eq!(decode::parallel::decode(&decode, stream), expected);
+    }
+
+    let compress = compress::Configuration::new();
+    let mut corrupt = compress.clone().build_encoder().encode(&data).unwrap();
+    let len = corrupt.len();
+    corrupt[len / 2] ^= 0xff;
+    for stream in &[&corrupt[..], &corrupt[..2], b"\x1f\x9d\x00"] {
+        let expected = compress.clone().build_decoder().decode(stream);
+        assert_eq!(compress.clone().decode_parallel(stream), expected);
+    }
+}
+
+/// Where each segment between clear codes starts in the decoded data.
+fn segment_offsets(decode: &decode::Configuration, encoded: &[u8]) -> Vec<u64> {
+    decode::parallel::scan(decode, encoded)
+        .unwrap()
+        .iter()
+        .map(|segment| segment.output_offset)
+        .collect()
+}
+
+/// Test data with symbols of the given size.
+fn symbols(size: u8) -> Vec<u8> {
+    let mask = if size >= 8 { 0xff } else { (1u8 << size) - 1 };
+    test_data(1 << 15).into_iter().map(|b| b & mask).collect()
+}
@@ -0,0 +1,204 @@
+use std::io::{self, Read, Write};
+use weezl::pdf::{Parameters, Reader, Writer};
+use weezl::{encode::Encoder, BitOrder};
+
+mod common;
+use common::test_data;
+
+fn encode(parameters: &Parameters, data: &[u8]) -> Vec<u8> {
+    let mut writer = Writer::new(vec![], parameters).unwrap();
+    // Odd chunks so rows are assembled from several writes.
+    for chunk in data.chunks(77) {
+        writer.write_all(chunk).unwrap();
+    }
+    writer.finish().unwrap()
+}
+
+fn decode(parameters: &Parameters, data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut decoded = vec![];
+    Reader::new(data, parameters)?.read_to_end(&mut decoded)?;
+    Ok(decoded)
+}
+
+/// Check the layout of the rows that a predictor produced from `data`.
+fn assert_filtered(parameters: &Parameters, data: &[u8], filtered: &[u8]) {
+    let pixel_bits = usize::from(parameters.colors) * usize::from(parameters.bits_per_component);
+    let row = (pixel_bits * parameters.columns as usize).div_ceil(8);
+
+    match parameters.predictor {
+        1 => assert!(filtered == data, "{:?}", parameters),
+        2 => {
+            // The first whole bytes of each row are not preceded by any sample.
+            assert_eq!(filtered.len(), data.len(), "{:?}", parameters);
+            let first = pixel_bits / 8;
+            for (filtered, data) in filtered.chunks(row).zip(data.chunks(row)) {
+                let first = first.min(data.len());
+                assert_eq!(filtered[..first], data[..first], "{:?}", parameters);
+            }
+        }
+        predictor => {
+            // Each row gains the byte of its filter type.
+            let rows = data.len().div_ceil(row);
+            assert_eq!(filtered.len(), data.len() + rows, "{:?}", parameters);
+            for filtered in filtered.chunks(row + 1) {
+                match predictor {
+                    15 => assert!(filtered[0] <= 4, "{:?}", parameters),
+                    _ => assert_eq!(filtered[0], predictor - 10, "{:?}", parameters),
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn specification_example() {
+    // The example of the `LZWDecode` filter in the PDF reference.
+    let encoded = b"\x80\x0b\x60\x50\x22\x0c\x0c\x85\x01";
+    let decoded = decode(&Parameters::default(), encoded).unwrap();
+    assert_eq!(decoded, b"-----A---B");
+}
+
+#[test]
+fn filtered_rows() {
+    let data = test_data(1 << 13);
+
+    for &predictor in &[1, 2, 10, 11, 12, 13, 14, 15] {
+        for &bits_per_component in &[1, 2, 4, 8, 16] {
+            for &(colors, columns) in &[(1, 1), (1, 100), (3, 33), (4, 17)] {
+                for &early_change in &[false, true] {
+                    let parameters = Parameters {
+                        early_change,
+                        predictor,
+                        colors,
+                        bits_per_component,
+                        columns,
+                    };
+
+                    let encoded = encode(&parameters, &data);
+                    // The codes hold the rows as filtered by the predictor.
+                    let filtered = parameters.decoder().decode(&encoded).unwrap();
+                    assert_filtered(&parameters, &data, &filtered);
+
+                    let decoded = decode(&parameters, &encoded).unwrap();
+                    assert!(decoded == data, "{:?}", parameters);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn tiff_predictor() {
+    let parameters = Parameters {
+        predictor: 2,
+        colors: 2,
+        bits_per_component: 16,
+        columns: 3,
+        ..Parameters::default()
+    };
+
+    // Two rows of three pixels, the second row is incomplete.
+    let image: &[u16] = &[100, 7, 90, 7, 95, 8, 1, 0xffff, 3];
+    let differences: &[u16] = &[100, 7, 0xfff6, 0, 5, 1, 1, 0xffff, 2];
+
+    let bytes = |samples: &[u16]| -> Vec<u8> {
+        samples
+            .iter()
+            .flat_map(|sample| sample.to_be_bytes())
+            .collect()
+    };
+
+    let encoded = Encoder::with_tiff_size_switch(BitOrder::Msb, 8)
+        .encode(&bytes(differences))
+        .unwrap();
+    assert_eq!(decode(&parameters, &encoded).unwrap(), bytes(image));
+    assert_eq!(encode(&parameters, &bytes(image)), encoded);
+
+    // Samples of less than a byte, packed from the high bits.
+    let parameters = Parameters {
+        bits_per_component: 4,
+        colors: 1,
+        columns: 4,
+        ..parameters
+    };
+    let encoded = Encoder::with_tiff_size_switch(BitOrder::Msb, 8)
+        .encode(&[0x1f, 0x1f, 0x23])
+        .unwrap();
+    assert_eq!(decode(&parameters, &encoded).unwrap(), [0x10, 0x10, 0x25]);
+}
+
+#[test]
+fn png_predictor() {
+    let parameters = Parameters {
+        predictor: 15,
+        colors: 1,
+        bits_per_component: 8,
+        columns: 4,
+        ..Parameters::default()
+    };
+
+    // Each row names its own filter type, regardless of the predictor value.
+    let filtered = [
+        0, 1, 2, 3, 4, // None
+        1, 1, 1, 1, 1, // Sub
+        2, 1, 1, 1, 1, // Up
+        3, 2, 2, 2, 2, // Average
+        4, 0, 0, 0, 0, // Paeth
+    ];
+    let image = [
+        1, 2, 3, 4, //
+        1, 2, 3, 4, //
+        2, 3, 4, 5, //
+        3, 5, 6, 7, //
+        3, 5, 6, 7, //
+    ];
+
+    let encoded = Encoder::with_tiff_size_switch(BitOrder::Msb, 8)
+        .encode(&filtered)
+        .unwrap();
+    assert_eq!(decode(&parameters, &encoded).unwrap(), image);
+}
+
+#[test]
+fn invalid_filter_type() {
+    let parameters = Parameters {
+        predictor: 10,
+        columns: 2,
+        ..Parameters::default()
+    };
+
+    let encoded = Encoder::with_tiff_size_switch(BitOrder::Msb, 8)
+        .encode(&[0, 1, 2, 5, 1, 2])
+        .unwrap();
+    let err = decode(&parameters, &encoded).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn invalid_parameters() {
+    let invalid = [
+        Parameters {
+            predictor: 3,
+            ..Parameters::default()
+        },
+        Parameters {
+            bits_per_component: 3,
+            ..Parameters::default()
+        },
+        Parameters {
+            colors: 0,
+            ..Parameters::default()
+        },
+        Parameters {
+            columns: 0,
+            ..Parameters::default()
+        },
+    ];
+
+    for parameters in &invalid {
+        let err = Reader::new(&b""[..], parameters).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = Writer::new(vec![], parameters).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
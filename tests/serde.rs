@@ -0,0 +1,169 @@
+use weezl::decode::{Configuration, Decoder, TableStrategy};
+use weezl::{compress, encode, BitOrder, LzwStatus};
+
+mod common;
+use common::test_data;
+
+const STRATEGIES: &[TableStrategy] = &[
+    TableStrategy::Classic,
+    TableStrategy::Chunked,
+    TableStrategy::Streaming,
+];
+
+#[test]
+fn resume_after_roundtrip() {
+    let data = test_data(1 << 15);
+
+    for &tiff in &[false, true] {
+        for &order in &[BitOrder::Lsb, BitOrder::Msb] {
+            let (encoder, config) = if tiff {
+                (
+                    encode::Configuration::with_tiff_size_switch(order, 8),
+                    Configuration::with_tiff_size_switch(order, 8),
+                )
+            } else {
+                (
+                    encode::Configuration::new(order, 8),
+                    Configuration::new(order, 8),
+                )
+            };
+            let encoded = encoder.build().encode(&data).unwrap();
+
+            for &strategy in STRATEGIES {
+                for &do_yield in &[false, true] {
+                    let config = config
+                        .clone()
+                        .with_table_strategy(strategy)
+                        .with_yield_on_full_buffer(do_yield);
+                    let decoded = decode_with_snapshots(config, &encoded);
+                    assert!(
+                        decoded == data,
+                        "{} {:?} {:?} {}",
+                        tiff,
+                        order,
+                        strategy,
+                        do_yield
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn fresh_and_finished_decoders() {
+    let encoded = encode::Encoder::new(BitOrder::Msb, 8)
+        .encode(b"TOBEORNOTTOBEORTOBEORNOT")
+        .unwrap();
+
+    let fresh = Decoder::new(BitOrder::Msb, 8);
+    let mut restored = roundtrip(&fresh);
+    assert_eq!(
+        restored.decode(&encoded).unwrap(),
+        b"TOBEORNOTTOBEORTOBEORNOT"
+    );
+
+    // The end code was seen, which is part of the state.
+    let restored = roundtrip(&restored);
+    assert!(restored.has_ended());
+}
+
+#[test]
+fn symbol_size_12() {
+    // The default maximum code size of 12 is accepted for this symbol size too.
+    let data = test_data(1 << 12);
+
+    for &order in &[BitOrder::Lsb, BitOrder::Msb] {
+        let encoded = encode::Encoder::new(order, 12).encode(&data).unwrap();
+        let fresh = roundtrip(&Decoder::new(order, 12));
+        assert!(
+            fresh.clone().decode(&encoded).unwrap() == data,
+            "{:?}",
+            order
+        );
+
+        for &strategy in STRATEGIES {
+            let config = Configuration::new(order, 12).with_table_strategy(strategy);
+            let decoded = decode_with_snapshots(config, &encoded);
+            assert!(decoded == data, "{:?} {:?}", order, strategy);
+        }
+    }
+}
+
+#[test]
+fn compress_is_not_serializable() {
+    let decoder = compress::Configuration::new().build_decoder();
+    assert!(serde_json::to_string(&decoder).is_err());
+}
+
+#[test]
+fn reject_inconsistent_state() {
+    let encoded = encode::Encoder::new(BitOrder::Lsb, 8)
+        .encode(b"TOBEORNOTTOBEORTOBEORNOT")
+        .unwrap();
+
+    for &strategy in STRATEGIES {
+        let mut decoder = Configuration::new(BitOrder::Lsb, 8)
+            .with_table_strategy(strategy)
+            .build();
+        let mut out = [0; 16];
+        let mut consumed = 0;
+        for _ in 0..2 {
+            let result = decoder.decode_bytes(&encoded[consumed..], &mut out);
+            consumed += result.consumed_in;
+        }
+
+        let mut json: serde_json::Value = serde_json::to_value(&decoder).unwrap();
+        let state = &mut json["state"];
+        assert!(!state["table"].as_array().unwrap().is_empty());
+
+        let mut corrupt = json.clone();
+        corrupt["state"]["table"][0]["depth"] = 1000.into();
+        assert!(serde_json::from_value::<Decoder>(corrupt).is_err());
+
+        let mut corrupt = json.clone();
+        corrupt["state"]["table"][0]["prev"] = 4000.into();
+        assert!(serde_json::from_value::<Decoder>(corrupt).is_err());
+
+        let mut corrupt = json.clone();
+        corrupt["state"]["bits"]["code_size"] = 17.into();
+        assert!(serde_json::from_value::<Decoder>(corrupt).is_err());
+
+        let mut corrupt = json.clone();
+        corrupt["max_code_size"] = 8.into();
+        assert!(serde_json::from_value::<Decoder>(corrupt).is_err());
+
+        assert!(serde_json::from_value::<Decoder>(json).is_ok());
+    }
+}
+
+fn roundtrip(decoder: &Decoder) -> Decoder {
+    let json = serde_json::to_string(decoder).unwrap();
+    serde_json::from_str(&json).unwrap()
+}
+
+/// Decode with small buffers, continuing with a deserialized copy after each call.
+fn decode_with_snapshots(config: Configuration, mut encoded: &[u8]) -> Vec<u8> {
+    let mut decoder = config.build();
+    let mut decoded = vec![];
+    let mut out = vec![0; 1000];
+    let mut calls = 0;
+
+    loop {
+        let result = decoder.decode_bytes(&encoded[..encoded.len().min(300)], &mut out);
+        encoded = &encoded[result.consumed_in..];
+        decoded.extend_from_slice(&out[..result.consumed_out]);
+        match result.status.unwrap() {
+            LzwStatus::Done => break,
+            LzwStatus::NoProgress => panic!("Decoding did not finish"),
+            LzwStatus::Ok => {}
+        }
+
+        calls += 1;
+        if calls % 3 == 0 {
+            decoder = roundtrip(&decoder);
+        }
+    }
+
+    decoded
+}
@@ -0,0 +1,231 @@
+use weezl::decode::{Configuration, TableStrategy, TokenKind};
+use weezl::{BitOrder, LzwStatus};
+
+mod common;
+use common::{decode_in_parts, decoder, encoder, test_data, Flavor};
+
+const STRATEGIES: &[TableStrategy] = &[
+    TableStrategy::Classic,
+    TableStrategy::Chunked,
+    TableStrategy::Streaming,
+];
+
+#[test]
+fn progress_of_each_strategy() {
+    let data = test_data(1 << 15);
+
+    for &flavor in &[Flavor::Gif, Flavor::Tiff] {
+        for &order in &[BitOrder::Lsb, BitOrder::Msb] {
+            for &max_code_size in &[9, 12, 16] {
+                let encoded = encoder(flavor, order, 8)
+                    .with_max_code_size(max_code_size)
+                    .build()
+                    .encode(&data)
+                    .unwrap();
+
+                for &do_yield in &[false, true] {
+                    let progress = |strategy| {
+                        let config = decoder(flavor, order, 8)
+                            .with_max_code_size(max_code_size)
+                            .with_table_strategy(strategy)
+                            .with_yield_on_full_buffer(do_yield);
+                        progress_in_parts(config, &encoded, 100)
+                    };
+
+                    let context = (flavor, order, max_code_size, do_yield);
+                    let classic = progress(TableStrategy::Classic);
+                    assert!(classic.0 == data, "{:?}", context);
+
+                    // Only the layout of the table differs.
+                    assert!(progress(TableStrategy::Chunked) == classic, "{:?}", context);
+
+                    // Words that do not fit are held back, so every buffer is filled.
+                    let (decoded, calls) = progress(TableStrategy::Streaming);
+                    assert!(decoded == data, "{:?}", context);
+                    let before_last = &calls[..calls.len() - 1];
+                    assert!(
+                        before_last.iter().all(|&(len, _)| len == 100),
+                        "{:?}",
+                        context
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn words_longer_than_entries() {
+    // A symbol size of 12 leaves no room in the table for any words.
+    for size in 2..=11u8 {
+        // Long runs of each symbol, so words grow well beyond the eight bytes of an entry.
+        let data: Vec<u8> = (0..1u32 << 14)
+            .map(|i| ((i / 97) % (1 << size.min(8))) as u8)
+            .collect();
+
+        for &flavor in &[Flavor::Gif, Flavor::Tiff] {
+            let encoded = encoder(flavor, BitOrder::Lsb, size)
+                .build()
+                .encode(&data)
+                .unwrap();
+            let longest = decoder(flavor, BitOrder::Lsb, size)
+                .tokens(&encoded)
+                .filter_map(|token| match token.unwrap().kind {
+                    TokenKind::Reference { len, .. } => Some(len),
+                    _ => None,
+                })
+                .max();
+            assert!(longest > Some(8), "{} {:?}", size, flavor);
+
+            for &strategy in STRATEGIES {
+                for &out_size in &[7, 8, 9, 4096] {
+                    let config = decoder(flavor, BitOrder::Lsb, size).with_table_strategy(strategy);
+                    let decoded = decode_in_parts(config, &encoded, out_size);
+                    assert!(
+                        data == decoded,
+                        "{} {:?} {:?} {}",
+                        size,
+                        flavor,
+                        strategy,
+                        out_size
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn small_output_buffers() {
+    let data = test_data(1 << 15);
+
+    for &flavor in &[Flavor::Gif, Flavor::Tiff] {
+        let encoded = encoder(flavor, BitOrder::Msb, 8)
+            .build()
+            .encode(&data)
+            .unwrap();
+
+        for &strategy in STRATEGIES {
+            for &do_yield in &[false, true] {
+                for &size in &[1, 2, 7, 9, 64] {
+                    let config = decoder(flavor, BitOrder::Msb, 8)
+                        .with_table_strategy(strategy)
+                        .with_yield_on_full_buffer(do_yield);
+                    let decoded = decode_in_parts(config, &encoded, size);
+                    assert!(
+                        data == decoded,
+                        "{:?} {:?} {} {}",
+                        flavor,
+                        strategy,
+                        do_yield,
+                        size
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn yield_stops_at_full_buffer() {
+    // Garbage follows the first codes, as when the expected length is known out-of-band.
+    let mut encoded = encoder(Flavor::Tiff, BitOrder::Msb, 8)
+        .build()
+        .encode(b"TOBEORNOT")
+        .unwrap();
+    encoded[7..].iter_mut().for_each(|b| *b = 0xff);
+
+    for &strategy in STRATEGIES {
+        let mut decoder = decoder(Flavor::Tiff, BitOrder::Msb, 8)
+            .with_table_strategy(strategy)
+            .with_yield_on_full_buffer(true)
+            .build();
+        let mut out = [0; 4];
+        let mut inp = &encoded[..];
+        let mut filled = 0;
+        // The decoder may return before writing anything, but must not read the garbage.
+        for _ in 0..4 {
+            let result = decoder.decode_bytes(inp, &mut out[filled..]);
+            result.status.unwrap();
+            inp = &inp[result.consumed_in..];
+            filled += result.consumed_out;
+        }
+        assert_eq!(filled, 4, "{:?}", strategy);
+        assert_eq!(&out, b"TOBE");
+    }
+}
+
+#[test]
+fn corrupt_streams_agree() {
+    let data = test_data(1 << 15);
+    let encoded = encoder(Flavor::Gif, BitOrder::Lsb, 8)
+        .build()
+        .encode(&data[..1 << 12])
+        .unwrap();
+
+    for i in 0..200 {
+        let mut corrupt = encoded.clone();
+        let at = (i * 7919) % corrupt.len();
+        corrupt[at] ^= 1 << (i % 8);
+
+        let results: Vec<_> = STRATEGIES
+            .iter()
+            .map(|&strategy| {
+                decoder(Flavor::Gif, BitOrder::Lsb, 8)
+                    .with_table_strategy(strategy)
+                    .build()
+                    .decode(&corrupt)
+            })
+            .collect();
+
+        assert_eq!(results[0], results[1], "{}", i);
+        assert_eq!(results[1], results[2], "{}", i);
+    }
+}
+
+#[test]
+fn reset_between_streams() {
+    let data = b"Hello, world. Hello, world.";
+    let encoded = encoder(Flavor::Gif, BitOrder::Lsb, 8)
+        .build()
+        .encode(data)
+        .unwrap();
+
+    for &strategy in STRATEGIES {
+        let mut decoder = decoder(Flavor::Gif, BitOrder::Lsb, 8)
+            .with_table_strategy(strategy)
+            .build();
+        for _ in 0..2 {
+            assert_eq!(decoder.decode(&encoded).unwrap(), data);
+            decoder.reset();
+        }
+    }
+}
+
+/// The decoded data and the output of each call, with whether it was the last one.
+fn progress_in_parts(
+    config: Configuration,
+    mut encoded: &[u8],
+    size: usize,
+) -> (Vec<u8>, Vec<(usize, bool)>) {
+    let mut decoder = config.build();
+    let mut decoded = vec![];
+    let mut calls = vec![];
+    let mut out = vec![0; size];
+
+    loop {
+        let result = decoder.decode_bytes(encoded, &mut out);
+        encoded = &encoded[result.consumed_in..];
+        decoded.extend_from_slice(&out[..result.consumed_out]);
+        let done = match result.status.unwrap() {
+            LzwStatus::Done => true,
+            LzwStatus::NoProgress => panic!("Decoding did not finish"),
+            LzwStatus::Ok => false,
+        };
+
+        calls.push((result.consumed_out, done));
+        if done {
+            break (decoded, calls);
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use weezl::tiff::{ByteOrder, Decoder, Encoder, Layout};
+use weezl::{decode, encode, BitOrder, LzwError};
+
+mod common;
+use common::test_data;
+
+/// The horizontal predictor as a separate pass over the whole strip.
+fn difference(
+    layout: &Layout,
+    samples: usize,
+    bits: u8,
+    order: ByteOrder,
+    strip: &[u8],
+) -> Vec<u8> {
+    let width = usize::from(bits / 8);
+    let read = |b: &[u8]| -> u32 {
+        let mut bytes = [0; 4];
+        match order {
+            ByteOrder::LittleEndian => {
+                bytes[..width].copy_from_slice(b);
+                u32::from_le_bytes(bytes)
+            }
+            ByteOrder::BigEndian => {
+                bytes[4 - width..].copy_from_slice(b);
+                u32::from_be_bytes(bytes)
+            }
+        }
+    };
+    let write = |b: &mut [u8], v: u32| match order {
+        ByteOrder::LittleEndian => b.copy_from_slice(&v.to_le_bytes()[..width]),
+        ByteOrder::BigEndian => b.copy_from_slice(&v.to_be_bytes()[4 - width..]),
+    };
+
+    let mut out = strip.to_vec();
+    for (orig, row) in strip
+        .chunks(layout.row_len())
+        .zip(out.chunks_mut(layout.row_len()))
+    {
+        let count = orig.len() / width;
+        for idx in samples..count {
+            let sample = read(&orig[idx * width..][..width]);
+            let left = read(&orig[(idx - samples) * width..][..width]);
+            write(&mut row[idx * width..][..width], sample.wrapping_sub(left));
+        }
+    }
+    out
+}
+
+fn layouts() -> Vec<(Layout, usize, u8, ByteOrder)> {
+    let mut layouts = vec![];
+    for &order in &[ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+        for &bits in &[8, 16, 32] {
+            for &(width, samples) in &[(1, 1), (100, 1), (33, 3), (17, 4), (5, 7)] {
+                let layout = Layout::new(width, samples, bits, order);
+                layouts.push((layout, usize::from(samples), bits, order));
+            }
+        }
+    }
+    layouts
+}
+
+#[test]
+fn decode_same_as_separate_pass() {
+    let data = test_data(1 << 14);
+    // Strips of full rows and one with a partial last row.
+    let strips = [&data[..], &data[..data.len() - 5]];
+
+    for (layout, samples, bits, order) in layouts() {
+        for strip in &strips {
+            let differences = difference(&layout, samples, bits, order, strip);
+            let encoded = encode::Encoder::with_tiff_size_switch(BitOrder::Msb, 8)
+                .encode(&differences)
+                .unwrap();
+
+            let mut decoded = vec![0; strip.len()];
+            let len = Decoder::new(layout)
+                .decode_strip(&encoded, &mut decoded)
+                .unwrap();
+            assert_eq!(len, encoded.len());
+            assert!(decoded == *strip, "Decoding failed for {:?}", layout);
+        }
+    }
+}
+
+#[test]
+fn encode_same_as_separate_pass() {
+    let data = test_data(1 << 14);
+    let strips = [&data[..], &data[..data.len() - 5], &[][..]];
+
+    for (layout, samples, bits, order) in layouts() {
+        for strip in &strips {
+            let differences = difference(&layout, samples, bits, order, strip);
+            let expected = encode::Encoder::with_tiff_size_switch(BitOrder::Msb, 8)
+                .encode(&differences)
+                .unwrap();
+
+            let encoded = Encoder::new(layout).encode_strip(strip).unwrap();
+            assert!(encoded == expected, "Encoding failed for {:?}", layout);
+        }
+    }
+}
+
+#[test]
+fn reuse_for_strips() {
+    let data = test_data(1 << 14);
+    let layout = Layout::new(64, 3, 8, ByteOrder::LittleEndian);
+    let mut encoder = Encoder::new(layout);
+    let mut decoder = Decoder::new(layout);
+
+    for strip in data.chunks(3 * layout.row_len()) {
+        let encoded = encoder.encode_strip(strip).unwrap();
+        let mut decoded = vec![0; strip.len()];
+        decoder.decode_strip(&encoded, &mut decoded).unwrap();
+        assert_eq!(decoded, strip);
+    }
+}
+
+#[test]
+fn truncated_strip() {
+    let data = test_data(1 << 14);
+    let layout = Layout::new(40, 2, 16, ByteOrder::BigEndian);
+    let encoded = Encoder::new(layout).encode_strip(&data).unwrap();
+
+    let mut decoded = vec![0; data.len()];
+    let result = Decoder::new(layout).decode_strip(&encoded[..encoded.len() / 2], &mut decoded);
+    assert_eq!(result, Err(LzwError::UnexpectedEof));
+
+    // All data decoded up to the end of the input has its predictor reverted.
+    let plain = decode::Configuration::with_tiff_size_switch(BitOrder::Msb, 8)
+        .with_end_marker(decode::EndMarker::AcceptMissing)
+        .build()
+        .decode(&encoded[..encoded.len() / 2])
+        .unwrap();
+    let len = plain.len() / 2 * 2;
+    assert_eq!(decoded[..len], data[..len]);
+}
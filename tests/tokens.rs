@@ -0,0 +1,149 @@
+use weezl::decode::{Configuration, Token, TokenKind};
+use weezl::{encode, BitOrder, LzwError};
+
+mod common;
+use common::test_data;
+
+/// The codes `clear, A, B, C, D` with 9 bits each and no end code.
+const MISSING_END: &[u8] = &[0x00, 0x83, 0x08, 0x19, 0x42, 0x04];
+
+fn tokens(config: Configuration, data: &[u8]) -> Result<Vec<Token>, LzwError> {
+    config.tokens(data).collect()
+}
+
+#[test]
+fn offsets_of_codes() {
+    let tokens = tokens(Configuration::new(BitOrder::Lsb, 8), MISSING_END).unwrap();
+    let kinds: Vec<_> = tokens.iter().map(|token| token.kind).collect();
+    let offsets: Vec<_> = tokens.iter().map(|token| token.bit_offset).collect();
+
+    assert_eq!(
+        kinds,
+        [
+            TokenKind::Clear,
+            TokenKind::Literal(b'A'.into()),
+            TokenKind::Literal(b'B'.into()),
+            TokenKind::Literal(b'C'.into()),
+            TokenKind::Literal(b'D'.into()),
+        ]
+    );
+    assert_eq!(offsets, [0, 9, 18, 27, 36]);
+}
+
+#[test]
+fn lengths_match_data() {
+    let data = test_data(1 << 15);
+
+    for &order in &[BitOrder::Lsb, BitOrder::Msb] {
+        for &tiff in &[false, true] {
+            let (encoder, decoder) = if tiff {
+                (
+                    encode::Configuration::with_tiff_size_switch(order, 8),
+                    Configuration::with_tiff_size_switch(order, 8),
+                )
+            } else {
+                (
+                    encode::Configuration::new(order, 8),
+                    Configuration::new(order, 8),
+                )
+            };
+
+            let encoded = encoder.build().encode(&data).unwrap();
+            let tokens = tokens(decoder, &encoded).unwrap();
+
+            let len: usize = tokens
+                .iter()
+                .map(|token| match token.kind {
+                    TokenKind::Literal(_) => 1,
+                    TokenKind::Reference { len, .. } => usize::from(len),
+                    _ => 0,
+                })
+                .sum();
+            assert_eq!(len, data.len(), "{:?} {}", order, tiff);
+            assert_eq!(tokens.last().unwrap().kind, TokenKind::End);
+
+            // The end code has at most 12 bits and less than a byte of padding follows.
+            let end = tokens.last().unwrap().bit_offset;
+            assert!(end + 20 > encoded.len() as u64 * 8);
+        }
+    }
+}
+
+#[test]
+fn code_size_changes() {
+    let data = test_data(1 << 15);
+    let encoded = encode::Encoder::new(BitOrder::Msb, 8)
+        .encode(&data)
+        .unwrap();
+    let tokens = tokens(Configuration::new(BitOrder::Msb, 8), &encoded).unwrap();
+
+    let mut code_size = 9;
+    let mut next_offset = 0;
+    for token in &tokens {
+        assert_eq!(token.bit_offset, next_offset, "{:?}", token);
+        match token.kind {
+            TokenKind::CodeSize(size) => code_size = size,
+            _ => next_offset += u64::from(code_size),
+        }
+    }
+
+    let sizes: Vec<_> = tokens
+        .iter()
+        .filter_map(|token| match token.kind {
+            TokenKind::CodeSize(size) => Some(size),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(sizes[..3], [10, 11, 12]);
+    // Clearing the full table resets the code size.
+    assert!(sizes.contains(&9));
+}
+
+#[test]
+fn errors_match_decoder() {
+    let data = test_data(1 << 15);
+    let encoded = encode::Encoder::new(BitOrder::Lsb, 8)
+        .encode(&data[..1 << 12])
+        .unwrap();
+
+    for i in 0..200 {
+        let mut corrupt = encoded.clone();
+        let at = (i * 7919) % corrupt.len();
+        corrupt[at] ^= 1 << (i % 8);
+
+        let config = Configuration::new(BitOrder::Lsb, 8);
+        let decoded = config.clone().build().decode(&corrupt);
+        let tokens = tokens(config, &corrupt);
+
+        match (decoded, tokens) {
+            (Err(LzwError::UnexpectedEof), Ok(tokens)) => {
+                assert_ne!(tokens.last().unwrap().kind, TokenKind::End, "{}", i);
+            }
+            (Err(err), Err(token_err)) => assert_eq!(err, token_err, "{}", i),
+            (Ok(decoded), Ok(tokens)) => {
+                assert_eq!(tokens.last().unwrap().kind, TokenKind::End, "{}", i);
+                let len: usize = tokens
+                    .iter()
+                    .map(|token| match token.kind {
+                        TokenKind::Literal(_) => 1,
+                        TokenKind::Reference { len, .. } => usize::from(len),
+                        _ => 0,
+                    })
+                    .sum();
+                assert_eq!(len, decoded.len(), "{}", i);
+            }
+            (decoded, tokens) => panic!("{}: {:?} {:?}", i, decoded, tokens),
+        }
+    }
+}
+
+#[test]
+fn stops_after_end() {
+    let mut encoded = encode::Encoder::new(BitOrder::Lsb, 8)
+        .encode(b"Hello, world")
+        .unwrap();
+    encoded.extend_from_slice(&[0xff; 8]);
+
+    let tokens = tokens(Configuration::new(BitOrder::Lsb, 8), &encoded).unwrap();
+    assert_eq!(tokens.last().unwrap().kind, TokenKind::End);
+}
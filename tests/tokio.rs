@@ -0,0 +1,97 @@
+use std::{env, fs};
+use tokio::io::{AsyncReadExt as _, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use weezl::{decode, encode, BitOrder};
+
+async fn pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("localhost:0")
+        .await
+        .expect("No loop tcp for testing");
+    let addr = listener.local_addr().expect("No address for listener");
+
+    let connect = TcpStream::connect(addr);
+    let accept = listener.accept();
+
+    let (a, (b, _)) = tokio::try_join!(connect, accept).expect("Can connect");
+    (a, b)
+}
+
+async fn assert_send_through(data: &[u8], send: &mut TcpStream, recv: &mut TcpStream) {
+    let mut recv = BufReader::new(recv);
+
+    let mut encoder = encode::Encoder::new(BitOrder::Lsb, 8);
+    let encode = encoder.into_tokio_async(send).encode_all(data);
+
+    let mut recv_buffer = vec![];
+    let mut decoder = decode::Decoder::new(BitOrder::Lsb, 8);
+    let decode = decoder
+        .into_tokio_async(&mut recv_buffer)
+        .decode_all(&mut recv);
+
+    let (encode, decode) = tokio::join!(encode, decode);
+    encode.status.expect("Could send/encoded data");
+    decode.status.expect("Could recv/decode data");
+
+    assert_eq!(recv_buffer, data);
+}
+
+async fn assert_read_through(data: &[u8], send: &mut TcpStream, recv: &mut TcpStream) {
+    let mut encoder = encode::Encoder::new(BitOrder::Msb, 8);
+    let encode = async {
+        let mut reader = encoder.into_tokio_read(data);
+        tokio::io::copy(&mut reader, send).await
+    };
+
+    let mut recv_buffer = vec![];
+    let mut decoder = decode::Decoder::new(BitOrder::Msb, 8);
+    let mut reader = decoder.into_tokio_read(BufReader::new(recv));
+    let decode = reader.read_to_end(&mut recv_buffer);
+
+    let (encode, decode) = tokio::join!(encode, decode);
+    encode.expect("Could send/encoded data");
+    decode.expect("Could recv/decode data");
+
+    assert_eq!(recv_buffer, data);
+}
+
+#[test]
+fn with_streams() {
+    let file = env::args().next().unwrap();
+    let data = fs::read(file).unwrap();
+
+    let rt = tokio::runtime::Runtime::new().expect("runtime");
+    let _enter = rt.enter();
+
+    let (mut send, mut recv) = rt.block_on(pair());
+    rt.block_on(assert_send_through(&data, &mut send, &mut recv));
+}
+
+#[test]
+fn with_readers() {
+    let file = env::args().next().unwrap();
+    let data = fs::read(file).unwrap();
+
+    let rt = tokio::runtime::Runtime::new().expect("runtime");
+    let _enter = rt.enter();
+
+    let (mut send, mut recv) = rt.block_on(pair());
+    rt.block_on(assert_read_through(&data, &mut send, &mut recv));
+}
+
+#[test]
+fn read_missing_end_code() {
+    let data = b"Hello, world";
+    let encoded = encode::Encoder::new(BitOrder::Lsb, 8).encode(data).unwrap();
+
+    let rt = tokio::runtime::Runtime::new().expect("runtime");
+    let mut decoder = decode::Decoder::new(BitOrder::Lsb, 8);
+    let mut decoded = vec![];
+    let err = rt
+        .block_on(
+            decoder
+                .into_tokio_read(&encoded[..encoded.len() - 2])
+                .read_to_end(&mut decoded),
+        )
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
@@ -0,0 +1,94 @@
+use std::io::{self, Write};
+use weezl::{decode, encode, BitOrder};
+
+mod common;
+use common::test_data;
+
+#[test]
+fn encode_rows() {
+    let data = test_data(1 << 18);
+
+    for &bit_order in &[BitOrder::Lsb, BitOrder::Msb] {
+        let expected = encode::Encoder::new(bit_order, 8).encode(&data).unwrap();
+
+        for &row in &[1, 7, 1000, 1 << 17] {
+            let encoder = encode::Encoder::new(bit_order, 8);
+            let mut writer = encode::Writer::with_capacity(64, encoder, vec![]);
+            for chunk in data.chunks(row) {
+                writer.write_all(chunk).unwrap();
+            }
+            let encoded = writer.finish().unwrap();
+            assert!(expected == encoded, "{:?}, {}", bit_order, row);
+        }
+    }
+}
+
+#[test]
+fn encode_invalid_byte() {
+    let mut writer = encode::Writer::new(encode::Encoder::new(BitOrder::Msb, 7), vec![]);
+    assert_eq!(writer.write(b"valid\x80").unwrap(), 5);
+    let err = writer.write(b"\x80").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    let encoded = writer.finish().unwrap();
+    let decoded = decode::Decoder::new(BitOrder::Msb, 7)
+        .decode(&encoded)
+        .unwrap();
+    assert_eq!(decoded, b"valid");
+}
+
+#[test]
+fn decode_chunks() {
+    let data = test_data(1 << 18);
+
+    for &bit_order in &[BitOrder::Lsb, BitOrder::Msb] {
+        let encoded = encode::Encoder::with_tiff_size_switch(bit_order, 8)
+            .encode(&data)
+            .unwrap();
+
+        for &chunk in &[1, 7, 1000, 1 << 17] {
+            let decoder = decode::Decoder::with_tiff_size_switch(bit_order, 8);
+            let mut writer = decode::Writer::with_capacity(64, decoder, vec![]);
+            for chunk in encoded.chunks(chunk) {
+                writer.write_all(chunk).unwrap();
+            }
+            let decoded = writer.finish().unwrap();
+            assert!(data == decoded, "{:?}, {}", bit_order, chunk);
+        }
+    }
+}
+
+#[test]
+fn decode_ignores_trailing_data() {
+    let mut encoded = encode::Encoder::new(BitOrder::Lsb, 8)
+        .encode(b"Hello, world")
+        .unwrap();
+    encoded.extend_from_slice(&[0xff; 16]);
+
+    let mut writer = decode::Writer::new(decode::Decoder::new(BitOrder::Lsb, 8), vec![]);
+    writer.write_all(&encoded).unwrap();
+    writer.write_all(&[0xff; 16]).unwrap();
+    assert_eq!(writer.get_ref(), b"Hello, world");
+    assert_eq!(writer.finish().unwrap(), b"Hello, world");
+}
+
+#[test]
+fn decode_missing_end_code() {
+    let data = test_data(1 << 18);
+    let encoded = encode::Encoder::new(BitOrder::Msb, 8)
+        .encode(&data)
+        .unwrap();
+
+    let mut writer = decode::Writer::new(decode::Decoder::new(BitOrder::Msb, 8), vec![]);
+    writer.write_all(&encoded[..encoded.len() / 2]).unwrap();
+    let err = writer.finish().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn decode_invalid_code() {
+    // A clear code followed by the (yet undefined) first free code.
+    let mut writer = decode::Writer::new(decode::Decoder::new(BitOrder::Msb, 8), vec![]);
+    let err = writer.write_all(&[0x80, 0x40, 0x80, 0x00]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
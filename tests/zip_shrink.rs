@@ -0,0 +1,191 @@
+use weezl::{decode::CodeReader, encode::CodeWriter, zip_shrink, BitOrder, LzwError};
+
+mod common;
+use common::test_data;
+
+/// Pack codes, each with its code size.
+fn pack(codes: &[(u16, u8)]) -> Vec<u8> {
+    let mut writer = CodeWriter::new(BitOrder::Lsb);
+    for &(code, size) in codes {
+        writer.write_code(code, size);
+    }
+    writer.finish()
+}
+
+/// The final code size and the number of partial clears of a stream.
+///
+/// Also checks that the code size is only increased for a code that needs it.
+fn code_size_and_clears(data: &[u8]) -> (u8, usize) {
+    let mut reader = CodeReader::new(BitOrder::Lsb, data);
+    let (mut code_size, mut clears) = (9, 0);
+    let mut increased = false;
+
+    while let Some(code) = reader.read_code(code_size) {
+        if code != 256 {
+            assert!(!increased || code >> (code_size - 1) != 0);
+            increased = false;
+            continue;
+        }
+
+        match reader.read_code(code_size) {
+            Some(1) => {
+                code_size += 1;
+                increased = true;
+            }
+            Some(2) => clears += 1,
+            other => panic!("Unexpected sub-code {:?}", other),
+        }
+    }
+
+    (code_size, clears)
+}
+
+#[test]
+fn encoder_control_codes() {
+    let data = test_data(1 << 17);
+    // Pseudo-random bytes fill the dictionary quickly and need many partial clears.
+    let mut state = 1u32;
+    let noise: Vec<u8> = (0..1 << 16)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state >> 24) as u8 & 0x1f
+        })
+        .collect();
+
+    // The final code size of each input and whether its dictionary was ever partially cleared.
+    let cases: [(&[u8], _, _); 3] = [(&data, 13, true), (&noise, 13, true), (b"abc", 9, false)];
+    for (input, code_size, cleared) in cases {
+        let encoded = zip_shrink::encode(input);
+        let (max_size, clears) = code_size_and_clears(&encoded);
+        assert_eq!(max_size, code_size);
+        assert_eq!(clears > 0, cleared);
+        assert_eq!(zip_shrink::decode(&encoded).unwrap(), input);
+    }
+
+    assert!(zip_shrink::encode(b"").is_empty());
+}
+
+#[test]
+fn control_codes() {
+    let encoded = pack(&[
+        (b'a'.into(), 9),
+        (b'b'.into(), 9),
+        // Increase the code size.
+        (256, 9),
+        (1, 9),
+        // 257 is `ab`, the entry `ba` is assigned to 258.
+        (257, 10),
+        // Free 257 and 258, they are not the prefix of another code.
+        (256, 10),
+        (2, 10),
+        // The new entry 257 is `abc`, extending the freed code.
+        (b'c'.into(), 10),
+        (257, 10),
+        // 259 is assigned just now, as `abca`.
+        (259, 10),
+    ]);
+
+    assert_eq!(zip_shrink::decode(&encoded).unwrap(), b"ababcabcabca");
+}
+
+#[test]
+fn kwkwk_after_partial_clear() {
+    // A stream as stored in a ZIP member, packed by hand from these 9-bit codes:
+    // `a`, `b`, 257 (`ab`, and 258 is `ba`), 256 and 2 to free both 257 and 258, then 257, 257
+    // and 258. The first 257 after the clear is the code that is assigned next. Its entry extends
+    // the previous code, which is the freed 257 itself, so the new 257 is its own prefix: `aba`.
+    let shrunk = [0x61, 0xc4, 0x04, 0x04, 0x28, 0x20, 0x60, 0x40, 0x81];
+    let expected = b"abababaabaabaa";
+
+    assert_eq!(zip_shrink::decode(&shrunk).unwrap(), expected);
+    let mut unshrunk = [0; 14];
+    zip_shrink::decode_exact(&shrunk, &mut unshrunk).unwrap();
+    assert_eq!(&unshrunk, expected);
+}
+
+#[test]
+fn repeated_partial_clears() {
+    let shrunk = pack(&[
+        // 257 is `ab`, 258 `bc`, 259 `cd` and 260 `db`.
+        (b'a'.into(), 9),
+        (b'b'.into(), 9),
+        (b'c'.into(), 9),
+        (b'd'.into(), 9),
+        (258, 9),
+        // Free all of them, then 257 is `bce`, extending the freed 258.
+        (256, 9),
+        (2, 9),
+        (b'e'.into(), 9),
+        // Free 257 again. 258 is still free even though it is the prefix of 257.
+        (256, 9),
+        (2, 9),
+        // So 257 is `eg` and 258 is `gh`, as assigned by the lowest free codes.
+        (b'g'.into(), 9),
+        (b'h'.into(), 9),
+        (258, 9),
+        (257, 9),
+    ]);
+
+    assert_eq!(zip_shrink::decode(&shrunk).unwrap(), b"abcdbceghgheg");
+}
+
+#[test]
+fn exact_length() {
+    let data = test_data(1 << 17);
+    let encoded = zip_shrink::encode(&data);
+
+    // Codes beyond the length are ignored.
+    let mut decoded = vec![0; data.len() / 2];
+    zip_shrink::decode_exact(&encoded, &mut decoded).unwrap();
+    assert_eq!(decoded, data[..data.len() / 2]);
+
+    let mut decoded = vec![0; data.len() + 1];
+    let err = zip_shrink::decode_exact(&encoded, &mut decoded);
+    assert_eq!(err, Err(LzwError::UnexpectedEof));
+}
+
+#[test]
+fn invalid_codes() {
+    let invalid = [
+        // No entry before the first code.
+        (&[(257, 9)][..], 257),
+        // A code that was freed, 257 is assigned next.
+        (
+            &[
+                (b'a'.into(), 9),
+                (b'b'.into(), 9),
+                (257, 9),
+                (256, 9),
+                (2, 9),
+                (258, 9),
+            ][..],
+            258,
+        ),
+        // An unknown sub-code.
+        (&[(b'a'.into(), 9), (256, 9), (3, 9)][..], 3),
+    ];
+
+    for (codes, code) in invalid {
+        match zip_shrink::decode(&pack(codes)) {
+            Err(LzwError::InvalidCode(err)) => assert_eq!(err.code, code),
+            other => panic!("Unexpected result {:?} for {:?}", other, codes),
+        }
+    }
+
+    // The code size can not exceed 13 bits.
+    let mut codes = vec![];
+    for size in 9..13 {
+        codes.extend_from_slice(&[(256, size), (1, size)]);
+    }
+    codes.extend_from_slice(&[(256, 13), (1, 13)]);
+    assert!(matches!(
+        zip_shrink::decode(&pack(&codes)),
+        Err(LzwError::InvalidCode(_))
+    ));
+
+    // A control code without its sub-code.
+    let truncated = pack(&[(b'a'.into(), 9), (256, 9)]);
+    assert_eq!(zip_shrink::decode(&truncated), Err(LzwError::UnexpectedEof));
+}